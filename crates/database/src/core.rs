@@ -0,0 +1,282 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use std::rc::Rc;
+
+use apl_parser::SyntaxTree;
+use apl_source::{FileId, SourceMap};
+
+use super::query::{Dependency, Input, Memo, Query, Revision, Slot};
+
+/// The text of a file, kept parsed so an edit only rescans and reparses the
+/// part of it that changed.
+pub struct Source;
+
+impl Input for Source {
+    type Key = FileId;
+    type Value = SyntaxTree;
+}
+
+// The file registered under a name
+struct FileName;
+
+impl Input for FileName {
+    type Key = String;
+    type Value = FileId;
+}
+
+/// Inputs, and every query computed from them so far.
+///
+/// Queries are only computed when asked for and remembered along with what
+/// they read. After an input changes, a remembered query is reused as long
+/// as nothing it read changed, so an edit to one file only recomputes what
+/// depends on that file.
+pub struct Database {
+    revision: Revision,
+    source_map: SourceMap,
+    // A `HashMap<I::Key, Slot<I::Value>>` for every input `I`
+    inputs: HashMap<TypeId, Box<dyn Any>>,
+    // A `HashMap<Q::Key, Memo<Q::Value>>` for every query `Q`
+    memos: RefCell<HashMap<TypeId, Box<dyn Any>>>,
+    // What each query being computed has read so far, innermost last
+    active: RefCell<Vec<Vec<Dependency>>>,
+}
+
+impl Database {
+    pub fn new() -> Self {
+        Self {
+            revision: 0,
+            source_map: SourceMap::new(),
+            inputs: HashMap::new(),
+            memos: RefCell::new(HashMap::new()),
+            active: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// Every registered file, used to render diagnostics.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Registers a file under `name`, or replaces its text if it was already
+    /// registered. Setting the text a file already has changes nothing.
+    pub fn set_source(&mut self, name: impl Into<String>, source: String) -> FileId {
+        let name = name.into();
+
+        let Some(file) = self.slot::<FileName>(&name).map(|slot| *slot.value) else {
+            let file = self.source_map.add_file(name.clone(), source.clone());
+            self.set::<Source>(file, SyntaxTree::new(source, file));
+            self.set::<FileName>(name, file);
+            return file;
+        };
+
+        if self.source_map.source(file) != source {
+            let len = self.source_map.source(file).len();
+            self.source_map.edit(file, 0..len, &source);
+            self.set::<Source>(file, SyntaxTree::new(source, file));
+        }
+        file
+    }
+
+    /// Registers a file like [`Database::set_source`], but parses it as it's
+    /// scanned without keeping its tokens, for files that are read rather
+    /// than edited.
+    pub fn load_source(&mut self, name: impl Into<String>, source: String) -> FileId {
+        let file = self.register(name.into());
+        if self.source_map.source(file) != source || self.slot::<Source>(&file).is_none() {
+            self.set_tree(file, SyntaxTree::parse(source, file));
+        }
+        file
+    }
+
+    /// Registers a file like [`Database::load_source`], parsing what
+    /// `reader` reads as it's read, e.g. stdin.
+    pub fn read_source(
+        &mut self,
+        name: impl Into<String>,
+        reader: impl BufRead,
+    ) -> io::Result<FileId> {
+        let file = self.register(name.into());
+        let tree = SyntaxTree::read(reader, file)?;
+        self.set_tree(file, tree);
+        Ok(file)
+    }
+
+    // The file registered under `name`, registering it with no text and no
+    // tree yet if there isn't one
+    fn register(&mut self, name: String) -> FileId {
+        if let Some(slot) = self.slot::<FileName>(&name) {
+            return *slot.value;
+        }
+        let file = self.source_map.add_file(name.clone(), String::new());
+        self.set::<FileName>(name, file);
+        file
+    }
+
+    // Replaces a file's tree, and its text with the tree's
+    fn set_tree(&mut self, file: FileId, tree: SyntaxTree) {
+        let len = self.source_map.source(file).len();
+        self.source_map.edit(file, 0..len, tree.source());
+        self.set::<Source>(file, tree);
+    }
+
+    /// Replaces `range` of a file's text with `text`.
+    pub fn edit_source(&mut self, file: FileId, range: Range<usize>, text: &str) {
+        self.source_map.edit(file, range.clone(), text);
+        self.update::<Source>(&file, |tree| tree.edit(range, text));
+    }
+
+    /// The tokens and statements of a registered file.
+    pub fn syntax(&self, file: FileId) -> Rc<SyntaxTree> {
+        self.input::<Source>(&file)
+            .expect("every registered file has a source")
+    }
+
+    /// The file registered under `name`.
+    pub fn file(&self, name: &str) -> Option<FileId> {
+        self.input::<FileName>(&name.to_string()).map(|file| *file)
+    }
+
+    pub fn set<I: Input>(&mut self, key: I::Key, value: I::Value) {
+        self.revision += 1;
+        let slot = Slot {
+            value: Rc::new(value),
+            changed_at: self.revision,
+        };
+        self.inputs_mut::<I>().insert(key, slot);
+    }
+
+    /// Changes an input that was set before in place. It's only copied when
+    /// something still holds on to the old value.
+    pub fn update<I: Input>(&mut self, key: &I::Key, change: impl FnOnce(&mut I::Value))
+    where
+        I::Value: Clone,
+    {
+        self.revision += 1;
+        let revision = self.revision;
+
+        let slot = self
+            .inputs_mut::<I>()
+            .get_mut(key)
+            .expect("only inputs that were set can be updated");
+        change(Rc::make_mut(&mut slot.value));
+        slot.changed_at = revision;
+    }
+
+    /// Reads an input. Reading one that isn't set is recorded too, so the
+    /// query is recomputed once it is.
+    pub fn input<I: Input>(&self, key: &I::Key) -> Option<Rc<I::Value>> {
+        let read = key.clone();
+        self.record(Rc::new(move |db: &Database| {
+            db.slot::<I>(&read).map_or(0, |slot| slot.changed_at)
+        }));
+
+        self.slot::<I>(key).map(|slot| slot.value.clone())
+    }
+
+    /// The value of a query, computing it only if something it read changed
+    /// since it was last computed.
+    pub fn get<Q: Query>(&self, key: &Q::Key) -> Rc<Q::Value> {
+        self.refresh::<Q>(key);
+
+        let read = key.clone();
+        self.record(Rc::new(move |db: &Database| db.refresh::<Q>(&read)));
+
+        self.with_memos::<Q, _>(|memos| memos[key].value.clone())
+    }
+
+    // Brings the value of a query up to date, returning the revision it last
+    // changed in
+    fn refresh<Q: Query>(&self, key: &Q::Key) -> Revision {
+        let memo = self.with_memos::<Q, _>(|memos| {
+            memos
+                .get(key)
+                .map(|memo| (memo.verified_at, memo.changed_at, memo.dependencies.clone()))
+        });
+
+        if let Some((verified_at, changed_at, dependencies)) = &memo {
+            // Dependencies are checked in the order they were read, so ones
+            // read only because of an earlier value aren't brought up to date
+            // when that value changed
+            if *verified_at == self.revision
+                || dependencies.iter().all(|read| read(self) <= *verified_at)
+            {
+                self.with_memos::<Q, _>(|memos| {
+                    memos.get_mut(key).unwrap().verified_at = self.revision
+                });
+                return *changed_at;
+            }
+        }
+
+        self.active.borrow_mut().push(Vec::new());
+        let value = Q::execute(self, key);
+        let dependencies = self.active.borrow_mut().pop().unwrap_or_default();
+
+        self.with_memos::<Q, _>(|memos| {
+            // A value that came out the same is kept along with its revision,
+            // so what depends on it isn't recomputed
+            let (value, changed_at) = match memos.get(key) {
+                Some(old) if *old.value == value => (old.value.clone(), old.changed_at),
+                _ => (Rc::new(value), self.revision),
+            };
+            memos.insert(
+                key.clone(),
+                Memo {
+                    value,
+                    verified_at: self.revision,
+                    changed_at,
+                    dependencies: dependencies.into(),
+                },
+            );
+            changed_at
+        })
+    }
+
+    // Records a read by the query being computed, if any
+    fn record(&self, dependency: Dependency) {
+        if let Some(reads) = self.active.borrow_mut().last_mut() {
+            reads.push(dependency);
+        }
+    }
+
+    fn slot<I: Input>(&self, key: &I::Key) -> Option<&Slot<I::Value>> {
+        self.inputs
+            .get(&TypeId::of::<I>())?
+            .downcast_ref::<HashMap<I::Key, Slot<I::Value>>>()?
+            .get(key)
+    }
+
+    fn inputs_mut<I: Input>(&mut self) -> &mut HashMap<I::Key, Slot<I::Value>> {
+        self.inputs
+            .entry(TypeId::of::<I>())
+            .or_insert_with(|| Box::new(HashMap::<I::Key, Slot<I::Value>>::new()))
+            .downcast_mut()
+            .expect("inputs are stored by their type")
+    }
+
+    // The memos of query `Q`, which must not be used to compute anything
+    fn with_memos<Q: Query, R>(
+        &self,
+        f: impl FnOnce(&mut HashMap<Q::Key, Memo<Q::Value>>) -> R,
+    ) -> R {
+        let mut memos = self.memos.borrow_mut();
+        let memos = memos
+            .entry(TypeId::of::<Q>())
+            .or_insert_with(|| Box::new(HashMap::<Q::Key, Memo<Q::Value>>::new()))
+            .downcast_mut()
+            .expect("memos are stored by their query");
+        f(memos)
+    }
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self::new()
+    }
+}
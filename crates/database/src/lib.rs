@@ -0,0 +1,114 @@
+mod core;
+mod query;
+
+pub use core::{Database, Source};
+pub use query::{Input, Query, Revision};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apl_source::FileId;
+    use std::cell::Cell;
+
+    thread_local! {
+        static EXECUTED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn executed() -> usize {
+        EXECUTED.with(|executed| executed.replace(0))
+    }
+
+    // How many statements a file has
+    struct Statements;
+
+    impl Query for Statements {
+        type Key = FileId;
+        type Value = usize;
+
+        fn execute(db: &Database, file: &FileId) -> usize {
+            EXECUTED.with(|executed| executed.set(executed.get() + 1));
+            db.syntax(*file).statements().len()
+        }
+    }
+
+    // Statements in every file, which only depends on their counts
+    struct Total;
+
+    impl Query for Total {
+        type Key = Vec<String>;
+        type Value = usize;
+
+        fn execute(db: &Database, files: &Vec<String>) -> usize {
+            EXECUTED.with(|executed| executed.set(executed.get() + 1));
+            files
+                .iter()
+                .filter_map(|name| db.file(name))
+                .map(|file| *db.get::<Statements>(&file))
+                .sum()
+        }
+    }
+
+    #[test]
+    fn memoizes_queries() {
+        let mut db = Database::new();
+        let a = db.set_source("a.apl", "let x = 1;".to_string());
+        let files = vec!["a.apl".to_string(), "b.apl".to_string()];
+
+        assert_eq!(*db.get::<Total>(&files), 1);
+        assert_eq!(executed(), 2);
+        assert_eq!(*db.get::<Total>(&files), 1);
+        assert_eq!(executed(), 0);
+
+        // Setting the same text isn't a change
+        let revision = db.revision();
+        db.set_source("a.apl", "let x = 1;".to_string());
+        assert_eq!(db.revision(), revision);
+
+        // Files that appear later are picked up
+        db.set_source("b.apl", "let y = 2; let z = 3;".to_string());
+        assert_eq!(*db.get::<Total>(&files), 3);
+        assert_eq!(executed(), 2);
+
+        // Only the edited file is counted again, and the total is kept as
+        // the count came out the same
+        db.edit_source(a, 8..9, "42");
+        assert_eq!(db.source_map().source(a), "let x = 42;");
+        assert_eq!(*db.get::<Total>(&files), 3);
+        assert_eq!(executed(), 1);
+
+        db.edit_source(a, 11..11, " let w = x;");
+        assert_eq!(*db.get::<Total>(&files), 4);
+        assert_eq!(executed(), 2);
+    }
+
+    #[test]
+    fn inputs() {
+        struct Limit;
+
+        impl Input for Limit {
+            type Key = ();
+            type Value = usize;
+        }
+
+        struct Limited;
+
+        impl Query for Limited {
+            type Key = FileId;
+            type Value = bool;
+
+            fn execute(db: &Database, file: &FileId) -> bool {
+                let limit = db.input::<Limit>(&()).map_or(usize::MAX, |limit| *limit);
+                db.syntax(*file).statements().len() <= limit
+            }
+        }
+
+        let mut db = Database::new();
+        let file = db.set_source("main.apl", "1; 2; 3;".to_string());
+
+        assert!(*db.get::<Limited>(&file));
+        db.set::<Limit>((), 2);
+        assert!(!*db.get::<Limited>(&file));
+        db.update::<Limit>(&(), |limit| *limit += 1);
+        assert!(*db.get::<Limited>(&file));
+    }
+}
@@ -0,0 +1,48 @@
+use std::hash::Hash;
+use std::rc::Rc;
+
+use super::Database;
+
+/// Counts changes to the inputs of a database, every change starts a new
+/// revision.
+pub type Revision = u64;
+
+/// A value computed from the inputs of a database and other queries, e.g.
+/// the names a file resolves. Passes define their own queries in their own
+/// crates, and `Database::get` computes and memoizes them.
+///
+/// `execute` must only read the database through `Database::get`,
+/// `Database::input` and `Database::file`, so every dependency is recorded.
+/// A query must not depend on itself.
+pub trait Query: 'static {
+    type Key: Clone + Eq + Hash + 'static;
+    // Compared after recomputing, so when the value didn't change the
+    // queries depending on it don't have to be recomputed either
+    type Value: PartialEq + 'static;
+
+    fn execute(db: &Database, key: &Self::Key) -> Self::Value;
+}
+
+/// A value set from outside the database, e.g. the text of a file or the
+/// names of the builtins.
+pub trait Input: 'static {
+    type Key: Clone + Eq + Hash + 'static;
+    type Value: 'static;
+}
+
+// Brings a value some query read up to date, returning the revision it last
+// changed in
+pub(crate) type Dependency = Rc<dyn Fn(&Database) -> Revision>;
+
+pub(crate) struct Memo<V> {
+    pub value: Rc<V>,
+    // The latest revision the value is known to be up to date in
+    pub verified_at: Revision,
+    pub changed_at: Revision,
+    pub dependencies: Rc<[Dependency]>,
+}
+
+pub(crate) struct Slot<V> {
+    pub value: Rc<V>,
+    pub changed_at: Revision,
+}
@@ -0,0 +1,128 @@
+use crate::lexerror::LexErrorType;
+use crate::parseerror::ParseErrorKind;
+use crate::resolveerror::ResolveErrorKind;
+
+/// A registered error code. Codes are stable: once assigned they are never
+/// reused for a different error, so they can be searched for and suppressed.
+#[derive(Debug)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub explanation: &'static str,
+}
+
+macro_rules! error_codes {
+    ($($code:literal => $name:ident,)*) => {
+        pub const ERROR_CODES: &[ErrorCode] = &[
+            $(ErrorCode {
+                code: $code,
+                name: stringify!($name),
+                explanation: include_str!(concat!("explanations/", $code, ".md")),
+            },)*
+        ];
+    };
+}
+
+error_codes! {
+    // Lexical errors
+    "E0001" => UnterminatedString,
+    "E0002" => UnexpectedCharacter,
+    "E0003" => InvalidEscape,
+    "E0004" => InvalidEscapeSequence,
+    "E0005" => MalformedNumber,
+    "E0006" => TooManyDecimalPoints,
+    "E0007" => TrailingDecimalPoint,
+    "E0008" => EmptyCharLiteral,
+    "E0009" => UnterminatedChar,
+    "E0010" => TooManyChars,
+    "E0011" => InvalidDigit,
+    "E0012" => EmptyExponent,
+    "E0013" => InvalidHexEscape,
+    "E0014" => InvalidUnicodeEscape,
+    "E0015" => CodePointOutOfRange,
+    "E0016" => SurrogateCodePoint,
+    "E0017" => UnterminatedBlockComment,
+
+    // Parse errors
+    "E0100" => UnexpectedToken,
+    "E0101" => ExpectedExpression,
+    "E0102" => ExpectedPattern,
+    "E0103" => InvalidNumberLiteral,
+    "E0104" => InvalidAssignmentTarget,
+    "E0105" => InvalidOperator,
+    "E0106" => RefutablePattern,
+    "E0107" => DuplicateBinding,
+    "E0108" => MisplacedRestPattern,
+    "E0109" => MultipleRestPatterns,
+    "E0110" => MisplacedModuleItem,
+    "E0111" => ExpectedDeclaration,
+    "E0112" => ExpectedModulePath,
+    "E0113" => BreakOutsideLoop,
+    "E0114" => ReturnOutsideFunction,
+
+    // Resolution errors
+    "E0200" => UndefinedVariable,
+    "E0201" => UndefinedMember,
+}
+
+/// Looks up a code such as `E0001`, ignoring case.
+pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES
+        .iter()
+        .find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+}
+
+impl LexErrorType {
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexErrorType::UnterminatedString => "E0001",
+            LexErrorType::UnexpectedCharacter(_) => "E0002",
+            LexErrorType::InvalidEscape(_) => "E0003",
+            LexErrorType::InvalidEscapeSequence(_) => "E0004",
+            LexErrorType::MalformedNumber => "E0005",
+            LexErrorType::TooManyDecimalPoints => "E0006",
+            LexErrorType::TrailingDecimalPoint => "E0007",
+            LexErrorType::EmptyCharLiteral => "E0008",
+            LexErrorType::UnterminatedChar => "E0009",
+            LexErrorType::TooManyChars => "E0010",
+            LexErrorType::InvalidDigit(..) => "E0011",
+            LexErrorType::EmptyExponent => "E0012",
+            LexErrorType::InvalidHexEscape => "E0013",
+            LexErrorType::InvalidUnicodeEscape => "E0014",
+            LexErrorType::CodePointOutOfRange(_) => "E0015",
+            LexErrorType::SurrogateCodePoint(_) => "E0016",
+            LexErrorType::UnterminatedBlockComment => "E0017",
+        }
+    }
+}
+
+impl ParseErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseErrorKind::UnexpectedToken => "E0100",
+            ParseErrorKind::ExpectedExpression => "E0101",
+            ParseErrorKind::ExpectedPattern => "E0102",
+            ParseErrorKind::InvalidNumberLiteral => "E0103",
+            ParseErrorKind::InvalidAssignmentTarget => "E0104",
+            ParseErrorKind::InvalidOperator => "E0105",
+            ParseErrorKind::RefutablePattern => "E0106",
+            ParseErrorKind::DuplicateBinding => "E0107",
+            ParseErrorKind::MisplacedRestPattern => "E0108",
+            ParseErrorKind::MultipleRestPatterns => "E0109",
+            ParseErrorKind::MisplacedModuleItem => "E0110",
+            ParseErrorKind::ExpectedDeclaration => "E0111",
+            ParseErrorKind::ExpectedModulePath => "E0112",
+            ParseErrorKind::BreakOutsideLoop => "E0113",
+            ParseErrorKind::ReturnOutsideFunction => "E0114",
+        }
+    }
+}
+
+impl ResolveErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ResolveErrorKind::UndefinedVariable => "E0200",
+            ResolveErrorKind::UndefinedMember => "E0201",
+        }
+    }
+}
@@ -1,9 +1,16 @@
+use std::fmt;
+
+use apl_source::Span;
+
+use crate::diagnostic::{Diagnostic, Label};
+
 #[derive(Debug, Clone)]
 pub struct LexError {
     pub error_type: LexErrorType,
     pub line: usize,
     pub column: usize,
-    pub snippet: String,
+    // Resolve through a `SourceMap` for the file name and source snippet
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -18,4 +25,82 @@ pub enum LexErrorType {
     EmptyCharLiteral,
     UnterminatedChar,
     TooManyChars,
-}
\ No newline at end of file
+    InvalidDigit(char, u32),
+    EmptyExponent,
+    InvalidHexEscape,
+    InvalidUnicodeEscape,
+    CodePointOutOfRange(u32),
+    SurrogateCodePoint(u32),
+    UnterminatedBlockComment,
+}
+
+impl fmt::Display for LexErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexErrorType::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            LexErrorType::UnterminatedString => write!(f, "unterminated string literal"),
+            LexErrorType::InvalidEscapeSequence(c) | LexErrorType::InvalidEscape(c) => {
+                write!(f, "invalid escape sequence '\\{}'", c)
+            }
+            LexErrorType::MalformedNumber => write!(f, "malformed number literal"),
+            LexErrorType::TooManyDecimalPoints => {
+                write!(f, "number literal has more than one decimal point")
+            }
+            LexErrorType::TrailingDecimalPoint => {
+                write!(f, "number literal ends with a decimal point")
+            }
+            LexErrorType::EmptyCharLiteral => write!(f, "empty char literal"),
+            LexErrorType::UnterminatedChar => write!(f, "unterminated char literal"),
+            LexErrorType::TooManyChars => {
+                write!(f, "char literal contains more than one character")
+            }
+            LexErrorType::InvalidDigit(c, radix) => {
+                write!(f, "invalid digit '{}' in a base {} number literal", c, radix)
+            }
+            LexErrorType::EmptyExponent => write!(f, "number literal has no exponent digits"),
+            LexErrorType::InvalidHexEscape => {
+                write!(f, "'\\x' escape must be followed by two hex digits")
+            }
+            LexErrorType::InvalidUnicodeEscape => {
+                write!(f, "'\\u' escape must be one to six hex digits in braces")
+            }
+            LexErrorType::CodePointOutOfRange(value) => {
+                write!(f, "code point {:X} is out of range", value)
+            }
+            LexErrorType::SurrogateCodePoint(value) => {
+                write!(f, "code point {:X} is a surrogate, not a character", value)
+            }
+            LexErrorType::UnterminatedBlockComment => write!(f, "unterminated block comment"),
+        }
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Self {
+        let diagnostic = Diagnostic::error(error.error_type.to_string())
+            .with_code(error.error_type.code())
+            .with_label(Label::primary(error.span, ""));
+
+        match error.error_type {
+            LexErrorType::UnterminatedString => {
+                diagnostic.with_help("add a closing '\"' to end the string")
+            }
+            LexErrorType::TrailingDecimalPoint => {
+                diagnostic.with_help("add a digit after the decimal point, e.g. `1.0`")
+            }
+            LexErrorType::TooManyChars => {
+                diagnostic.with_help("use double quotes for strings, e.g. \"ab\"")
+            }
+            LexErrorType::EmptyExponent => {
+                diagnostic.with_help("add digits after the exponent, e.g. `1e3`")
+            }
+            LexErrorType::InvalidUnicodeEscape => {
+                diagnostic.with_help("write the code point in hex, e.g. `\\u{1F600}`")
+            }
+            LexErrorType::UnterminatedBlockComment => {
+                diagnostic.with_help("add a '*/' for this '/*' and each one nested in it")
+            }
+            _ => diagnostic,
+        }
+    }
+}
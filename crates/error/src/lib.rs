@@ -1,3 +1,162 @@
+pub mod codes;
+pub mod diagnostic;
 pub mod lexerror;
+pub mod moduleerror;
+pub mod parseerror;
+pub mod refactorerror;
+pub mod render;
+pub mod resolveerror;
+pub mod runtimeerror;
 
-pub use lexerror::LexError;
\ No newline at end of file
+pub use codes::ErrorCode;
+pub use diagnostic::{Diagnostic, Label, Severity};
+pub use lexerror::LexError;
+pub use moduleerror::ModuleError;
+pub use parseerror::ParseError;
+pub use refactorerror::RefactorError;
+pub use render::Renderer;
+pub use resolveerror::ResolveError;
+pub use runtimeerror::RuntimeError;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apl_source::{SourceMap, Span};
+
+    #[test]
+    fn render_plain() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("main.apl", "let a = 1;\nlet s = \"abc;\n");
+
+        let diagnostic = Diagnostic::error("unterminated string literal")
+            .with_code("E0002")
+            .with_label(Label::primary(Span::new(file, 19, 24), "string starts here"))
+            .with_label(Label::secondary(Span::new(file, 4, 5), "unrelated"))
+            .with_note("strings can't span lines")
+            .with_help("add a closing '\"'");
+
+        assert_eq!(
+            Renderer::plain().render(&diagnostic, &source_map),
+            r#"error[E0002]: unterminated string literal
+ --> main.apl:2:9
+  |
+2 | let s = "abc;
+  |         ^^^^^ string starts here
+1 | let a = 1;
+  |     - unrelated
+  = note: strings can't span lines
+  = help: add a closing '"'
+"#
+        );
+    }
+
+    #[test]
+    fn render_labels_across_files() {
+        let mut source_map = SourceMap::new();
+        let main = source_map.add_file("main.apl", "use l.x;");
+        let lib = source_map.add_file("lib.apl", "\tlet x = 1;");
+
+        let diagnostic = Diagnostic::warning("x is private")
+            .with_label(Label::primary(Span::new(main, 6, 7), ""))
+            .with_label(Label::secondary(Span::new(lib, 5, 6), "declared here"));
+
+        assert_eq!(
+            Renderer::plain().render(&diagnostic, &source_map),
+            "warning: x is private
+ --> main.apl:1:7
+  |
+1 | use l.x;
+  |       ^
+ ::: lib.apl:1:6
+  |
+1 | \tlet x = 1;
+  | \t    - declared here
+"
+        );
+    }
+
+    #[test]
+    fn error_codes_are_unique_and_explained() {
+        for (i, error_code) in codes::ERROR_CODES.iter().enumerate() {
+            assert!(
+                codes::ERROR_CODES[..i]
+                    .iter()
+                    .all(|other| other.code != error_code.code && other.name != error_code.name),
+                "{} is registered twice",
+                error_code.code
+            );
+            assert!(!error_code.explanation.trim().is_empty());
+        }
+
+        // Every variant maps to a registered code with a matching name
+        use lexerror::LexErrorType::*;
+        let lex = [
+            UnexpectedCharacter('@'),
+            UnterminatedString,
+            InvalidEscapeSequence('q'),
+            InvalidEscape('q'),
+            MalformedNumber,
+            TooManyDecimalPoints,
+            TrailingDecimalPoint,
+            EmptyCharLiteral,
+            UnterminatedChar,
+            TooManyChars,
+            InvalidDigit('2', 2),
+            EmptyExponent,
+            InvalidHexEscape,
+            InvalidUnicodeEscape,
+            CodePointOutOfRange(0x110000),
+            SurrogateCodePoint(0xD800),
+            UnterminatedBlockComment,
+        ];
+        for error_type in lex {
+            let registered = codes::lookup(error_type.code()).unwrap();
+            assert!(format!("{:?}", error_type).starts_with(registered.name));
+        }
+
+        use parseerror::ParseErrorKind::*;
+        let parse = [
+            UnexpectedToken,
+            ExpectedExpression,
+            ExpectedPattern,
+            InvalidNumberLiteral,
+            InvalidAssignmentTarget,
+            InvalidOperator,
+            RefutablePattern,
+            DuplicateBinding,
+            MisplacedRestPattern,
+            MultipleRestPatterns,
+            MisplacedModuleItem,
+            ExpectedDeclaration,
+            ExpectedModulePath,
+            BreakOutsideLoop,
+            ReturnOutsideFunction,
+        ];
+        for kind in parse {
+            let registered = codes::lookup(kind.code()).unwrap();
+            assert_eq!(format!("{:?}", kind), registered.name);
+        }
+
+        use resolveerror::ResolveErrorKind::*;
+        for kind in [UndefinedVariable, UndefinedMember] {
+            let registered = codes::lookup(kind.code()).unwrap();
+            assert_eq!(format!("{:?}", kind), registered.name);
+        }
+
+        assert_eq!(codes::lookup("e0001").unwrap().name, "UnterminatedString");
+        assert!(codes::lookup("E9999").is_none());
+    }
+
+    #[test]
+    fn render_colored() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("main.apl", "@");
+        let diagnostic =
+            Diagnostic::error("unexpected character").with_label(Label::primary(Span::new(file, 0, 1), ""));
+
+        let rendered = Renderer::colored().render(&diagnostic, &source_map);
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m"));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+        assert!(!Renderer::plain().render(&diagnostic, &source_map).contains('\x1b'));
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::{Diagnostic, LexError, ParseError};
+
+#[derive(Debug, Clone)]
+pub enum ModuleError {
+    NotFound {
+        path: PathBuf,
+        importer: Option<PathBuf>,
+    },
+    Io {
+        path: PathBuf,
+        message: String,
+    },
+    Lex {
+        path: PathBuf,
+        errors: Vec<LexError>,
+    },
+    Parse {
+        path: PathBuf,
+        error: ParseError,
+    },
+    // The chain of modules forming the cycle, starting and ending with the same path
+    Cycle {
+        chain: Vec<PathBuf>,
+    },
+    DuplicateAlias {
+        path: PathBuf,
+        alias: String,
+    },
+    UnknownModule {
+        path: PathBuf,
+        alias: String,
+    },
+    NotExported {
+        path: PathBuf,
+        module: PathBuf,
+        name: String,
+    },
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModuleError::NotFound {
+                path,
+                importer: Some(importer),
+            } => write!(
+                f,
+                "module '{}' imported from '{}' was not found",
+                path.display(),
+                importer.display()
+            ),
+            ModuleError::NotFound {
+                path,
+                importer: None,
+            } => write!(f, "module '{}' was not found", path.display()),
+            ModuleError::Io { path, message } => {
+                write!(f, "could not read '{}': {}", path.display(), message)
+            }
+            ModuleError::Lex { path, errors } => write!(
+                f,
+                "'{}' has {} lexical error(s), first: {:?}",
+                path.display(),
+                errors.len(),
+                errors[0].error_type
+            ),
+            ModuleError::Parse { path, error } => {
+                write!(f, "could not parse '{}': {}", path.display(), error)
+            }
+            ModuleError::Cycle { chain } => {
+                let chain: Vec<String> = chain.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "import cycle detected: {}", chain.join(" -> "))
+            }
+            ModuleError::DuplicateAlias { path, alias } => write!(
+                f,
+                "module alias '{}' is imported more than once in '{}'",
+                alias,
+                path.display()
+            ),
+            ModuleError::UnknownModule { path, alias } => write!(
+                f,
+                "'{}' uses unknown module '{}', import it with `import \"...\" as {};`",
+                path.display(),
+                alias,
+                alias
+            ),
+            ModuleError::NotExported { path, module, name } => write!(
+                f,
+                "'{}' uses '{}' which is not exported by '{}', mark it `pub`",
+                path.display(),
+                name,
+                module.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ModuleError {}
+
+impl ModuleError {
+    /// Source errors become one diagnostic each, loader errors have no span
+    /// and are reported as a single message.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            ModuleError::Lex { errors, .. } => errors.iter().map(Diagnostic::from).collect(),
+            ModuleError::Parse { error, .. } => vec![Diagnostic::from(error)],
+            _ => vec![Diagnostic::error(self.to_string())],
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+
+use apl_source::Span;
+
+use crate::diagnostic::{Diagnostic, Label};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub message: String,
+    // The token the parser stopped at
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseErrorKind {
+    UnexpectedToken,
+    ExpectedExpression,
+    ExpectedPattern,
+    InvalidNumberLiteral,
+    InvalidAssignmentTarget,
+    InvalidOperator,
+    RefutablePattern,
+    DuplicateBinding,
+    MisplacedRestPattern,
+    MultipleRestPatterns,
+    MisplacedModuleItem,
+    ExpectedDeclaration,
+    ExpectedModulePath,
+    BreakOutsideLoop,
+    ReturnOutsideFunction,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic::error(&error.message)
+            .with_code(error.kind.code())
+            .with_label(Label::primary(error.span, ""))
+    }
+}
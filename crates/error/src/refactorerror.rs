@@ -0,0 +1,42 @@
+use std::fmt;
+
+use apl_source::Span;
+
+use crate::diagnostic::{Diagnostic, Label};
+
+/// Why a refactoring was refused, usually because it would change what the
+/// program does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefactorError {
+    pub message: String,
+    // The code that stands in the way, if there is one
+    pub span: Option<Span>,
+}
+
+impl RefactorError {
+    pub fn new(message: impl Into<String>, span: Option<Span>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for RefactorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RefactorError {}
+
+impl From<&RefactorError> for Diagnostic {
+    fn from(error: &RefactorError) -> Self {
+        let diagnostic = Diagnostic::error(&error.message);
+
+        match error.span {
+            Some(span) => diagnostic.with_label(Label::primary(span, "")),
+            None => diagnostic,
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use apl_source::{Location, SourceMap};
+
+use crate::diagnostic::{Diagnostic, Label, Severity};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Renders diagnostics rustc-style, with the offending source lines and
+/// `^^^` underlines below each labeled span.
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    pub fn new(color: bool) -> Self {
+        Self { color }
+    }
+
+    /// A renderer without ANSI escapes, for logs and files.
+    pub fn plain() -> Self {
+        Self::new(false)
+    }
+
+    pub fn colored() -> Self {
+        Self::new(true)
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, source_map: &SourceMap) -> String {
+        let mut out = String::new();
+        let severity_style = Self::severity_style(diagnostic.severity);
+
+        // error[E0001]: message
+        let header = match &diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity.as_str(), code),
+            None => diagnostic.severity.as_str().to_string(),
+        };
+        out.push_str(&self.paint(severity_style, &header));
+        out.push_str(&self.paint(BOLD, &format!(": {}", diagnostic.message)));
+        out.push('\n');
+
+        // Labels grouped by file and line, primary file first
+        let mut labels: Vec<(&Label, Location)> = diagnostic
+            .labels
+            .iter()
+            .map(|label| (label, source_map.location(label.span)))
+            .collect();
+        labels.sort_by_key(|(label, location)| {
+            (
+                !label.primary,
+                label.span.file,
+                location.line,
+                location.column,
+            )
+        });
+
+        let width = labels
+            .iter()
+            .map(|(_, location)| location.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(width);
+        let gutter = |text: &str| self.paint(BLUE, text);
+
+        let mut current: Option<(&Label, Location)> = None;
+        for &(label, location) in &labels {
+            let same_file = current.is_some_and(|(l, _)| l.span.file == label.span.file);
+            let same_line = same_file && current.is_some_and(|(_, loc)| loc.line == location.line);
+
+            if !same_file {
+                let arrow = if current.is_none() { "-->" } else { ":::" };
+                out.push_str(&format!(
+                    "{}{} {}\n",
+                    pad,
+                    gutter(arrow),
+                    source_map.format_location(label.span)
+                ));
+                out.push_str(&format!("{} {}\n", pad, gutter("|")));
+            }
+
+            let text = source_map.snippet(label.span);
+            if !same_line {
+                out.push_str(&format!(
+                    "{} {}\n",
+                    gutter(&format!("{:>width$} |", location.line)),
+                    text
+                ));
+            }
+
+            // Indent to the label's column, keeping tabs so the marker lines up
+            let indent: String = text
+                .chars()
+                .take(location.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let remaining = text.chars().count().saturating_sub(location.column - 1);
+            // Spans covering several lines are underlined to the end of the first
+            let spanned = &source_map.source(label.span.file)[label.span.start..label.span.end];
+            let length = spanned
+                .split('\n')
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .count()
+                .clamp(1, remaining.max(1));
+
+            let (marker, style) = if label.primary {
+                ("^", severity_style)
+            } else {
+                ("-", BLUE)
+            };
+            let mut underline = marker.repeat(length);
+            if !label.message.is_empty() {
+                underline.push(' ');
+                underline.push_str(&label.message);
+            }
+            out.push_str(&format!(
+                "{} {} {}{}\n",
+                pad,
+                gutter("|"),
+                indent,
+                self.paint(style, &underline)
+            ));
+
+            current = Some((label, location));
+        }
+
+        for note in &diagnostic.notes {
+            out.push_str(&format!(
+                "{} {} {}\n",
+                pad,
+                gutter("="),
+                self.titled("note", note)
+            ));
+        }
+        if let Some(help) = &diagnostic.help {
+            out.push_str(&format!(
+                "{} {} {}\n",
+                pad,
+                gutter("="),
+                self.titled("help", help)
+            ));
+        }
+
+        out
+    }
+
+    pub fn render_all(&self, diagnostics: &[Diagnostic], source_map: &SourceMap) -> String {
+        diagnostics
+            .iter()
+            .map(|diagnostic| self.render(diagnostic, source_map))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn titled(&self, title: &str, text: &str) -> String {
+        format!("{}: {}", self.paint(BOLD, title), text)
+    }
+
+    fn severity_style(severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => "\x1b[1;31m",
+            Severity::Warning => "\x1b[1;33m",
+            Severity::Note => "\x1b[1;32m",
+        }
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
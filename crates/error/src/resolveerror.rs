@@ -0,0 +1,50 @@
+use std::fmt;
+
+use apl_source::Span;
+
+use crate::diagnostic::{Diagnostic, Label};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolveError {
+    pub kind: ResolveErrorKind,
+    pub message: String,
+    // The name that could not be resolved
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolveErrorKind {
+    UndefinedVariable,
+    UndefinedMember,
+}
+
+impl ResolveError {
+    pub fn new(kind: ResolveErrorKind, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+impl From<&ResolveError> for Diagnostic {
+    fn from(error: &ResolveError) -> Self {
+        let label = match error.kind {
+            ResolveErrorKind::UndefinedVariable => "not found in this scope",
+            ResolveErrorKind::UndefinedMember => "not exported by the module",
+        };
+
+        Diagnostic::error(&error.message)
+            .with_code(error.kind.code())
+            .with_label(Label::primary(error.span, label))
+    }
+}
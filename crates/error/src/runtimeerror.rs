@@ -0,0 +1,40 @@
+use std::fmt;
+
+use apl_source::Span;
+
+use crate::diagnostic::{Diagnostic, Label};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    // Not every expression carries a span, e.g. literals and operators
+    pub span: Option<Span>,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>, span: Option<Span>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Self {
+        let diagnostic = Diagnostic::error(&error.message);
+
+        match error.span {
+            Some(span) => diagnostic.with_label(Label::primary(span, "")),
+            None => diagnostic,
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use apl_error::Diagnostic;
+use apl_parser::ast::{Stmt, Visibility};
+use apl_parser::core::Parser;
+use apl_scanner::Scanner;
+use apl_source::{FileId, Span};
+
+use apl_parser::ast::{Expr, Pattern};
+
+use super::expressions::{INDENT, format_expr, pattern_text, string_literal};
+
+/// Formats a whole module. Fails when the source doesn't scan or parse,
+/// since there is no tree to print.
+pub fn format_source(source: &str, file: FileId) -> Result<String, Vec<Diagnostic>> {
+    if source.trim().is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut scanner = Scanner::with_file(source, file);
+    let tokens = scanner.scan_tokens();
+    if !scanner.errors.is_empty() {
+        return Err(scanner.errors.iter().map(Diagnostic::from).collect());
+    }
+
+    let mut parser = Parser::new(tokens);
+    let statements = parser
+        .try_parse()
+        .map_err(|error| vec![Diagnostic::from(&error)])?;
+
+    let mut formatter = Formatter {
+        source,
+        comments: std::mem::take(&mut scanner.comments).into_iter().peekable(),
+        spans: parser.statement_spans().to_vec().into_iter().peekable(),
+        out: String::new(),
+        indent: 0,
+        last_end: 0,
+        at_block_start: true,
+    };
+
+    formatter.statements(&statements);
+    formatter.comments_before(source.len());
+
+    Ok(formatter.out)
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    comments: Peekable<IntoIter<Span>>,
+    // Spans of the statements in the order the formatter visits them
+    spans: Peekable<IntoIter<Span>>,
+    out: String,
+    indent: usize,
+    // Where the last statement or comment written ended in the source
+    last_end: usize,
+    at_block_start: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn statements(&mut self, statements: &[Stmt]) {
+        let mut previous_was_function = false;
+
+        for stmt in statements {
+            let span = self.spans.next().expect("a span for every statement");
+            let is_function = matches!(stmt, Stmt::FunctionDecl(_));
+
+            // Functions are always set apart by a blank line
+            if (is_function || previous_was_function) && !self.at_block_start {
+                self.blank_line();
+            }
+            previous_was_function = is_function;
+
+            self.comments_before(span.start);
+            self.separate(span.start);
+
+            self.write_indent();
+            self.statement(stmt, span);
+            self.last_end = span.end;
+
+            // A comment on the same line stays at the end of the line
+            if let Some(&comment) = self.comments.peek()
+                && comment.start >= span.end
+                && !self.source[span.end..comment.start].contains('\n')
+            {
+                self.comments.next();
+                self.out.push(' ');
+                self.out.push_str(self.comment_text(comment));
+                self.last_end = comment.end;
+            }
+            self.out.push('\n');
+
+            // Comments inside an expression go on their own lines after it
+            self.comments_before(span.end);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt, span: Span) {
+        match stmt {
+            Stmt::VariableDecl(decl) => {
+                self.visibility(decl.visibility);
+                let prefix = format!("let {} = ", self.pattern(&decl.pattern));
+                self.out.push_str(&prefix);
+                let value = self.expr(&decl.initializer, self.column());
+                self.out.push_str(&value);
+                self.out.push(';');
+            }
+            Stmt::Expression(expr) => {
+                let expr = self.expr(expr, self.column());
+                self.out.push_str(&expr);
+                self.out.push(';');
+            }
+            Stmt::FunctionDecl(function) => {
+                self.visibility(function.visibility);
+                let params: Vec<&str> = function.params.iter().map(|p| p.as_str()).collect();
+                self.out
+                    .push_str(&format!("fn {}({}) ", function.name, params.join(", ")));
+                self.block(&function.statements, span.end);
+            }
+            Stmt::For(for_loop) => {
+                let prefix = format!("for {} in ", self.pattern(&for_loop.pattern));
+                self.out.push_str(&prefix);
+                let iterable = self.expr(&for_loop.iterable, self.column());
+                self.out.push_str(&iterable);
+                self.out.push(' ');
+                self.block(&for_loop.body, span.end);
+            }
+            Stmt::If(if_stmt) => {
+                self.out.push_str("if ");
+                let condition = self.expr(&if_stmt.condition, self.column());
+                self.out.push_str(&condition);
+                self.out.push(' ');
+
+                // The then branch ends where the else branch starts
+                let then_end = match self.next_span_after_block(&if_stmt.then_branch) {
+                    Some(start) if if_stmt.else_branch.is_some() => start.min(span.end),
+                    _ => span.end,
+                };
+                self.block(&if_stmt.then_branch, then_end);
+
+                match if_stmt.else_branch.as_deref() {
+                    Some([else_if @ Stmt::If(_)]) => {
+                        let else_span = self.spans.next().expect("a span for `else if`");
+                        self.out.push_str(" else ");
+                        self.statement(else_if, else_span);
+                    }
+                    Some(else_branch) => {
+                        self.out.push_str(" else ");
+                        self.block(else_branch, span.end);
+                    }
+                    None => {}
+                }
+            }
+            Stmt::While(while_loop) => {
+                self.out.push_str("while ");
+                let condition = self.expr(&while_loop.condition, self.column());
+                self.out.push_str(&condition);
+                self.out.push(' ');
+                self.block(&while_loop.body, span.end);
+            }
+            Stmt::Loop(body) => {
+                self.out.push_str("loop ");
+                self.block(body, span.end);
+            }
+            Stmt::Break => self.out.push_str("break;"),
+            Stmt::Continue => self.out.push_str("continue;"),
+            Stmt::Return(None) => self.out.push_str("return;"),
+            Stmt::Return(Some(value)) => {
+                self.out.push_str("return ");
+                let value = self.expr(value, self.column());
+                self.out.push_str(&value);
+                self.out.push(';');
+            }
+            Stmt::Import(import) => self.out.push_str(&format!(
+                "import {} as {};",
+                string_literal(&import.path),
+                import.alias
+            )),
+            Stmt::Use(use_decl) => {
+                let names: Vec<&str> = use_decl.names.iter().map(|n| n.as_str()).collect();
+                match names.as_slice() {
+                    [name] => self
+                        .out
+                        .push_str(&format!("use {}.{};", use_decl.module, name)),
+                    _ => self.out.push_str(&format!(
+                        "use {}.{{{}}};",
+                        use_decl.module,
+                        names.join(", ")
+                    )),
+                }
+            }
+        }
+    }
+
+    // The start of the first statement after the ones in `block`, which are
+    // visited next
+    fn next_span_after_block(&mut self, block: &[Stmt]) -> Option<usize> {
+        let count = count_statements(block);
+        self.spans.clone().nth(count).map(|span| span.start)
+    }
+
+    /// Writes `{ ... }`, keeping comments that appear before `end` inside.
+    fn block(&mut self, statements: &[Stmt], end: usize) {
+        let has_comments = self.comments.peek().is_some_and(|c| c.start < end);
+        if statements.is_empty() && !has_comments {
+            self.out.push_str("{}");
+            return;
+        }
+
+        self.out.push_str("{\n");
+        self.indent += 1;
+        self.at_block_start = true;
+
+        self.statements(statements);
+        self.comments_before(end);
+
+        self.indent -= 1;
+        self.write_indent();
+        self.out.push('}');
+        self.at_block_start = false;
+    }
+
+    fn expr(&self, expr: &Expr, column: usize) -> String {
+        format_expr(expr, self.indent, column)
+    }
+
+    fn pattern(&self, pattern: &Pattern) -> String {
+        pattern_text(pattern)
+    }
+
+    fn visibility(&mut self, visibility: Visibility) {
+        if visibility == Visibility::Public {
+            self.out.push_str("pub ");
+        }
+    }
+
+    /// Writes each remaining comment that starts before `offset` on its own line.
+    fn comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.next_if(|c| c.start < offset) {
+            self.separate(comment.start);
+            self.write_indent();
+            self.out.push_str(self.comment_text(comment));
+            self.out.push('\n');
+            self.last_end = comment.end;
+        }
+    }
+
+    // Keeps a single blank line where the source had one or more
+    fn separate(&mut self, start: usize) {
+        let gap = &self.source[self.last_end.min(start)..start];
+        if gap.matches('\n').count() > 1 && !self.at_block_start {
+            self.blank_line();
+        }
+        self.at_block_start = false;
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn comment_text(&self, comment: Span) -> &'a str {
+        self.source[comment.start..comment.end].trim_end()
+    }
+
+    fn write_indent(&mut self) {
+        self.out.push_str(&INDENT.repeat(self.indent));
+    }
+
+    // The column the next character written will be at
+    fn column(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |i| i + 1);
+        self.out[line_start..].chars().count()
+    }
+}
+
+// Statements the parser recorded spans for within `statements`
+fn count_statements(statements: &[Stmt]) -> usize {
+    statements.iter().map(|stmt| 1 + count_nested(stmt)).sum()
+}
+
+fn count_nested(stmt: &Stmt) -> usize {
+    match stmt {
+        Stmt::FunctionDecl(function) => count_statements(&function.statements),
+        Stmt::For(for_loop) => count_statements(&for_loop.body),
+        Stmt::While(while_loop) => count_statements(&while_loop.body),
+        Stmt::Loop(body) => count_statements(body),
+        Stmt::If(if_stmt) => {
+            count_statements(&if_stmt.then_branch)
+                + if_stmt.else_branch.as_deref().map_or(0, count_statements)
+        }
+        _ => 0,
+    }
+}
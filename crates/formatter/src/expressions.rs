@@ -0,0 +1,220 @@
+use apl_parser::ast::{BinaryOp, Expr, Literal, Part, Pattern, UnaryOp};
+
+pub(crate) const INDENT: &str = "    ";
+
+// Lines longer than this have their argument lists broken up
+pub(crate) const MAX_WIDTH: usize = 100;
+
+/// Formats `expr` starting at `column` of a line indented `indent` levels,
+/// breaking argument lists, arrays and tuples one item per line when they
+/// don't fit.
+pub(crate) fn format_expr(expr: &Expr, indent: usize, column: usize) -> String {
+    let single_line = flat(expr);
+    if column + single_line.chars().count() <= MAX_WIDTH {
+        return single_line;
+    }
+
+    match expr {
+        Expr::Call { callee, arguments } => {
+            let open = format!("{}(", flat(callee));
+            list(&open, arguments, ")", indent)
+        }
+        Expr::MethodAccess { object, method } => match &**method {
+            Expr::Call { callee, arguments } => {
+                let object = format_expr(object, indent, column);
+                let open = format!("{}.{}(", object, flat(callee));
+                list(&open, arguments, ")", indent)
+            }
+            _ => single_line,
+        },
+        Expr::Array(elements) => list("[", elements, "]", indent),
+        Expr::Tuple(elements) => list("(", elements, ")", indent),
+        Expr::Binary(binary) => {
+            let left = format_expr(&binary.left, indent, column);
+            let prefix = format!("{} {} ", left, binary_op(binary.op));
+            let right = format_expr(&binary.right, indent, last_line_width(&prefix, column));
+            prefix + &right
+        }
+        Expr::Assignment { name, value } => {
+            let prefix = format!("{} = ", name);
+            prefix.clone() + &format_expr(value, indent, column + prefix.len())
+        }
+        Expr::Grouping(inner) => format!("({})", format_expr(inner, indent, column + 1)),
+        _ => single_line,
+    }
+}
+
+// Items one per line, indented one level deeper than the line they start on
+fn list(open: &str, items: &[Expr], close: &str, indent: usize) -> String {
+    if items.is_empty() {
+        return format!("{}{}", open, close);
+    }
+
+    let mut out = format!("{}\n", open);
+    for item in items {
+        out.push_str(&INDENT.repeat(indent + 1));
+        out.push_str(&format_expr(item, indent + 1, (indent + 1) * INDENT.len()));
+        out.push_str(",\n");
+    }
+    out.push_str(&INDENT.repeat(indent));
+    out.push_str(close);
+    out
+}
+
+// Width of the last line of `text` if it starts at `column`
+fn last_line_width(text: &str, column: usize) -> usize {
+    match text.rfind('\n') {
+        Some(i) => text[i + 1..].chars().count(),
+        None => column + text.chars().count(),
+    }
+}
+
+/// Formats `expr` on a single line.
+fn flat(expr: &Expr) -> String {
+    let list = |items: &[Expr]| items.iter().map(flat).collect::<Vec<_>>().join(", ");
+
+    match expr {
+        Expr::Literal(literal) => literal_text(literal),
+        Expr::Variable(variable) => variable.name.to_string(),
+        Expr::Identifier(name) => name.to_string(),
+        Expr::Binary(binary) => format!(
+            "{} {} {}",
+            flat(&binary.left),
+            binary_op(binary.op),
+            flat(&binary.right)
+        ),
+        Expr::Unary(unary) => {
+            let op = match unary.op {
+                UnaryOp::Not => "!",
+                UnaryOp::Negative => "-",
+            };
+            format!("{}{}", op, flat(&unary.right))
+        }
+        Expr::Grouping(inner) => format!("({})", flat(inner)),
+        Expr::Assignment { name, value } => format!("{} = {}", name, flat(value)),
+        Expr::Call { callee, arguments } => format!("{}({})", flat(callee), list(arguments)),
+        Expr::MethodAccess { object, method } => format!("{}.{}", flat(object), flat(method)),
+        Expr::PropertyAccess { object, propert } => format!("{}.{}", flat(object), propert),
+        Expr::Array(elements) => format!("[{}]", list(elements)),
+        Expr::Tuple(elements) if elements.len() == 1 => format!("({},)", flat(&elements[0])),
+        Expr::Tuple(elements) => format!("({})", list(elements)),
+        Expr::Index { object, index } => format!("{}[{}]", flat(object), flat(index)),
+        Expr::Interpolated { parts, .. } => {
+            let mut out = String::from('"');
+            for part in parts {
+                match part {
+                    Part::Text(text) => out.push_str(&string_text(text)),
+                    Part::Expr(expr) => out.push_str(&format!("${{{}}}", flat(expr))),
+                }
+            }
+            out.push('"');
+            out
+        }
+    }
+}
+
+fn binary_op(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::Multiply => "*",
+        BinaryOp::Divide => "/",
+        BinaryOp::Modulo => "%",
+        BinaryOp::Equal => "==",
+        BinaryOp::NotEqual => "!=",
+        BinaryOp::Greater => ">",
+        BinaryOp::GreaterEqual => ">=",
+        BinaryOp::Less => "<",
+        BinaryOp::LessEqual => "<=",
+    }
+}
+
+// Literals are written as they were, so numbers keep their radix, separators
+// and exponent, raw and multi-line strings their form and escapes theirs
+fn literal_text(literal: &Literal) -> String {
+    match literal {
+        Literal::Integer { lexeme, .. }
+        | Literal::BigInteger { lexeme, .. }
+        | Literal::Float { lexeme, .. }
+        | Literal::Decimal { lexeme, .. }
+        | Literal::String { lexeme, .. }
+        | Literal::Char { lexeme, .. } => lexeme.clone(),
+    }
+}
+
+pub(crate) fn string_literal(s: &str) -> String {
+    format!("\"{}\"", string_text(s))
+}
+
+// The text of a string between its quotes, `${` is escaped so it isn't read
+// back as an interpolation
+fn string_text(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
+            c => match escape(c) {
+                Some(escaped) => out.push_str(&escaped),
+                None => out.push(c),
+            },
+        }
+    }
+    out
+}
+
+// Escapes for the text of interpolated strings and import paths, so no
+// control character is written to the source as it is
+fn escape(c: char) -> Option<String> {
+    match c {
+        '\\' => Some("\\\\".to_string()),
+        '\n' => Some("\\n".to_string()),
+        '\t' => Some("\\t".to_string()),
+        '\r' => Some("\\r".to_string()),
+        '\0' => Some("\\0".to_string()),
+        c if c.is_ascii_control() => Some(format!("\\x{:02x}", c as u32)),
+        c if c.is_control() => Some(format!("\\u{{{:x}}}", c as u32)),
+        _ => None,
+    }
+}
+
+pub(crate) fn pattern_text(pattern: &Pattern) -> String {
+    let list = |items: &[Pattern]| {
+        items
+            .iter()
+            .map(pattern_text)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match pattern {
+        Pattern::Identifier(name) => name.to_string(),
+        Pattern::Wildcard => "_".to_string(),
+        Pattern::Literal(literal) => literal_text(literal),
+        Pattern::Rest(Some(name)) => format!("..{}", name),
+        Pattern::Rest(None) => "..".to_string(),
+        Pattern::Array(elements) => format!("[{}]", list(elements)),
+        Pattern::Tuple(elements) if elements.len() == 1 => {
+            format!("({},)", pattern_text(&elements[0]))
+        }
+        Pattern::Tuple(elements) => format!("({})", list(elements)),
+        Pattern::Struct { name, fields } => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|field| match &field.pattern {
+                    // `Point { x }` is shorthand for `Point { x: x }`
+                    Pattern::Identifier(binding) if *binding == field.name => {
+                        field.name.to_string()
+                    }
+                    pattern => format!("{}: {}", field.name, pattern_text(pattern)),
+                })
+                .collect();
+            if fields.is_empty() {
+                format!("{} {{}}", name)
+            } else {
+                format!("{} {{ {} }}", name, fields.join(", "))
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+mod core;
+mod expressions;
+
+pub use core::format_source;
+
+use apl_error::Diagnostic;
+use apl_source::FileId;
+
+/// Formats a source that isn't part of a `SourceMap`.
+pub fn format(source: &str) -> Result<String, Vec<Diagnostic>> {
+    format_source(source, FileId::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_formats(input: &str, expected: &str) {
+        let formatted = format(input).unwrap();
+        assert_eq!(formatted, expected);
+        // Formatting is idempotent
+        assert_eq!(format(&formatted).unwrap(), expected);
+    }
+
+    #[test]
+    fn format_spacing_and_indentation() {
+        assert_formats(
+            "let   a=1+2*(3-x) ;fn add(a,b){return a+b;}\nif a>1{print(a);}else if a==1{}else{loop{break;}}",
+            r#"let a = 1 + 2 * (3 - x);
+
+fn add(a, b) {
+    return a + b;
+}
+
+if a > 1 {
+    print(a);
+} else if a == 1 {} else {
+    loop {
+        break;
+    }
+}
+"#,
+        );
+
+        assert_formats(
+            r#"import "lib/m.apl" as m ;use m.{a,b};use m.c;pub let [x,..rest]=(1,);for Point{x,y:py} in ps{s="a\"b\n";}"#,
+            r#"import "lib/m.apl" as m;
+use m.{a, b};
+use m.c;
+pub let [x, ..rest] = (1,);
+for Point { x, y: py } in ps {
+    s = "a\"b\n";
+}
+"#,
+        );
+
+        assert_formats(
+            "let n=[1_000,1.5e3,0.05d,2d,1.10d];",
+            "let n = [1_000, 1.5e3, 0.05d, 2d, 1.10d];\n",
+        );
+
+        assert_formats(
+            r#"let s="Hi ${ name }, ${n+1} \${x} ${"\"${a}\""}";"#,
+            "let s = \"Hi ${name}, ${n + 1} \\${x} ${\"\\\"${a}\\\"\"}\";\n",
+        );
+    }
+
+    #[test]
+    fn format_preserves_comments_and_blank_lines() {
+        assert_formats(
+            r#"// Leading comment
+let a = 1; // trailing
+
+
+/* block */
+fn f() {
+  // inside
+  let b = 2;
+
+  let c = b;
+  // at the end
+}
+fn g() { /* only a comment */ }
+"#,
+            r#"// Leading comment
+let a = 1; // trailing
+
+/* block */
+fn f() {
+    // inside
+    let b = 2;
+
+    let c = b;
+    // at the end
+}
+
+fn g() {
+    /* only a comment */
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn format_breaks_long_argument_lists() {
+        assert_formats(
+            "print(first_argument_value, second_argument_value, third_argument_value, [1, 2, 3], fourth_argument_value);",
+            "print(\n    first_argument_value,\n    second_argument_value,\n    third_argument_value,\n    [1, 2, 3],\n    fourth_argument_value,\n);\n",
+        );
+    }
+
+    #[test]
+    fn format_keeps_raw_and_multiline_strings() {
+        assert_formats(
+            r###"let p=r"C:\x";let q=r#"say "hi""#;"###,
+            "let p = r\"C:\\x\";\nlet q = r#\"say \"hi\"\"#;\n",
+        );
+
+        let source = r#"fn f() {
+    let s = """
+        {
+          "a": 1
+        }
+        """;
+}
+"#;
+        assert_formats(source, source);
+    }
+
+    #[test]
+    fn format_keeps_number_literals() {
+        let source = "let n = 0xFF + 0b1010 + 0o17 + 0x_FF + 1_000 + 9_223_372_036_854_775_808;
+let f = [1.5e3, 1.0e-7, 2E+4, 1_000.000_1, 1_0.5d];
+";
+        assert_formats(source, source);
+    }
+
+    #[test]
+    fn format_keeps_escapes() {
+        let source = r#"let c = ['\r', '\0', '\x7f', '\u{41}', '\''];
+let s = "a\r\0\x01\u{41}";
+let t = "a\r\0 ${x} \x01\x7f\u{85}";
+"#;
+        assert_formats(source, source);
+    }
+
+    #[test]
+    fn format_errors() {
+        let errors = format("let a = ;").unwrap_err();
+        assert_eq!(errors[0].code.as_deref(), Some("E0101"));
+        assert!(format("let s = \"open").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::Write;
+
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Zero};
+
+use super::value::Native;
+use super::{Decimal, Interpreter, Value};
+
+/// Functions available in every module without an import.
+pub const BUILTINS: &[Native] = &[
+    Native {
+        name: "print",
+        arity: None,
+        function: print,
+    },
+    Native {
+        name: "len",
+        arity: Some(1),
+        function: len,
+    },
+    Native {
+        name: "range",
+        arity: None,
+        function: range,
+    },
+    Native {
+        name: "type_of",
+        arity: Some(1),
+        function: type_of,
+    },
+    Native {
+        name: "str",
+        arity: Some(1),
+        function: str,
+    },
+    Native {
+        name: "push",
+        arity: Some(2),
+        function: push,
+    },
+    Native {
+        name: "int",
+        arity: Some(1),
+        function: int,
+    },
+    Native {
+        name: "float",
+        arity: Some(1),
+        function: float,
+    },
+    Native {
+        name: "decimal",
+        arity: Some(1),
+        function: decimal,
+    },
+    Native {
+        name: "rational",
+        arity: None,
+        function: rational,
+    },
+];
+
+fn print(interpreter: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let line = args
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    writeln!(interpreter.output, "{}", line).map_err(|e| e.to_string())?;
+    Ok(Value::Unit)
+}
+
+fn len(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let len = match &args[0] {
+        Value::String(s) => s.chars().count(),
+        Value::Array(values) => values.borrow().len(),
+        Value::Tuple(values) => values.len(),
+        other => return Err(format!("len() is not defined for {}", other.type_name())),
+    };
+
+    i64::try_from(len)
+        .map(Value::Integer)
+        .map_err(|_| "length does not fit in an int".to_string())
+}
+
+// `range(end)` or `range(start, end)`, the end is exclusive
+fn range(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let (start, end) = match args.as_slice() {
+        [Value::Integer(end)] => (0, *end),
+        [Value::Integer(start), Value::Integer(end)] => (*start, *end),
+        [_] | [_, _]
+            if args
+                .iter()
+                .all(|arg| matches!(arg, Value::Integer(_) | Value::BigInteger(_))) =>
+        {
+            return Err("range() bounds must fit in 64 bits".to_string());
+        }
+        _ => return Err("range() expects one or two ints".to_string()),
+    };
+
+    Ok(Value::array((start..end).map(Value::Integer).collect()))
+}
+
+fn type_of(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    Ok(Value::String(args[0].type_name().to_string()))
+}
+
+fn str(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    Ok(Value::String(args[0].to_string()))
+}
+
+fn push(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    match &args[0] {
+        Value::Array(values) => {
+            values.borrow_mut().push(args[1].clone());
+            Ok(Value::Unit)
+        }
+        other => Err(format!(
+            "push() expects an array, found {}",
+            other.type_name()
+        )),
+    }
+}
+
+// Rounds toward zero
+fn int(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    match &args[0] {
+        Value::Integer(_) | Value::BigInteger(_) => Ok(args[0].clone()),
+        Value::Float(n) => BigInt::from_f32(n.trunc())
+            .map(Value::integer)
+            .ok_or_else(|| format!("int() can't convert {}", n)),
+        Value::Decimal(n) => Ok(Value::integer(n.trunc())),
+        Value::Rational(n) => Ok(Value::integer(n.trunc().to_integer())),
+        other => Err(format!("int() expects a number, found {}", other.type_name())),
+    }
+}
+
+fn float(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    args[0]
+        .to_float()
+        .map(Value::Float)
+        .ok_or_else(|| format!("float() expects a number, found {}", args[0].type_name()))
+}
+
+// Floats become the shortest decimal that reads back as them, and rationals
+// are rounded like a decimal division when they don't end
+fn decimal(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let decimal = match &args[0] {
+        Value::Float(n) => {
+            Decimal::from_float(*n).ok_or_else(|| format!("decimal() can't convert {}", n))?
+        }
+        Value::Rational(n) => Decimal::from_rational(n, 0),
+        other => other.to_decimal().ok_or_else(|| {
+            format!("decimal() expects a number, found {}", other.type_name())
+        })?,
+    };
+    Ok(Value::Decimal(decimal))
+}
+
+// `rational(x)` or the exact quotient `rational(numerator, denominator)`
+fn rational(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, String> {
+    let exact = |value: &Value| match value {
+        Value::Float(n) => Decimal::from_float(*n)
+            .map(|n| n.to_rational())
+            .ok_or_else(|| format!("rational() can't convert {}", n)),
+        other => other.to_rational().ok_or_else(|| {
+            format!("rational() expects numbers, found {}", other.type_name())
+        }),
+    };
+
+    let value = match args.as_slice() {
+        [value] => exact(value)?,
+        [numerator, denominator] => {
+            let denominator = exact(denominator)?;
+            if denominator.is_zero() {
+                return Err("Division by zero".to_string());
+            }
+            exact(numerator)? / denominator
+        }
+        _ => return Err("rational() expects one or two numbers".to_string()),
+    };
+    Ok(Value::Rational(value))
+}
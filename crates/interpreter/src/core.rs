@@ -0,0 +1,774 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use apl_error::RuntimeError;
+use apl_loader::{Module, ModuleLoader};
+use apl_parser::ast::{BinaryOp, Expr, Function, Literal, Part, Pattern, Stmt, UnaryOp};
+use apl_source::{Span, Symbol};
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+use super::builtins::BUILTINS;
+use super::value::{Closure, ModuleValue};
+use super::{Decimal, Environment, Value};
+
+// Deep recursion in the interpreted program would otherwise overflow the
+// interpreter's own stack. Each call takes a few kilobytes of native stack in
+// debug builds, so embedders should run the interpreter on a thread with a
+// large stack, see `STACK_SIZE`.
+const MAX_CALL_DEPTH: usize = 1000;
+
+/// Native stack size that fits `MAX_CALL_DEPTH` nested calls.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+// How control leaves a statement
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+/// A tree-walking interpreter. Top-level statements run in a single
+/// environment that persists between calls to [`Interpreter::execute`].
+pub struct Interpreter {
+    pub(crate) output: Box<dyn Write>,
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    // Evaluated modules by canonical path
+    modules: HashMap<PathBuf, Value>,
+    call_depth: usize,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::with_output(std::io::stdout())
+    }
+
+    /// Creates an interpreter that prints to `output` instead of stdout.
+    pub fn with_output(output: impl Write + 'static) -> Self {
+        let mut globals = Environment::new();
+        for native in BUILTINS {
+            globals.define(native.name, Value::Native(*native));
+        }
+        globals.define("true", Value::Bool(true));
+        globals.define("false", Value::Bool(false));
+
+        let globals = Rc::new(RefCell::new(globals));
+        Self {
+            output: Box::new(output),
+            environment: Rc::new(RefCell::new(Environment::with_enclosing(globals.clone()))),
+            globals,
+            modules: HashMap::new(),
+            call_depth: 0,
+        }
+    }
+
+    /// Names defined before any code runs, for the resolver.
+    pub fn global_names() -> Vec<&'static str> {
+        let mut names = Self::builtin_functions();
+        names.extend(["true", "false"]);
+        names
+    }
+
+    /// The globals that are native functions.
+    pub fn builtin_functions() -> Vec<&'static str> {
+        BUILTINS.iter().map(|native| native.name).collect()
+    }
+
+    /// Runs every module the loader loaded, dependencies first. Modules that
+    /// already ran are skipped.
+    pub fn run(&mut self, loader: &ModuleLoader) -> Result<(), RuntimeError> {
+        for module in loader.modules() {
+            if !self.modules.contains_key(&module.path) {
+                self.run_module(module)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes the imports of `module` available in the top-level environment,
+    /// running the imported modules first if needed. `module` itself isn't run.
+    pub fn import(&mut self, loader: &ModuleLoader, module: &Module) -> Result<(), RuntimeError> {
+        let mut pending: Vec<&PathBuf> = module.imports.values().collect();
+        let mut needed = HashSet::new();
+        while let Some(path) = pending.pop() {
+            if needed.insert(path.clone()) {
+                pending.extend(
+                    loader
+                        .module(path)
+                        .into_iter()
+                        .flat_map(|m| m.imports.values()),
+                );
+            }
+        }
+
+        for dependency in loader.modules() {
+            if needed.contains(&dependency.path) && !self.modules.contains_key(&dependency.path) {
+                self.run_module(dependency)?;
+            }
+        }
+
+        for (alias, path) in &module.imports {
+            let value = self.modules[path].clone();
+            self.environment.borrow_mut().define(*alias, value);
+        }
+        Ok(())
+    }
+
+    fn run_module(&mut self, module: &Module) -> Result<(), RuntimeError> {
+        let mut environment = Environment::with_enclosing(self.globals.clone());
+        for (alias, path) in &module.imports {
+            environment.define(*alias, self.modules[path].clone());
+        }
+
+        let environment = Rc::new(RefCell::new(environment));
+        let previous = std::mem::replace(&mut self.environment, environment.clone());
+        let result = self.execute(&module.statements);
+        self.environment = previous;
+        result?;
+
+        let members = module
+            .exports()
+            .into_iter()
+            .filter_map(|name| Some((name, environment.borrow().get(name)?)))
+            .collect();
+        let name = module
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        self.modules.insert(
+            module.path.clone(),
+            Value::Module(Rc::new(ModuleValue { name, members })),
+        );
+        Ok(())
+    }
+
+    /// Executes statements in the top-level environment.
+    pub fn execute(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        // The parser rejects `break` and `return` outside loops and functions
+        self.statements(statements).map(|_| ())
+    }
+
+    // Functions are hoisted so they can be called before their declaration
+    fn statements(&mut self, statements: &[Stmt]) -> Result<Flow, RuntimeError> {
+        for stmt in statements {
+            if let Stmt::FunctionDecl(function) = stmt {
+                self.define_function(function);
+            }
+        }
+
+        for stmt in statements {
+            match self.statement(stmt)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<Flow, RuntimeError> {
+        let environment = Rc::new(RefCell::new(environment));
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = self.statements(statements);
+        self.environment = previous;
+        result
+    }
+
+    fn scope(&self) -> Environment {
+        Environment::with_enclosing(self.environment.clone())
+    }
+
+    fn define_function(&mut self, function: &Function) {
+        let closure = Closure {
+            function: function.clone(),
+            environment: self.environment.clone(),
+        };
+        self.environment
+            .borrow_mut()
+            .define(function.name.name, Value::Function(Rc::new(closure)));
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<Flow, RuntimeError> {
+        match stmt {
+            Stmt::VariableDecl(decl) => {
+                let value = self.evaluate(&decl.initializer)?;
+                bind(&decl.pattern, value, &mut self.environment.borrow_mut())
+                    .map_err(|message| RuntimeError::new(message, Some(decl.pattern_span)))?;
+            }
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
+            }
+            // Hoisted in `statements`
+            Stmt::FunctionDecl(_) => {}
+            Stmt::For(for_loop) => {
+                let iterable = self.evaluate(&for_loop.iterable)?;
+                let items = match iterable {
+                    Value::Array(values) => values.borrow().clone(),
+                    Value::Tuple(values) => values,
+                    Value::String(s) => s.chars().map(Value::Char).collect(),
+                    other => {
+                        return Err(RuntimeError::new(
+                            format!("Cannot iterate over {}", other.type_name()),
+                            span_of(&for_loop.iterable),
+                        ));
+                    }
+                };
+
+                for item in items {
+                    let mut environment = self.scope();
+                    bind(&for_loop.pattern, item, &mut environment).map_err(|message| {
+                        RuntimeError::new(message, Some(for_loop.pattern_span))
+                    })?;
+
+                    match self.block(&for_loop.body, environment)? {
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => {}
+                        flow => return Ok(flow),
+                    }
+                }
+            }
+            Stmt::If(if_stmt) => {
+                if self.condition(&if_stmt.condition)? {
+                    return self.block(&if_stmt.then_branch, self.scope());
+                } else if let Some(else_branch) = &if_stmt.else_branch {
+                    return self.block(else_branch, self.scope());
+                }
+            }
+            Stmt::While(while_loop) => {
+                while self.condition(&while_loop.condition)? {
+                    match self.block(&while_loop.body, self.scope())? {
+                        Flow::Break => break,
+                        Flow::Normal | Flow::Continue => {}
+                        flow => return Ok(flow),
+                    }
+                }
+            }
+            Stmt::Loop(body) => loop {
+                match self.block(body, self.scope())? {
+                    Flow::Break => break,
+                    Flow::Normal | Flow::Continue => {}
+                    flow => return Ok(flow),
+                }
+            },
+            Stmt::Break => return Ok(Flow::Break),
+            Stmt::Continue => return Ok(Flow::Continue),
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Unit,
+                };
+                return Ok(Flow::Return(value));
+            }
+            // Aliases are defined before the module runs
+            Stmt::Import(_) => {}
+            Stmt::Use(use_decl) => {
+                let module = self.environment.borrow().get(use_decl.module.name);
+                let Some(Value::Module(module)) = module else {
+                    return Err(RuntimeError::new(
+                        format!("'{}' is not a module", use_decl.module),
+                        Some(use_decl.module.span),
+                    ));
+                };
+
+                for name in &use_decl.names {
+                    let value = member(&module, name.name, name.span)?;
+                    self.environment.borrow_mut().define(name.name, value);
+                }
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn condition(&mut self, expr: &Expr) -> Result<bool, RuntimeError> {
+        match self.evaluate(expr)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(RuntimeError::new(
+                format!("Expected a bool condition, found {}", other.type_name()),
+                span_of(expr),
+            )),
+        }
+    }
+
+    /// Evaluates an expression in the top-level environment.
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::Literal(literal) => Ok(literal_value(literal)),
+            Expr::Variable(variable) => self
+                .environment
+                .borrow()
+                .get(variable.name.name)
+                .ok_or_else(|| {
+                    RuntimeError::new(
+                        format!("Undefined variable '{}'", variable.name),
+                        Some(variable.name.span),
+                    )
+                }),
+            Expr::Identifier(name) => {
+                self.environment.borrow().get(*name).ok_or_else(|| {
+                    RuntimeError::new(format!("Undefined variable '{}'", name), None)
+                })
+            }
+            Expr::Binary(binary) => {
+                let left = self.evaluate(&binary.left)?;
+                let right = self.evaluate(&binary.right)?;
+                binary_op(binary.op, left, right)
+                    .map_err(|message| RuntimeError::new(message, span_of(expr)))
+            }
+            Expr::Unary(unary) => {
+                let value = self.evaluate(&unary.right)?;
+                let result = match (unary.op, value) {
+                    (UnaryOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+                    (UnaryOp::Negative, Value::Integer(n)) => Ok(n
+                        .checked_neg()
+                        .map_or_else(|| Value::integer(-BigInt::from(n)), Value::Integer)),
+                    (UnaryOp::Negative, Value::BigInteger(n)) => Ok(Value::integer(-n)),
+                    (UnaryOp::Negative, Value::Decimal(n)) => Ok(Value::Decimal(-&n)),
+                    (UnaryOp::Negative, Value::Rational(n)) => Ok(Value::Rational(-n)),
+                    (UnaryOp::Negative, Value::Float(n)) => Ok(Value::Float(-n)),
+                    (op, value) => Err(format!(
+                        "Cannot apply '{}' to {}",
+                        if op == UnaryOp::Not { "!" } else { "-" },
+                        value.type_name()
+                    )),
+                };
+                result.map_err(|message| RuntimeError::new(message, span_of(expr)))
+            }
+            Expr::Grouping(inner) => self.evaluate(inner),
+            Expr::Assignment { name, value } => {
+                let value = self.evaluate(value)?;
+                if !self
+                    .environment
+                    .borrow_mut()
+                    .assign(name.name, value.clone())
+                {
+                    return Err(RuntimeError::new(
+                        format!("Undefined variable '{}'", name),
+                        Some(name.span),
+                    ));
+                }
+                Ok(value)
+            }
+            Expr::Call { callee, arguments } => {
+                let function = self.evaluate(callee)?;
+                let arguments = self.arguments(arguments)?;
+                self.call(function, arguments, span_of(callee))
+            }
+            Expr::MethodAccess { object, method } => {
+                let Expr::Call { callee, arguments } = &**method else {
+                    unreachable!("the parser only produces calls as methods");
+                };
+                let object = self.evaluate(object)?;
+
+                if let (Value::Module(module), Expr::Variable(name)) = (&object, &**callee) {
+                    let function = member(module, name.name.name, name.name.span)?;
+                    let arguments = self.arguments(arguments)?;
+                    return self.call(function, arguments, Some(name.name.span));
+                }
+
+                // `a.f(b)` calls `f(a, b)`
+                let function = self.evaluate(callee)?;
+                let mut all = vec![object];
+                all.extend(self.arguments(arguments)?);
+                self.call(function, all, span_of(callee))
+            }
+            Expr::PropertyAccess { object, propert } => match self.evaluate(object)? {
+                Value::Module(module) => member(&module, propert.name, propert.span),
+                other => Err(RuntimeError::new(
+                    format!("{} has no property '{}'", other.type_name(), propert),
+                    Some(propert.span),
+                )),
+            },
+            Expr::Array(elements) => Ok(Value::array(self.arguments(elements)?)),
+            Expr::Tuple(elements) => Ok(Value::Tuple(self.arguments(elements)?)),
+            Expr::Index { object, index } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                self.index(object, index)
+                    .map_err(|message| RuntimeError::new(message, span_of(expr)))
+            }
+            // Values are formatted the way `print` shows them
+            Expr::Interpolated { parts, .. } => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        Part::Text(part) => text.push_str(part),
+                        Part::Expr(part) => text.push_str(&self.evaluate(part)?.to_string()),
+                    }
+                }
+                Ok(Value::String(text))
+            }
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[Expr]) -> Result<Vec<Value>, RuntimeError> {
+        arguments.iter().map(|arg| self.evaluate(arg)).collect()
+    }
+
+    fn index(&self, object: Value, index: Value) -> Result<Value, String> {
+        let i = match index {
+            Value::Integer(i) => i,
+            Value::BigInteger(i) => return Err(format!("Index {} is out of bounds", i)),
+            _ => return Err(format!("Index must be an int, found {}", index.type_name())),
+        };
+
+        let value = match &object {
+            Value::Array(values) => usize::try_from(i)
+                .ok()
+                .and_then(|i| values.borrow().get(i).cloned()),
+            Value::Tuple(values) => usize::try_from(i).ok().and_then(|i| values.get(i).cloned()),
+            Value::String(s) => usize::try_from(i)
+                .ok()
+                .and_then(|i| s.chars().nth(i))
+                .map(Value::Char),
+            other => return Err(format!("Cannot index into {}", other.type_name())),
+        };
+
+        value.ok_or_else(|| format!("Index {} is out of bounds", i))
+    }
+
+    pub(crate) fn call(
+        &mut self,
+        function: Value,
+        arguments: Vec<Value>,
+        span: Option<Span>,
+    ) -> Result<Value, RuntimeError> {
+        let arity = match &function {
+            Value::Function(closure) => Some(closure.function.params.len()),
+            Value::Native(native) => native.arity,
+            other => {
+                return Err(RuntimeError::new(
+                    format!("{} is not callable", other.type_name()),
+                    span,
+                ));
+            }
+        };
+        if let Some(arity) = arity.filter(|&arity| arity != arguments.len()) {
+            return Err(RuntimeError::new(
+                format!("Expected {} arguments but got {}", arity, arguments.len()),
+                span,
+            ));
+        }
+
+        match function {
+            Value::Function(closure) => {
+                if self.call_depth == MAX_CALL_DEPTH {
+                    return Err(RuntimeError::new("Maximum call depth exceeded", span));
+                }
+
+                let mut environment = Environment::with_enclosing(closure.environment.clone());
+                for (param, argument) in closure.function.params.iter().zip(arguments) {
+                    environment.define(param.name, argument);
+                }
+
+                self.call_depth += 1;
+                let result = self.block(&closure.function.statements, environment);
+                self.call_depth -= 1;
+
+                match result? {
+                    Flow::Return(value) => Ok(value),
+                    _ => Ok(Value::Unit),
+                }
+            }
+            Value::Native(native) => (native.function)(self, arguments)
+                .map_err(|message| RuntimeError::new(message, span)),
+            _ => unreachable!("checked above"),
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::Integer { value, .. } => Value::Integer(*value),
+        Literal::BigInteger { value, .. } => Value::integer(value.clone()),
+        Literal::Float { value, .. } => Value::Float(*value),
+        Literal::Decimal {
+            mantissa, scale, ..
+        } => Value::Decimal(Decimal::new(mantissa.clone(), *scale)),
+        Literal::String { value, .. } => Value::String(value.clone()),
+        Literal::Char { value, .. } => Value::Char(*value),
+    }
+}
+
+fn member(module: &ModuleValue, name: Symbol, span: Span) -> Result<Value, RuntimeError> {
+    module.members.get(&name).cloned().ok_or_else(|| {
+        RuntimeError::new(
+            format!("Module '{}' has no public member '{}'", module.name, name),
+            Some(span),
+        )
+    })
+}
+
+/// The best span for an expression, built from the identifiers in it.
+fn span_of(expr: &Expr) -> Option<Span> {
+    match expr {
+        Expr::Variable(variable) => Some(variable.name.span),
+        Expr::Assignment { name, .. } => Some(name.span),
+        Expr::PropertyAccess { propert, .. } => Some(propert.span),
+        Expr::Call { callee, .. } => span_of(callee),
+        Expr::MethodAccess { method, .. } => span_of(method),
+        Expr::Unary(unary) => span_of(&unary.right),
+        Expr::Grouping(inner) => span_of(inner),
+        Expr::Index { object, index } => match (span_of(object), span_of(index)) {
+            (Some(start), Some(end)) => Some(start.to(end)),
+            (start, end) => start.or(end),
+        },
+        Expr::Binary(binary) => match (span_of(&binary.left), span_of(&binary.right)) {
+            (Some(start), Some(end)) => Some(start.to(end)),
+            (start, end) => start.or(end),
+        },
+        Expr::Interpolated { span, .. } => Some(*span),
+        Expr::Literal(_) | Expr::Identifier(_) | Expr::Array(_) | Expr::Tuple(_) => None,
+    }
+}
+
+fn binary_op(op: BinaryOp, left: Value, right: Value) -> Result<Value, String> {
+    use std::cmp::Ordering;
+
+    let ordering = match op {
+        BinaryOp::Equal => return Ok(Value::Bool(left == right)),
+        BinaryOp::NotEqual => return Ok(Value::Bool(left != right)),
+        BinaryOp::Greater | BinaryOp::GreaterEqual | BinaryOp::Less | BinaryOp::LessEqual => {
+            match (&left, &right) {
+                (Value::Integer(a), Value::Integer(b)) => a.partial_cmp(b),
+                (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+                (
+                    Value::Integer(_) | Value::BigInteger(_),
+                    Value::Integer(_) | Value::BigInteger(_),
+                ) => left.to_big().partial_cmp(&right.to_big()),
+                (
+                    Value::Integer(_) | Value::BigInteger(_) | Value::Float(_),
+                    Value::Integer(_) | Value::BigInteger(_) | Value::Float(_),
+                ) => left.to_float().partial_cmp(&right.to_float()),
+                (a, b) if a.is_exact() && b.is_exact() => {
+                    a.to_rational().partial_cmp(&b.to_rational())
+                }
+                (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+                (Value::Char(a), Value::Char(b)) => a.partial_cmp(b),
+                _ => return Err(operand_error(op, &left, &right)),
+            }
+        }
+        _ => return arithmetic(op, left, right),
+    };
+
+    // NaN compares false with everything
+    Ok(Value::Bool(ordering.is_some_and(|ordering| match op {
+        BinaryOp::Greater => ordering == Ordering::Greater,
+        BinaryOp::GreaterEqual => ordering != Ordering::Less,
+        BinaryOp::Less => ordering == Ordering::Less,
+        _ => ordering != Ordering::Greater,
+    })))
+}
+
+fn arithmetic(op: BinaryOp, left: Value, right: Value) -> Result<Value, String> {
+    match (&left, &right) {
+        (Value::Integer(a), Value::Integer(b)) => {
+            let result = match op {
+                BinaryOp::Add => a.checked_add(*b),
+                BinaryOp::Subtract => a.checked_sub(*b),
+                BinaryOp::Multiply => a.checked_mul(*b),
+                BinaryOp::Divide | BinaryOp::Modulo if *b == 0 => {
+                    return Err("Division by zero".to_string());
+                }
+                BinaryOp::Divide => a.checked_div(*b),
+                _ => a.checked_rem(*b),
+            };
+            // Results that overflow are promoted instead
+            Ok(result.map_or_else(
+                || big_arithmetic(op, BigInt::from(*a), BigInt::from(*b)),
+                Value::Integer,
+            ))
+        }
+        (Value::Integer(_) | Value::BigInteger(_), Value::Integer(_) | Value::BigInteger(_)) => {
+            let (a, b) = (left.to_big().unwrap(), right.to_big().unwrap());
+            if matches!(op, BinaryOp::Divide | BinaryOp::Modulo) && b.is_zero() {
+                return Err("Division by zero".to_string());
+            }
+            Ok(big_arithmetic(op, a, b))
+        }
+        (a, b) if a.is_exact() && b.is_exact() => exact_arithmetic(op, a, b),
+        (
+            Value::Integer(_) | Value::BigInteger(_) | Value::Float(_),
+            Value::Integer(_) | Value::BigInteger(_) | Value::Float(_),
+        ) => {
+            let a = left.to_float().unwrap();
+            let b = right.to_float().unwrap();
+            Ok(Value::Float(match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Subtract => a - b,
+                BinaryOp::Multiply => a * b,
+                BinaryOp::Divide => a / b,
+                _ => a % b,
+            }))
+        }
+        (Value::String(a), Value::String(b)) if op == BinaryOp::Add => {
+            Ok(Value::String(format!("{}{}", a, b)))
+        }
+        (Value::Array(a), Value::Array(b)) if op == BinaryOp::Add => {
+            let mut values = a.borrow().clone();
+            values.extend(b.borrow().iter().cloned());
+            Ok(Value::array(values))
+        }
+        _ => Err(operand_error(op, &left, &right)),
+    }
+}
+
+fn big_arithmetic(op: BinaryOp, a: BigInt, b: BigInt) -> Value {
+    Value::integer(match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Subtract => a - b,
+        BinaryOp::Multiply => a * b,
+        BinaryOp::Divide => a / b,
+        _ => a % b,
+    })
+}
+
+// Arithmetic on a decimal or rational and another exact number, which is
+// exact. Ints are converted to the other operand's type, and decimals to
+// rationals.
+fn exact_arithmetic(op: BinaryOp, left: &Value, right: &Value) -> Result<Value, String> {
+    if matches!(left, Value::Rational(_)) || matches!(right, Value::Rational(_)) {
+        let (a, b) = (left.to_rational().unwrap(), right.to_rational().unwrap());
+        if matches!(op, BinaryOp::Divide | BinaryOp::Modulo) && b.is_zero() {
+            return Err("Division by zero".to_string());
+        }
+        return Ok(Value::Rational(match op {
+            BinaryOp::Add => a + b,
+            BinaryOp::Subtract => a - b,
+            BinaryOp::Multiply => a * b,
+            BinaryOp::Divide => a / b,
+            _ => a % b,
+        }));
+    }
+
+    let (a, b) = (left.to_decimal().unwrap(), right.to_decimal().unwrap());
+    if matches!(op, BinaryOp::Divide | BinaryOp::Modulo) && b.is_zero() {
+        return Err("Division by zero".to_string());
+    }
+    Ok(Value::Decimal(match op {
+        BinaryOp::Add => &a + &b,
+        BinaryOp::Subtract => &a - &b,
+        BinaryOp::Multiply => &a * &b,
+        BinaryOp::Divide => &a / &b,
+        _ => &a % &b,
+    }))
+}
+
+fn operand_error(op: BinaryOp, left: &Value, right: &Value) -> String {
+    let symbol = match op {
+        BinaryOp::Add => "+",
+        BinaryOp::Subtract => "-",
+        BinaryOp::Multiply => "*",
+        BinaryOp::Divide => "/",
+        BinaryOp::Modulo => "%",
+        BinaryOp::Equal => "==",
+        BinaryOp::NotEqual => "!=",
+        BinaryOp::Greater => ">",
+        BinaryOp::GreaterEqual => ">=",
+        BinaryOp::Less => "<",
+        BinaryOp::LessEqual => "<=",
+    };
+    format!(
+        "Cannot apply '{}' to {} and {}",
+        symbol,
+        left.type_name(),
+        right.type_name()
+    )
+}
+
+/// Binds the names in `pattern` to the matching parts of `value`.
+fn bind(pattern: &Pattern, value: Value, environment: &mut Environment) -> Result<(), String> {
+    match pattern {
+        Pattern::Identifier(name) => environment.define(name.name, value),
+        Pattern::Wildcard => {}
+        Pattern::Literal(literal) => {
+            if literal_value(literal) != value {
+                return Err(format!("Pattern does not match {}", value.repr()));
+            }
+        }
+        Pattern::Array(patterns) => match value {
+            Value::Array(values) => {
+                let values = values.borrow().clone();
+                bind_sequence(patterns, values, environment, Value::array)?;
+            }
+            other => return Err(format!("Expected an array, found {}", other.type_name())),
+        },
+        Pattern::Tuple(patterns) => match value {
+            Value::Tuple(values) => bind_sequence(patterns, values, environment, Value::Tuple)?,
+            other => return Err(format!("Expected a tuple, found {}", other.type_name())),
+        },
+        Pattern::Struct { name, .. } => {
+            return Err(format!(
+                "Cannot destructure {} as '{}'",
+                value.type_name(),
+                name
+            ));
+        }
+        // The parser only allows rest patterns inside arrays and tuples
+        Pattern::Rest(_) => unreachable!("rest pattern outside a sequence"),
+    }
+
+    Ok(())
+}
+
+fn bind_sequence(
+    patterns: &[Pattern],
+    mut values: Vec<Value>,
+    environment: &mut Environment,
+    collect: fn(Vec<Value>) -> Value,
+) -> Result<(), String> {
+    let Some(rest) = patterns.iter().position(|p| matches!(p, Pattern::Rest(_))) else {
+        if patterns.len() != values.len() {
+            return Err(format!(
+                "Expected {} elements, found {}",
+                patterns.len(),
+                values.len()
+            ));
+        }
+        for (pattern, value) in patterns.iter().zip(values) {
+            bind(pattern, value, environment)?;
+        }
+        return Ok(());
+    };
+
+    let after = patterns.len() - rest - 1;
+    if values.len() < rest + after {
+        return Err(format!(
+            "Expected at least {} elements, found {}",
+            rest + after,
+            values.len()
+        ));
+    }
+
+    let tail = values.split_off(values.len() - after);
+    let middle = values.split_off(rest);
+
+    for (pattern, value) in patterns[..rest].iter().zip(values) {
+        bind(pattern, value, environment)?;
+    }
+    if let Pattern::Rest(Some(name)) = &patterns[rest] {
+        environment.define(name.name, collect(middle));
+    }
+    for (pattern, value) in patterns[rest + 1..].iter().zip(tail) {
+        bind(pattern, value, environment)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+mod builtins;
+mod core;
+mod environment;
+mod number;
+mod value;
+
+pub use core::{Interpreter, STACK_SIZE};
+pub use environment::Environment;
+pub use number::Decimal;
+pub use value::Value;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apl_error::RuntimeError;
+    use apl_parser::core::Parser;
+    use apl_scanner::Scanner;
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::rc::Rc;
+
+    // Collects everything the interpreter prints
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run(source: &str) -> Result<String, RuntimeError> {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .try_parse()
+            .unwrap();
+
+        let output = Output::default();
+        let mut interpreter = Interpreter::with_output(output.clone());
+        interpreter.execute(&statements)?;
+
+        let printed = output.0.borrow().clone();
+        Ok(String::from_utf8(printed).unwrap())
+    }
+
+    #[test]
+    fn arithmetic_and_printing() {
+        assert_eq!(run("print(1 + 2 * 3, 7 / 2, 7 % 2);").unwrap(), "7 3 1\n");
+        assert_eq!(run("print(1.5 * 2, -(2 - 3));").unwrap(), "3.0 1\n");
+        assert_eq!(
+            run(r#"print("a" + "b", 'c', [1, "x"], (1,));"#).unwrap(),
+            "ab c [1, \"x\"] (1,)\n"
+        );
+        assert_eq!(
+            run("print(1 < 2, 2 == 2.0, !true);").unwrap(),
+            "true true false\n"
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        let source = r#"
+let total = 0;
+for i in range(10) {
+    if i == 3 {
+        continue;
+    } else if i == 6 {
+        break;
+    }
+    total = total + i;
+}
+let n = 0;
+while n < 3 {
+    n = n + 1;
+}
+loop {
+    n = n * 2;
+    if n > 20 {
+        break;
+    }
+}
+print(total, n);
+"#;
+        assert_eq!(run(source).unwrap(), "12 24\n");
+    }
+
+    #[test]
+    fn functions_and_closures() {
+        let source = r#"
+print(fib(15));
+fn fib(n) {
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+
+fn counter() {
+    let count = [0];
+    fn next() {
+        count.push(len(count));
+        return len(count);
+    }
+    return next;
+}
+let next = counter();
+next();
+print(next(), "abc".len(), type_of(next));
+"#;
+        assert_eq!(run(source).unwrap(), "610\n3 3 function\n");
+    }
+
+    #[test]
+    fn destructuring() {
+        let source = r#"
+let [first, ..middle, last] = range(5);
+let (a, (b, _)) = (1, ("two", 3));
+for (k, v) in [(1, 'x'), (2, 'y')] {
+    print(k, v);
+}
+print(first, middle, last, a, b);
+"#;
+        assert_eq!(run(source).unwrap(), "1 x\n2 y\n0 [1, 2, 3] 4 1 two\n");
+    }
+
+    #[test]
+    fn string_interpolation() {
+        let source = r#"
+let name = "Ada";
+let n = 2;
+print("Hello ${name}, you have ${n + 1} items");
+print("${[1, "a"]} ${0.5d * 3} ${"nested ${name}"} \${n}");
+"#;
+        assert_eq!(
+            run(source).unwrap(),
+            "Hello Ada, you have 3 items\n[1, \"a\"] 1.5 nested Ada ${n}\n"
+        );
+        assert!(run(r#"print("${missing}");"#).is_err());
+
+        // Errors point at the whole string
+        let error = run(r#"print("n: ${1}" - 1);"#).unwrap_err();
+        let span = error.span.unwrap();
+        assert_eq!((span.start, span.end), (6, 15));
+    }
+
+    #[test]
+    fn big_integers() {
+        let source = r#"
+let max = 9223372036854775807;
+print(2147483647 + 1, max + 1, -max - 1, -(-max - 1));
+let big = max * max;
+print(big, big / max == max, big - big + 1 == 1, type_of(big));
+print(big > max, -big < 0.0, 0x1_0000_0000_0000_0000 % 10);
+"#;
+        assert_eq!(
+            run(source).unwrap(),
+            "2147483648 9223372036854775808 -9223372036854775808 9223372036854775808\n\
+             85070591730234615847396907784232501249 true true int\n\
+             true true 6\n"
+        );
+    }
+
+    #[test]
+    fn exact_numbers() {
+        let source = r#"
+print(1.10d + 2d, 0.1d + 0.2d == 0.3d, 1.10d * 3, type_of(1.10d));
+print(1d / 3d, 10.00d / 4, 7.5d % 2, -1.10d);
+let third = rational(1, 3);
+print(third, third * 3, third + 0.5d, type_of(third * 3));
+print(third < 0.34d, rational(0.1) == 0.1d, 1 == 1.0d, [1.10d, third]);
+print(int(-7.9d), int(rational(7, 2)), float(0.5d), decimal(0.1), decimal(third));
+"#;
+        assert_eq!(
+            run(source).unwrap(),
+            "3.10 true 3.30 decimal\n\
+             0.3333333333333333333333333333 2.50 1.5 -1.10\n\
+             1/3 1 5/6 rational\n\
+             true true true [1.10d, 1/3]\n\
+             -7 3 0.5 0.1 0.3333333333333333333333333333\n"
+        );
+
+        let error = |source: &str| run(source).unwrap_err().message;
+        assert_eq!(
+            error("print(1.5d + 1.0);"),
+            "Cannot apply '+' to decimal and float"
+        );
+        assert_eq!(error("print(1d / 0);"), "Division by zero");
+        assert_eq!(error("print(rational(1, 0));"), "Division by zero");
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let error = |source: &str| run(source).unwrap_err();
+
+        assert_eq!(error("print(1 / 0);").message, "Division by zero");
+        assert_eq!(
+            error("print(0x1_0000_0000_0000_0000 % 0);").message,
+            "Division by zero"
+        );
+        assert_eq!(
+            error("print(range(9223372036854775808));").message,
+            "range() bounds must fit in 64 bits"
+        );
+        assert_eq!(
+            error("let [a, b] = [1];").message,
+            "Expected 2 elements, found 1"
+        );
+        assert_eq!(error("print([1][3]);").message, "Index 3 is out of bounds");
+        assert_eq!(
+            error("fn f(a) {} f();").message,
+            "Expected 1 arguments but got 0"
+        );
+
+        let recursion = std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(|| run("fn f() { return f(); } f();").unwrap_err().message)
+            .unwrap();
+        assert_eq!(recursion.join().unwrap(), "Maximum call depth exceeded");
+
+        // Destructuring errors point at the whole pattern
+        for source in [
+            "let [a, b] = [1];",
+            "let [_, _] = [1];",
+            "for (a, b) in [1] {}",
+        ] {
+            let span = error(source).span.unwrap();
+            assert_eq!((span.start, span.end), (4, 10), "{}", source);
+        }
+
+        // Errors point at the identifiers involved
+        let error = error("let a = 1; let b = \"s\"; print(a - b);");
+        assert_eq!(error.message, "Cannot apply '-' to int and string");
+        let span = error.span.unwrap();
+        assert_eq!((span.start, span.end), (30, 35));
+    }
+}
@@ -0,0 +1,205 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use super::Value;
+
+// How many digits after the decimal point a quotient that doesn't end is
+// rounded to
+const DIVISION_SCALE: u32 = 28;
+
+/// An exact decimal number, `mantissa / 10^scale`. The scale is kept, so
+/// `1.10d` prints as `1.10`, and results have the largest scale of their
+/// operands like in `1.10d + 2d == 3.10d`.
+#[derive(Clone)]
+pub struct Decimal {
+    mantissa: BigInt,
+    scale: u32,
+}
+
+impl Decimal {
+    pub fn new(mantissa: BigInt, scale: u32) -> Self {
+        Self { mantissa, scale }
+    }
+
+    /// The shortest decimal that reads back as `value`, so `0.1` is `0.1d`.
+    pub fn from_float(value: f32) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+
+        // Display never uses exponents
+        let text = value.to_string();
+        let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+        let mantissa = format!("{}{}", whole, fraction).parse().ok()?;
+        Some(Self::new(mantissa, fraction.len() as u32))
+    }
+
+    /// `value` rounded half to even to at most `DIVISION_SCALE` digits after
+    /// the decimal point, or `scale` if that's more. Trailing zeros are only
+    /// kept down to `scale`.
+    pub fn from_rational(value: &BigRational, scale: u32) -> Self {
+        let max_scale = scale.max(DIVISION_SCALE);
+        let scaled = value * BigRational::from_integer(pow10(max_scale));
+
+        let floor = scaled.floor();
+        let rounded = match (&scaled - &floor).cmp(&BigRational::new(1.into(), 2.into())) {
+            Ordering::Greater => floor + BigRational::one(),
+            Ordering::Equal if floor.to_integer().bit(0) => floor + BigRational::one(),
+            _ => floor,
+        };
+
+        let mut decimal = Self::new(rounded.to_integer(), max_scale);
+        let ten = BigInt::from(10);
+        while decimal.scale > scale && (&decimal.mantissa % &ten).is_zero() {
+            decimal.mantissa /= &ten;
+            decimal.scale -= 1;
+        }
+        decimal
+    }
+
+    pub fn to_rational(&self) -> BigRational {
+        BigRational::new(self.mantissa.clone(), pow10(self.scale))
+    }
+
+    pub fn to_float(&self) -> f32 {
+        self.to_string().parse().unwrap_or(f32::NAN)
+    }
+
+    /// The integer part, rounding toward zero.
+    pub fn trunc(&self) -> BigInt {
+        &self.mantissa / pow10(self.scale)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    // The mantissa at a scale no smaller than this one's
+    fn rescaled(&self, scale: u32) -> BigInt {
+        &self.mantissa * pow10(scale - self.scale)
+    }
+}
+
+impl Add for &Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal::new(self.rescaled(scale) + other.rescaled(scale), scale)
+    }
+}
+
+impl Sub for &Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal::new(self.rescaled(scale) - other.rescaled(scale), scale)
+    }
+}
+
+impl Mul for &Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: &Decimal) -> Decimal {
+        Decimal::new(&self.mantissa * &other.mantissa, self.scale + other.scale)
+    }
+}
+
+/// Rounded as by `Decimal::from_rational` when the quotient doesn't end.
+/// Panics when dividing by zero.
+impl Div for &Decimal {
+    type Output = Decimal;
+
+    fn div(self, other: &Decimal) -> Decimal {
+        let quotient = self.to_rational() / other.to_rational();
+        Decimal::from_rational(&quotient, self.scale.max(other.scale))
+    }
+}
+
+/// The remainder of dividing toward zero, which has the sign of the
+/// dividend. Panics when dividing by zero.
+impl Rem for &Decimal {
+    type Output = Decimal;
+
+    fn rem(self, other: &Decimal) -> Decimal {
+        let scale = self.scale.max(other.scale);
+        Decimal::new(self.rescaled(scale) % other.rescaled(scale), scale)
+    }
+}
+
+impl Neg for &Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal::new(-&self.mantissa, self.scale)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.mantissa.abs().to_string();
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+
+        let sign = if self.mantissa.is_negative() { "-" } else { "" };
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, whole)
+        } else {
+            write!(f, "{}{}.{}", sign, whole, fraction)
+        }
+    }
+}
+
+fn pow10(exponent: u32) -> BigInt {
+    num_traits::pow(BigInt::from(10), exponent as usize)
+}
+
+impl Value {
+    pub(crate) fn is_exact(&self) -> bool {
+        matches!(
+            self,
+            Value::Integer(_) | Value::BigInteger(_) | Value::Decimal(_) | Value::Rational(_)
+        )
+    }
+
+    pub(crate) fn to_big(&self) -> Option<BigInt> {
+        match self {
+            Value::Integer(n) => Some(BigInt::from(*n)),
+            Value::BigInteger(n) => Some(n.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn to_decimal(&self) -> Option<Decimal> {
+        match self {
+            Value::Decimal(n) => Some(n.clone()),
+            _ => self.to_big().map(|n| Decimal::new(n, 0)),
+        }
+    }
+
+    pub(crate) fn to_rational(&self) -> Option<BigRational> {
+        match self {
+            Value::Rational(n) => Some(n.clone()),
+            Value::Decimal(n) => Some(n.to_rational()),
+            _ => self.to_big().map(BigRational::from_integer),
+        }
+    }
+
+    pub(crate) fn to_float(&self) -> Option<f32> {
+        match self {
+            Value::Integer(n) => Some(*n as f32),
+            Value::BigInteger(n) => n.to_f32(),
+            Value::Float(n) => Some(*n),
+            Value::Decimal(n) => Some(n.to_float()),
+            Value::Rational(n) => n.to_f32(),
+            _ => None,
+        }
+    }
+}
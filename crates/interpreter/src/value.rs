@@ -0,0 +1,155 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use apl_parser::ast::Function;
+use apl_source::Symbol;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+
+use super::{Decimal, Environment, Interpreter};
+
+pub type NativeFn = fn(&mut Interpreter, Vec<Value>) -> Result<Value, String>;
+
+#[derive(Clone)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Integer(i64),
+    // Integers that don't fit in an `i64`, see `Value::integer`
+    BigInteger(BigInt),
+    Float(f32),
+    Decimal(Decimal),
+    // Made by `rational()`, stays a rational even when it's a whole number
+    Rational(BigRational),
+    String(String),
+    Char(char),
+    // Arrays are shared, so `push(xs, 1)` is visible through every alias of `xs`
+    Array(Rc<RefCell<Vec<Value>>>),
+    Tuple(Vec<Value>),
+    Function(Rc<Closure>),
+    Native(Native),
+    Module(Rc<ModuleValue>),
+}
+
+pub struct Closure {
+    pub function: Function,
+    pub environment: Rc<RefCell<Environment>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct Native {
+    pub name: &'static str,
+    // `None` for variadic functions
+    pub arity: Option<usize>,
+    pub function: NativeFn,
+}
+
+pub struct ModuleValue {
+    pub name: String,
+    pub members: HashMap<Symbol, Value>,
+}
+
+impl Value {
+    pub fn array(values: Vec<Value>) -> Self {
+        Value::Array(Rc::new(RefCell::new(values)))
+    }
+
+    /// An int, which is only a `BigInteger` when it doesn't fit in an `i64`.
+    pub fn integer(n: BigInt) -> Self {
+        i64::try_from(&n).map_or_else(|_| Value::BigInteger(n), Value::Integer)
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Unit => "unit",
+            Value::Bool(_) => "bool",
+            Value::Integer(_) | Value::BigInteger(_) => "int",
+            Value::Float(_) => "float",
+            Value::Decimal(_) => "decimal",
+            Value::Rational(_) => "rational",
+            Value::String(_) => "string",
+            Value::Char(_) => "char",
+            Value::Array(_) => "array",
+            Value::Tuple(_) => "tuple",
+            Value::Function(_) | Value::Native(_) => "function",
+            Value::Module(_) => "module",
+        }
+    }
+
+    /// The value as it would be written in source, e.g. strings are quoted.
+    pub fn repr(&self) -> String {
+        match self {
+            Value::String(s) => format!("{:?}", s),
+            Value::Char(c) => format!("{:?}", c),
+            Value::Decimal(n) => format!("{}d", n),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |values: &[Value]| {
+            values
+                .iter()
+                .map(Value::repr)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Integer(n) => write!(f, "{}", n),
+            Value::BigInteger(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{:?}", n),
+            Value::Decimal(n) => write!(f, "{}", n),
+            Value::Rational(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Char(c) => write!(f, "{}", c),
+            Value::Array(values) => write!(f, "[{}]", list(&values.borrow())),
+            Value::Tuple(values) if values.len() == 1 => write!(f, "({},)", values[0].repr()),
+            Value::Tuple(values) => write!(f, "({})", list(values)),
+            Value::Function(closure) => write!(f, "<fn {}>", closure.function.name),
+            Value::Native(native) => write!(f, "<builtin {}>", native.name),
+            Value::Module(module) => write!(f, "<module {}>", module.name),
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.repr())
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Unit, Value::Unit) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::BigInteger(a), Value::BigInteger(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Integer(a), Value::Float(b)) | (Value::Float(b), Value::Integer(a)) => {
+                *a as f32 == *b
+            }
+            (Value::BigInteger(a), Value::Float(b)) | (Value::Float(b), Value::BigInteger(a)) => {
+                a.to_f32() == Some(*b)
+            }
+            // Decimals and rationals equal any exact number of the same value
+            (a, b) if a.is_exact() && b.is_exact() => a.to_rational() == b.to_rational(),
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Char(a), Value::Char(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => *a.borrow() == *b.borrow(),
+            (Value::Tuple(a), Value::Tuple(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => a.name == b.name,
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use apl_database::Database;
+use apl_error::ModuleError;
+use apl_parser::{ast::Stmt, core::Parser};
+use apl_scanner::Scanner;
+use apl_source::{FileId, SourceMap};
+
+use super::{Exports, Imports, Module};
+
+/// Loads modules and their imports into a `Database`, checking that the
+/// imports exist, aren't circular and name what the modules export.
+pub struct ModuleLoader {
+    database: Database,
+    modules: HashMap<PathBuf, Module>,
+    // Modules in the order they finished loading, dependencies first
+    order: Vec<PathBuf>,
+    // Modules currently being loaded, used for cycle detection
+    stack: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn new() -> Self {
+        Self {
+            database: Database::new(),
+            modules: HashMap::new(),
+            order: Vec::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Loads the module at `path` and everything it imports.
+    pub fn load(&mut self, path: &Path) -> Result<&Module, ModuleError> {
+        let path = self.load_module(path, None)?;
+        Ok(&self.modules[&path])
+    }
+
+    /// Loads a module from `source` rather than a file, e.g. from stdin.
+    /// Its imports are resolved relative to the current directory.
+    pub fn load_source(&mut self, name: &str, source: String) -> Result<&Module, ModuleError> {
+        let path = PathBuf::from(name);
+
+        self.stack.push(path.clone());
+        let module = self.parse_source(&path, source);
+        self.stack.pop();
+
+        self.modules.insert(path.clone(), module?);
+        self.order.push(path.clone());
+
+        Ok(&self.modules[&path])
+    }
+
+    /// Loads a module like [`ModuleLoader::load_source`] from what `reader`
+    /// reads, parsing it as it's read, e.g. from stdin.
+    pub fn load_reader(
+        &mut self,
+        name: &str,
+        reader: impl BufRead,
+    ) -> Result<&Module, ModuleError> {
+        let path = PathBuf::from(name);
+        let file = self
+            .database
+            .read_source(name, reader)
+            .map_err(|e| ModuleError::Io {
+                path: path.clone(),
+                message: e.to_string(),
+            })?;
+
+        self.stack.push(path.clone());
+        let module = self.parse_file(&path, file);
+        self.stack.pop();
+
+        self.modules.insert(path.clone(), module?);
+        self.order.push(path.clone());
+
+        Ok(&self.modules[&path])
+    }
+
+    /// Loads the module at `path` from `source` instead of reading the file,
+    /// e.g. a file with unsaved changes in an editor. Its imports are read
+    /// from disk, relative to `path`.
+    pub fn load_file_source(
+        &mut self,
+        path: &Path,
+        source: String,
+    ) -> Result<&Module, ModuleError> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+        self.stack.push(path.clone());
+        let module = self.parse_source(&path, source);
+        self.stack.pop();
+
+        self.modules.insert(path.clone(), module?);
+        self.order.push(path.clone());
+
+        Ok(&self.modules[&path])
+    }
+
+    /// Every file read while loading, used to render diagnostics.
+    pub fn source_map(&self) -> &SourceMap {
+        self.database.source_map()
+    }
+
+    /// The database the modules were parsed into, for running queries on
+    /// them.
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    pub fn database_mut(&mut self) -> &mut Database {
+        &mut self.database
+    }
+
+    pub fn module(&self, path: &Path) -> Option<&Module> {
+        self.modules.get(path)
+    }
+
+    /// All loaded modules, each one after the modules it imports.
+    pub fn modules(&self) -> impl Iterator<Item = &Module> {
+        self.order.iter().map(|path| &self.modules[path])
+    }
+
+    fn load_module(
+        &mut self,
+        path: &Path,
+        importer: Option<&Path>,
+    ) -> Result<PathBuf, ModuleError> {
+        let path = path.canonicalize().map_err(|_| ModuleError::NotFound {
+            path: path.to_path_buf(),
+            importer: importer.map(Path::to_path_buf),
+        })?;
+
+        if let Some(start) = self.stack.iter().position(|p| *p == path) {
+            let mut chain = self.stack[start..].to_vec();
+            chain.push(path);
+            return Err(ModuleError::Cycle { chain });
+        }
+
+        if self.modules.contains_key(&path) {
+            return Ok(path);
+        }
+
+        self.stack.push(path.clone());
+        let module = self.parse_module(&path);
+        self.stack.pop();
+
+        self.modules.insert(path.clone(), module?);
+        self.order.push(path.clone());
+
+        Ok(path)
+    }
+
+    fn parse_module(&mut self, path: &Path) -> Result<Module, ModuleError> {
+        let source = fs::read_to_string(path).map_err(|e| ModuleError::Io {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+
+        self.parse_source(path, source)
+    }
+
+    fn parse_source(&mut self, path: &Path, source: String) -> Result<Module, ModuleError> {
+        let file = self
+            .database
+            .load_source(path.display().to_string(), source);
+        self.parse_file(path, file)
+    }
+
+    fn parse_file(&mut self, path: &Path, file: FileId) -> Result<Module, ModuleError> {
+        let tree = self.database.syntax(file);
+
+        if !tree.lex_errors().is_empty() {
+            return Err(ModuleError::Lex {
+                path: path.to_path_buf(),
+                errors: tree.lex_errors().to_vec(),
+            });
+        }
+        if tree.parse_errors().next().is_some() {
+            // Reported as a parse that stops at the first error would, since
+            // recovering reports blocks left open differently
+            let mut scanner = Scanner::with_file(tree.source(), file);
+            let error = Parser::new(&mut scanner)
+                .try_parse()
+                .expect_err("the module has parse errors");
+            return Err(ModuleError::Parse {
+                path: path.to_path_buf(),
+                error,
+            });
+        }
+        let statements = tree.statements().to_vec();
+
+        let mut imports = HashMap::new();
+
+        for (alias, import_path) in self.database.get::<Imports>(&file).iter() {
+            let imported = self.load_module(import_path, Some(path))?;
+
+            if imports.insert(*alias, imported).is_some() {
+                return Err(ModuleError::DuplicateAlias {
+                    path: path.to_path_buf(),
+                    alias: alias.to_string(),
+                });
+            }
+        }
+
+        for stmt in &statements {
+            if let Stmt::Use(use_decl) = stmt {
+                let module = imports.get(&use_decl.module.name).ok_or_else(|| {
+                    ModuleError::UnknownModule {
+                        path: path.to_path_buf(),
+                        alias: use_decl.module.to_string(),
+                    }
+                })?;
+
+                let exports = self.database.get::<Exports>(&self.modules[module].file);
+                if let Some(name) = use_decl
+                    .names
+                    .iter()
+                    .find(|n| !exports.contains(&n.name))
+                {
+                    return Err(ModuleError::NotExported {
+                        path: path.to_path_buf(),
+                        module: module.clone(),
+                        name: name.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(Module {
+            path: path.to_path_buf(),
+            file,
+            statements,
+            imports,
+        })
+    }
+}
+
+impl Default for ModuleLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,127 @@
+mod core;
+mod module;
+mod queries;
+
+pub use core::ModuleLoader;
+pub use module::Module;
+pub use queries::{Exports, Imports};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apl_error::ModuleError;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Creates a fresh directory containing the given files
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("apl_loader_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for (path, source) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn load_relative_imports() {
+        let dir = write_files(
+            "relative",
+            &[
+                ("main.apl", "import \"lib/math.apl\" as m;\nuse m.{add, pi};"),
+                ("lib/math.apl", "import \"consts.apl\" as c;\npub fn add(a, b) {}\npub let pi = 3;"),
+                ("lib/consts.apl", "pub let [one, two] = pair;"),
+            ],
+        );
+
+        let mut loader = ModuleLoader::new();
+        let main = loader.load(&dir.join("main.apl")).unwrap();
+        assert_eq!(
+            main.imports[&"m".into()],
+            dir.join("lib/math.apl").canonicalize().unwrap()
+        );
+
+        // Dependencies are loaded before their importers
+        let order: Vec<_> = loader
+            .modules()
+            .map(|m| m.path.file_name().unwrap().to_owned())
+            .collect();
+        assert_eq!(order, vec!["consts.apl", "math.apl", "main.apl"]);
+
+        let consts = loader
+            .module(&dir.join("lib/consts.apl").canonicalize().unwrap())
+            .unwrap();
+        assert_eq!(consts.exports(), vec!["one", "two"]);
+    }
+
+    #[test]
+    fn lex_errors_point_into_imported_file() {
+        let dir = write_files(
+            "lex",
+            &[
+                ("main.apl", "import \"lib.apl\" as l;"),
+                ("lib.apl", "fn f() {}\npub let x = @;"),
+            ],
+        );
+
+        let mut loader = ModuleLoader::new();
+        let errors = match loader.load(&dir.join("main.apl")) {
+            Err(ModuleError::Lex { errors, .. }) => errors,
+            other => panic!("Expected lex error, got {:?}", other),
+        };
+
+        let source_map = loader.source_map();
+        let location = source_map.format_location(errors[0].span);
+        assert!(location.ends_with("lib.apl:2:13"), "{}", location);
+        assert_eq!(source_map.snippet(errors[0].span), "pub let x = @;");
+    }
+
+    #[test]
+    fn import_cycle() {
+        let dir = write_files(
+            "cycle",
+            &[
+                ("a.apl", "import \"b.apl\" as b;"),
+                ("b.apl", "import \"a.apl\" as a;"),
+            ],
+        );
+
+        match ModuleLoader::new().load(&dir.join("a.apl")) {
+            Err(ModuleError::Cycle { chain }) => {
+                let names: Vec<_> = chain.iter().map(|p| p.file_name().unwrap()).collect();
+                assert_eq!(names, vec!["a.apl", "b.apl", "a.apl"]);
+            }
+            other => panic!("Expected import cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn use_errors() {
+        let dir = write_files(
+            "use",
+            &[
+                ("private.apl", "import \"lib.apl\" as l;\nuse l.secret;"),
+                ("unknown.apl", "use l.shared;"),
+                ("missing.apl", "import \"nope.apl\" as n;"),
+                ("lib.apl", "fn secret() {}\npub fn shared() {}"),
+            ],
+        );
+
+        let mut loader = ModuleLoader::new();
+        assert!(matches!(
+            loader.load(&dir.join("private.apl")),
+            Err(ModuleError::NotExported { name, .. }) if name == "secret"
+        ));
+        assert!(matches!(
+            loader.load(&dir.join("unknown.apl")),
+            Err(ModuleError::UnknownModule { alias, .. }) if alias == "l"
+        ));
+        assert!(matches!(
+            loader.load(&dir.join("missing.apl")),
+            Err(ModuleError::NotFound { importer: Some(_), .. })
+        ));
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use apl_parser::ast::{Stmt, Visibility};
+use apl_source::{FileId, Symbol};
+
+#[derive(Debug)]
+pub struct Module {
+    // Canonical path of the source file
+    pub path: PathBuf,
+    pub file: FileId,
+    pub statements: Vec<Stmt>,
+    // Import alias to the canonical path of the imported module
+    pub imports: HashMap<Symbol, PathBuf>,
+}
+
+impl Module {
+    /// Names declared `pub` at the top level of the module.
+    pub fn exports(&self) -> Vec<Symbol> {
+        exports(&self.statements)
+    }
+}
+
+pub(crate) fn exports(statements: &[Stmt]) -> Vec<Symbol> {
+    let mut exports = Vec::new();
+
+    for stmt in statements {
+        match stmt {
+            Stmt::FunctionDecl(function) if function.visibility == Visibility::Public => {
+                exports.push(function.name.name);
+            }
+            Stmt::VariableDecl(decl) if decl.visibility == Visibility::Public => {
+                exports.extend(
+                    decl.pattern
+                        .bindings()
+                        .into_iter()
+                        .map(|name| name.name),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    exports
+}
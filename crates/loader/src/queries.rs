@@ -0,0 +1,44 @@
+use std::path::{Path, PathBuf};
+
+use apl_database::{Database, Query};
+use apl_parser::ast::Stmt;
+use apl_source::{FileId, Symbol};
+
+use super::module::exports;
+
+/// The names a file declares `pub` at the top level.
+pub struct Exports;
+
+impl Query for Exports {
+    type Key = FileId;
+    type Value = Vec<Symbol>;
+
+    fn execute(db: &Database, file: &FileId) -> Vec<Symbol> {
+        exports(db.syntax(*file).statements())
+    }
+}
+
+/// The aliases a file imports modules as and their paths, which are relative
+/// to the importing file.
+pub struct Imports;
+
+impl Query for Imports {
+    type Key = FileId;
+    type Value = Vec<(Symbol, PathBuf)>;
+
+    fn execute(db: &Database, file: &FileId) -> Vec<(Symbol, PathBuf)> {
+        let name = db.source_map().file(*file).name();
+        let directory = Path::new(name).parent().unwrap_or(Path::new(""));
+
+        db.syntax(*file)
+            .statements()
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Import(import) => {
+                    Some((import.alias.name, directory.join(&import.path)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,431 @@
+use std::ops::Range;
+use std::rc::Rc;
+
+use apl_database::Database;
+use apl_error::Diagnostic;
+use apl_interpreter::Interpreter;
+use apl_loader::Imports;
+use apl_parser::SyntaxTree;
+use apl_parser::ast::{Function, Ident, Pattern, Stmt, Visibility};
+use apl_resolver::{Globals, Reference, Resolution, Resolve};
+use apl_scanner::TokenType;
+use apl_source::{FileId, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Variable,
+}
+
+/// A function or variable declaration, with the declarations inside a
+/// function as its children.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    // The whole declaration, and just its name
+    pub span: Span,
+    pub name_span: Span,
+    pub children: Vec<Symbol>,
+}
+
+// A declared name and the part of the document it can be used in
+#[derive(Debug, Clone)]
+pub(crate) struct Binding {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub span: Span,
+    // What hovering the name shows
+    pub detail: String,
+    // Parameter names, for functions
+    pub params: Vec<String>,
+    pub visible: Range<usize>,
+    // Whether the name is a module's import alias
+    pub module: bool,
+}
+
+// A `pub` item of an imported module
+#[derive(Debug, Clone)]
+pub(crate) struct Member {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub detail: String,
+    pub params: Vec<String>,
+}
+
+/// Everything the server knows about one version of a document.
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    pub symbols: Vec<Symbol>,
+    // Shared with the database, for its tokens
+    pub(crate) tree: Rc<SyntaxTree>,
+    pub(crate) bindings: Vec<Binding>,
+    pub(crate) resolution: Rc<Resolution>,
+    // The members of each module imported, by alias
+    pub(crate) modules: Vec<(String, Vec<Member>)>,
+}
+
+/// A database with the interpreter's builtins in scope, to analyze documents
+/// in.
+pub fn database() -> Database {
+    let mut database = Database::new();
+    let globals = Interpreter::global_names();
+    database.set::<Globals>((), globals.iter().map(|name| name.to_string()).collect());
+    database
+}
+
+impl Analysis {
+    /// Scans, parses and resolves `source`.
+    pub fn new(source: &str) -> Self {
+        let mut database = database();
+        let file = database.set_source("<document>", source.to_string());
+        Self::analyze(&database, file)
+    }
+
+    /// Resolves a file in the database. Parsing recovers from errors so
+    /// incomplete code still has symbols, but parse errors are only reported
+    /// once scanning succeeds, and resolve errors once parsing does.
+    pub fn analyze(database: &Database, file: FileId) -> Self {
+        let tree = database.syntax(file);
+        let mut analysis = Self {
+            diagnostics: Vec::new(),
+            symbols: Vec::new(),
+            tree: tree.clone(),
+            bindings: Vec::new(),
+            resolution: Rc::default(),
+            modules: Vec::new(),
+        };
+        analysis
+            .diagnostics
+            .extend(tree.lex_errors().iter().map(Diagnostic::from));
+
+        if analysis.diagnostics.is_empty() {
+            analysis
+                .diagnostics
+                .extend(tree.parse_errors().map(Diagnostic::from));
+        }
+
+        let statements = tree.statements();
+        let mut spans = tree.statement_spans().into_iter();
+        analysis.symbols = analysis.declarations(statements, &mut spans, 0..tree.source().len());
+
+        let resolution = database.get::<Resolve>(&file);
+        if analysis.diagnostics.is_empty() {
+            analysis
+                .diagnostics
+                .extend(resolution.errors.iter().map(Diagnostic::from));
+        }
+        analysis.resolution = resolution;
+
+        for (alias, path) in database.get::<Imports>(&file).iter() {
+            // Registered under the same name the loader gives them
+            let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if let Some(imported) = database.file(&path.display().to_string()) {
+                let members = members(database.syntax(imported).statements());
+                analysis.modules.push((alias.to_string(), members));
+            }
+        }
+
+        analysis
+    }
+
+    /// The declaration of the name at `offset`.
+    pub fn definition(&self, offset: usize) -> Option<Span> {
+        self.reference_at(offset)
+            .map(|reference| reference.declaration)
+    }
+
+    /// Every use of the name at `offset` in source order, optionally
+    /// including its declaration.
+    pub fn references(&self, offset: usize, include_declaration: bool) -> Vec<Span> {
+        let Some(declaration) = self.definition(offset) else {
+            return Vec::new();
+        };
+
+        let mut spans: Vec<Span> = self
+            .resolution
+            .references
+            .iter()
+            .filter(|r| r.declaration == declaration)
+            .filter(|r| include_declaration || r.span != declaration)
+            .map(|r| r.span)
+            .collect();
+        spans.sort_by_key(|span| span.start);
+        spans.dedup();
+        spans
+    }
+
+    /// A description of the name at `offset` and the span it covers.
+    pub fn hover(&self, offset: usize) -> Option<(String, Span)> {
+        if let Some(reference) = self.reference_at(offset) {
+            let binding = self
+                .bindings
+                .iter()
+                .find(|binding| binding.span == reference.declaration)?;
+            return Some((binding.detail.clone(), reference.span));
+        }
+
+        // Builtins aren't declared anywhere the resolver can point to
+        let token = self
+            .tree
+            .tokens()
+            .iter()
+            .find(|token| token.span.start <= offset && offset <= token.span.end)?;
+        match &token.token_type {
+            TokenType::Identifier(name) if Interpreter::global_names().contains(&name.as_str()) => {
+                Some((builtin_detail(name.as_str()), token.span))
+            }
+            _ => None,
+        }
+    }
+
+    /// The bindings in scope at `offset`, without the ones shadowed there.
+    pub(crate) fn visible(&self, offset: usize) -> Vec<&Binding> {
+        let mut visible: Vec<&Binding> = Vec::new();
+
+        for binding in &self.bindings {
+            if binding.visible.start > offset || offset > binding.visible.end {
+                continue;
+            }
+            // Later and more deeply nested bindings start later
+            match visible.iter_mut().find(|b| b.name == binding.name) {
+                Some(shadowed) if shadowed.visible.start <= binding.visible.start => {
+                    *shadowed = binding
+                }
+                Some(_) => {}
+                None => visible.push(binding),
+            }
+        }
+
+        visible
+    }
+
+    /// The members of the module `name` is the import alias of at `offset`,
+    /// unless something else declared there shadows it.
+    pub(crate) fn members(&self, name: &str, offset: usize) -> Option<&[Member]> {
+        let binding = self
+            .visible(offset)
+            .into_iter()
+            .find(|binding| binding.name == name)?;
+        if !binding.module {
+            return None;
+        }
+        self.modules
+            .iter()
+            .find(|(alias, _)| alias == name)
+            .map(|(_, members)| members.as_slice())
+    }
+
+    // Offsets at either end of a name count as on it, so the cursor can be
+    // just after it
+    fn reference_at(&self, offset: usize) -> Option<&Reference> {
+        self.resolution
+            .references
+            .iter()
+            .find(|r| r.span.start <= offset && offset <= r.span.end)
+    }
+
+    // Walks statements in the same order the parser recorded their spans,
+    // collecting symbols and the bindings each declaration makes in `block`
+    fn declarations(
+        &mut self,
+        statements: &[Stmt],
+        spans: &mut impl Iterator<Item = Span>,
+        block: Range<usize>,
+    ) -> Vec<Symbol> {
+        let mut symbols = Vec::new();
+
+        for stmt in statements {
+            let span = spans.next().expect("a span for every statement");
+            let extent = self.extent(span);
+            // What a statement declares can be used after it
+            let after = extent.end..block.end;
+
+            match stmt {
+                Stmt::FunctionDecl(function) => {
+                    // Functions are hoisted, so they're visible in the whole block
+                    let detail = signature(function);
+                    self.bind(&function.name, SymbolKind::Function, detail, block.clone())
+                        .params = function.params.iter().map(|p| p.to_string()).collect();
+                    for param in &function.params {
+                        let detail = format!("(parameter) {}", param);
+                        self.bind(param, SymbolKind::Variable, detail, extent.clone());
+                    }
+
+                    symbols.push(Symbol {
+                        name: function.name.to_string(),
+                        kind: SymbolKind::Function,
+                        span,
+                        name_span: function.name.span,
+                        children: self.declarations(&function.statements, spans, extent),
+                    });
+                }
+                Stmt::VariableDecl(decl) => {
+                    let keyword = match decl.visibility {
+                        Visibility::Public => "pub let",
+                        Visibility::Private => "let",
+                    };
+                    for name in decl.pattern.bindings() {
+                        let detail = format!("{} {}", keyword, name);
+                        self.bind(name, SymbolKind::Variable, detail, after.clone());
+                        symbols.push(Symbol {
+                            name: name.to_string(),
+                            kind: SymbolKind::Variable,
+                            span,
+                            name_span: name.span,
+                            children: Vec::new(),
+                        });
+                    }
+                }
+                Stmt::For(for_loop) => {
+                    self.bind_pattern(&for_loop.pattern, "for", extent.clone());
+                    symbols.extend(self.declarations(&for_loop.body, spans, extent));
+                }
+                Stmt::If(if_stmt) => {
+                    let else_start = self.else_position(span);
+                    let then_block = extent.start..else_start.unwrap_or(extent.end);
+                    symbols.extend(self.declarations(&if_stmt.then_branch, spans, then_block));
+
+                    if let Some(else_branch) = &if_stmt.else_branch {
+                        let else_block = else_start.unwrap_or(extent.end)..extent.end;
+                        symbols.extend(self.declarations(else_branch, spans, else_block));
+                    }
+                }
+                Stmt::While(while_loop) => {
+                    symbols.extend(self.declarations(&while_loop.body, spans, extent))
+                }
+                Stmt::Loop(body) => symbols.extend(self.declarations(body, spans, extent)),
+                Stmt::Import(import) => {
+                    let detail = format!("import \"{}\" as {}", import.path, import.alias);
+                    self.bind(&import.alias, SymbolKind::Variable, detail, after)
+                        .module = true;
+                }
+                Stmt::Use(use_decl) => {
+                    for name in &use_decl.names {
+                        let detail = format!("use {}.{}", use_decl.module, name);
+                        self.bind(name, SymbolKind::Variable, detail, after.clone());
+                    }
+                }
+                Stmt::Expression(_) | Stmt::Break | Stmt::Continue | Stmt::Return(_) => {}
+            }
+        }
+
+        symbols
+    }
+
+    fn bind(
+        &mut self,
+        name: &Ident,
+        kind: SymbolKind,
+        detail: String,
+        visible: Range<usize>,
+    ) -> &mut Binding {
+        self.bindings.push(Binding {
+            name: name.to_string(),
+            kind,
+            span: name.span,
+            detail,
+            params: Vec::new(),
+            visible,
+            module: false,
+        });
+        self.bindings.last_mut().unwrap()
+    }
+
+    fn bind_pattern(&mut self, pattern: &Pattern, keyword: &str, visible: Range<usize>) {
+        for name in pattern.bindings() {
+            let detail = format!("{} {}", keyword, name);
+            self.bind(name, SymbolKind::Variable, detail, visible.clone());
+        }
+    }
+
+    // A statement's span, except that a statement running into the end of
+    // the input (a block left open while typing) covers the rest of it
+    fn extent(&self, span: Span) -> Range<usize> {
+        let last_token_end = self
+            .tree
+            .tokens()
+            .iter()
+            .rev()
+            .find(|token| token.token_type != TokenType::Eof)
+            .map_or(0, |token| token.span.end);
+
+        if span.end >= last_token_end {
+            span.start..self.tree.source().len()
+        } else {
+            span.start..span.end
+        }
+    }
+
+    // Where the `else` block of the `if` statement at `span` starts, found by
+    // matching the braces of its `then` block
+    fn else_position(&self, span: Span) -> Option<usize> {
+        let mut depth = 0;
+        let mut tokens = self
+            .tree
+            .tokens()
+            .iter()
+            .skip_while(|token| token.span.start < span.start);
+
+        while let Some(token) = tokens.next() {
+            match token.token_type {
+                TokenType::CurlyOpen => depth += 1,
+                TokenType::CurlyClose => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let next = tokens.next()?;
+                        return (next.token_type == TokenType::Else).then_some(next.span.end);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+// The `pub` functions and variables declared at the top of a module
+fn members(statements: &[Stmt]) -> Vec<Member> {
+    let mut members = Vec::new();
+
+    for stmt in statements {
+        match stmt {
+            Stmt::FunctionDecl(function) if function.visibility == Visibility::Public => {
+                members.push(Member {
+                    name: function.name.to_string(),
+                    kind: SymbolKind::Function,
+                    detail: signature(function),
+                    params: function.params.iter().map(|p| p.to_string()).collect(),
+                });
+            }
+            Stmt::VariableDecl(decl) if decl.visibility == Visibility::Public => {
+                members.extend(decl.pattern.bindings().into_iter().map(|name| Member {
+                    name: name.to_string(),
+                    kind: SymbolKind::Variable,
+                    detail: format!("pub let {}", name),
+                    params: Vec::new(),
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    members
+}
+
+fn signature(function: &Function) -> String {
+    let params: Vec<&str> = function.params.iter().map(|p| p.as_str()).collect();
+    let keyword = match function.visibility {
+        Visibility::Public => "pub fn",
+        Visibility::Private => "fn",
+    };
+    format!("{} {}({})", keyword, function.name, params.join(", "))
+}
+
+pub(crate) fn builtin_detail(name: &str) -> String {
+    if Interpreter::builtin_functions().contains(&name) {
+        format!("(builtin) fn {}", name)
+    } else {
+        format!("(builtin) {}", name)
+    }
+}
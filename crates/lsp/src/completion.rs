@@ -0,0 +1,203 @@
+use apl_interpreter::Interpreter;
+use apl_scanner::{Category, KEYWORDS, ScannedToken, TokenType};
+
+use super::analysis::builtin_detail;
+use super::{Analysis, SymbolKind};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompletionKind {
+    Function,
+    Variable,
+    Keyword,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: Option<String>,
+}
+
+/// The signature of the function called around the cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub name: String,
+    pub detail: String,
+    pub params: Vec<String>,
+    pub active_param: usize,
+}
+
+impl Analysis {
+    /// What can be written at `offset`. After a `.` only functions are
+    /// offered, since `a.f(b)` calls `f(a, b)`, unless the `.` follows a
+    /// module's alias, which offers what the module exports. Clients filter
+    /// the list by what has been typed so far.
+    pub fn completions(&self, offset: usize) -> Vec<Completion> {
+        let mut before = self.tokens_before(offset).rev();
+
+        // Skip the name being typed, if any
+        let mut previous = before.next();
+        if previous.is_some_and(|token| {
+            token.span.end == offset
+                && matches!(
+                    token.token_type.category(),
+                    Some(Category::Variable | Category::Keyword)
+                )
+        }) {
+            previous = before.next();
+        }
+        let after_dot = previous.is_some_and(|token| token.token_type == TokenType::Dot);
+
+        // After a module's alias its `pub` items are what can be written
+        if after_dot
+            && let Some(TokenType::Identifier(receiver)) = before.next().map(|t| &t.token_type)
+            && let Some(members) = self.members(receiver.as_str(), offset)
+        {
+            let mut completions: Vec<Completion> = members
+                .iter()
+                .map(|member| Completion {
+                    label: member.name.clone(),
+                    kind: match member.kind {
+                        SymbolKind::Function => CompletionKind::Function,
+                        SymbolKind::Variable => CompletionKind::Variable,
+                    },
+                    detail: Some(member.detail.clone()),
+                })
+                .collect();
+            completions.sort_by(|a, b| a.label.cmp(&b.label));
+            return completions;
+        }
+
+        let visible = self.visible(offset);
+        let mut completions: Vec<Completion> = visible
+            .iter()
+            .filter(|binding| !after_dot || binding.kind == SymbolKind::Function)
+            .map(|binding| Completion {
+                label: binding.name.clone(),
+                kind: match binding.kind {
+                    SymbolKind::Function => CompletionKind::Function,
+                    SymbolKind::Variable => CompletionKind::Variable,
+                },
+                detail: Some(binding.detail.clone()),
+            })
+            .collect();
+
+        let functions = Interpreter::builtin_functions();
+        for name in Interpreter::global_names() {
+            let kind = if functions.contains(&name) {
+                CompletionKind::Function
+            } else if after_dot {
+                continue;
+            } else {
+                CompletionKind::Variable
+            };
+
+            if !visible.iter().any(|binding| binding.name == name) {
+                completions.push(Completion {
+                    label: name.to_string(),
+                    kind,
+                    detail: Some(builtin_detail(name)),
+                });
+            }
+        }
+
+        if !after_dot {
+            completions.extend(KEYWORDS.iter().map(|(keyword, _)| Completion {
+                label: keyword.to_string(),
+                kind: CompletionKind::Keyword,
+                detail: None,
+            }));
+        }
+
+        completions.sort_by(|a, b| a.label.cmp(&b.label));
+        completions
+    }
+
+    /// The function whose argument list `offset` is in, and which argument
+    /// it is at. Works on tokens, so calls still being typed are found.
+    pub fn signature(&self, offset: usize) -> Option<Signature> {
+        let before: Vec<&ScannedToken> = self.tokens_before(offset).collect();
+        let mut depth = 0;
+        let mut commas = 0;
+
+        let open = before.iter().rposition(|token| {
+            match token.token_type {
+                TokenType::ParenthesesClose | TokenType::BracketClose | TokenType::CurlyClose => {
+                    depth += 1
+                }
+                TokenType::ParenthesesOpen | TokenType::BracketOpen | TokenType::CurlyOpen
+                    if depth > 0 =>
+                {
+                    depth -= 1
+                }
+                TokenType::ParenthesesOpen
+                | TokenType::BracketOpen
+                | TokenType::CurlyOpen
+                | TokenType::SemiColon => return true,
+                TokenType::Comma if depth == 0 => commas += 1,
+                // Keywords never appear in an argument list, so an unclosed
+                // call before one has ended
+                _ if token.token_type.category() == Some(Category::Keyword) => return true,
+                _ => {}
+            }
+            false
+        })?;
+        if before[open].token_type != TokenType::ParenthesesOpen {
+            return None;
+        }
+
+        let TokenType::Identifier(name) = &before.get(open.checked_sub(1)?)?.token_type else {
+            return None;
+        };
+        let receiver = match open.checked_sub(3).map(|i| (before[i], before[i + 1])) {
+            Some((receiver, dot)) if dot.token_type == TokenType::Dot => Some(receiver),
+            _ => None,
+        };
+
+        // A module's function is called with just the arguments written
+        if let Some(TokenType::Identifier(alias)) = receiver.map(|t| &t.token_type)
+            && let Some(members) = self.members(alias.as_str(), offset)
+        {
+            let member = members
+                .iter()
+                .find(|member| member.name == name.as_str())
+                .filter(|member| member.kind == SymbolKind::Function)?;
+            return Some(Signature {
+                name: name.to_string(),
+                detail: member.detail.clone(),
+                params: member.params.clone(),
+                active_param: commas,
+            });
+        }
+
+        // The object of a method call is its first argument
+        let is_method = open >= 2 && before[open - 2].token_type == TokenType::Dot;
+        let active_param = commas + usize::from(is_method);
+
+        let visible = self.visible(offset);
+        match visible.iter().find(|binding| binding.name == name.as_str()) {
+            Some(binding) if binding.kind == SymbolKind::Function => Some(Signature {
+                name: name.to_string(),
+                detail: binding.detail.clone(),
+                params: binding.params.clone(),
+                active_param,
+            }),
+            Some(_) => None,
+            None if Interpreter::builtin_functions().contains(&name.as_str()) => Some(Signature {
+                name: name.to_string(),
+                detail: builtin_detail(name.as_str()),
+                params: Vec::new(),
+                active_param,
+            }),
+            None => None,
+        }
+    }
+
+    fn tokens_before(&self, offset: usize) -> impl DoubleEndedIterator<Item = &ScannedToken> {
+        let tokens = self.tree.tokens();
+        let end = tokens.partition_point(|token| token.span.end <= offset);
+        tokens[..end]
+            .iter()
+            .filter(|token| token.token_type != TokenType::Eof)
+    }
+}
@@ -0,0 +1,179 @@
+use std::rc::Rc;
+
+use apl_database::Database;
+use apl_parser::SyntaxTree;
+use apl_resolver::Resolve;
+use apl_scanner::{Category, highlight};
+use apl_source::{FileId, Span};
+use lsp_types::{Position, Range, SemanticToken, TextDocumentContentChangeEvent};
+
+use super::Analysis;
+
+/// An open document, with LSP positions counted in UTF-16 code units as the
+/// protocol requires by default. Its text is kept in a database shared by
+/// every open document, so a document importing another is resolved against
+/// what that one exports.
+pub struct Document {
+    file: FileId,
+    tree: Rc<SyntaxTree>,
+    // Byte offset of the start of every line
+    line_starts: Vec<usize>,
+    pub version: i32,
+    pub analysis: Analysis,
+}
+
+impl Document {
+    /// Registers the document under `name`, the path imports in it are
+    /// relative to.
+    pub fn new(database: &mut Database, name: String, text: String, version: i32) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let file = database.set_source(name, text);
+
+        Self {
+            file,
+            tree: database.syntax(file),
+            line_starts,
+            version,
+            analysis: Analysis::analyze(database, file),
+        }
+    }
+
+    /// Applies changes sent by the client in order, each either replacing a
+    /// range or the whole text, and analyzes the result.
+    pub fn apply(
+        &mut self,
+        database: &mut Database,
+        changes: Vec<TextDocumentContentChangeEvent>,
+        version: i32,
+    ) {
+        for change in changes {
+            let range = match change.range {
+                Some(range) => self.offset(range.start)..self.offset(range.end),
+                None => 0..self.text().len(),
+            };
+            self.edit(database, range, &change.text);
+        }
+
+        self.version = version;
+        self.analysis = Analysis::analyze(database, self.file);
+    }
+
+    /// Analyzes the document again if how it resolves changed, e.g. because
+    /// a document it imports was edited. Returns whether it did.
+    pub fn refresh(&mut self, database: &Database) -> bool {
+        let resolution = database.get::<Resolve>(&self.file);
+        if Rc::ptr_eq(&resolution, &self.analysis.resolution) {
+            return false;
+        }
+        self.analysis = Analysis::analyze(database, self.file);
+        true
+    }
+
+    // Replaces a range, only scanning and parsing again what it affects
+    fn edit(&mut self, database: &mut Database, range: std::ops::Range<usize>, text: &str) {
+        let (start, end) = (range.start, range.end);
+
+        // The database only edits the tree in place when nothing else holds
+        // on to it
+        self.tree = Rc::new(SyntaxTree::new(String::new(), self.file));
+        database.edit_source(self.file, range, text);
+        self.tree = database.syntax(self.file);
+
+        // Lines starting inside the range are gone, and the ones after it moved
+        let delta = text.len() as isize - (end - start) as isize;
+        let removed = self.line_starts.partition_point(|&line| line <= start)
+            ..self.line_starts.partition_point(|&line| line <= end);
+        let inserted = text.match_indices('\n').map(|(i, _)| start + i + 1);
+        let moved = removed.start + inserted.clone().count();
+        self.line_starts.splice(removed, inserted);
+        for line in &mut self.line_starts[moved..] {
+            *line = line.wrapping_add_signed(delta);
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.tree.source()
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text().len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let character: usize = self.text()[self.line_starts[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+
+        Position::new(line as u32, character as u32)
+    }
+
+    /// The byte offset of a position, positions past the end of a line map to
+    /// its end.
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return self.text().len();
+        };
+        let line = self.text()[start..].split('\n').next().unwrap_or("");
+
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+            units += c.len_utf16();
+        }
+        start + line.len()
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        Range::new(self.position(span.start), self.position(span.end))
+    }
+
+    /// Highlighting for the whole document, encoded relative to the previous
+    /// token. Tokens spanning lines are split, since clients needn't support
+    /// multiline tokens. Types are indices into `Category::ALL`.
+    pub fn semantic_tokens(&self) -> Vec<SemanticToken> {
+        let mut tokens = Vec::new();
+        let mut previous = Position::new(0, 0);
+
+        for (span, category) in highlight(self.text()) {
+            let token_type = Category::ALL.iter().position(|&c| c == category).unwrap() as u32;
+            let range = self.range(span);
+
+            for line in range.start.line..=range.end.line {
+                let start = if line == range.start.line {
+                    range.start.character
+                } else {
+                    0
+                };
+                let end = if line == range.end.line {
+                    range.end.character
+                } else {
+                    let next_line = self.line_starts[line as usize + 1];
+                    self.position(next_line - 1).character
+                };
+                if end <= start {
+                    continue;
+                }
+
+                let delta_line = line - previous.line;
+                let delta_start = if delta_line == 0 {
+                    start - previous.character
+                } else {
+                    start
+                };
+                tokens.push(SemanticToken {
+                    delta_line,
+                    delta_start,
+                    length: end - start,
+                    token_type,
+                    token_modifiers_bitset: 0,
+                });
+                previous = Position::new(line, start);
+            }
+        }
+
+        tokens
+    }
+}
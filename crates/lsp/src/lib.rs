@@ -0,0 +1,373 @@
+mod analysis;
+mod completion;
+mod document;
+mod server;
+
+pub use analysis::{Analysis, Symbol, SymbolKind, database};
+pub use completion::{Completion, CompletionKind, Signature};
+pub use document::Document;
+pub use server::run;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apl_scanner::Category;
+    use lsp_types::Position;
+
+    // Offset of the `n`th occurrence of `needle`, counting from zero
+    fn offset_of(source: &str, needle: &str, n: usize) -> usize {
+        source.match_indices(needle).nth(n).unwrap().0
+    }
+
+    #[test]
+    fn diagnostics() {
+        let codes = |source: &str| -> Vec<Option<String>> {
+            Analysis::new(source)
+                .diagnostics
+                .into_iter()
+                .map(|d| d.code)
+                .collect()
+        };
+
+        assert!(codes("let a = 1; print(a);").is_empty());
+        assert_eq!(codes("let s = \"abc"), vec![Some("E0001".to_string())]);
+        assert_eq!(codes("let = 1;").len(), 1);
+        assert_eq!(codes("print(b);"), vec![Some("E0200".to_string())]);
+        assert!(codes("").is_empty());
+    }
+
+    #[test]
+    fn document_symbols() {
+        let source = "let (a, b) = (1, 2);\nfn f(x) {\n    let y = x;\n    if y { let z = 1; }\n}";
+        let analysis = Analysis::new(source);
+
+        let outline: Vec<(&str, SymbolKind, Vec<&str>)> = analysis
+            .symbols
+            .iter()
+            .map(|s| {
+                let children = s.children.iter().map(|c| c.name.as_str()).collect();
+                (s.name.as_str(), s.kind, children)
+            })
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                ("a", SymbolKind::Variable, vec![]),
+                ("b", SymbolKind::Variable, vec![]),
+                ("f", SymbolKind::Function, vec!["y", "z"]),
+            ]
+        );
+
+        let f = &analysis.symbols[2];
+        assert_eq!(&source[f.span.start..f.span.end], &source[21..]);
+        assert_eq!(&source[f.name_span.start..f.name_span.end], "f");
+    }
+
+    #[test]
+    fn navigation() {
+        let source = "fn add(a, b) { return a + b; }\nlet x = add(1, 2);\nprint(add(x, x));";
+        let analysis = Analysis::new(source);
+
+        let declaration = offset_of(source, "add", 0);
+        let call = offset_of(source, "add", 2);
+        assert_eq!(analysis.definition(call + 1).unwrap().start, declaration);
+
+        let starts = |spans: Vec<apl_source::Span>| -> Vec<usize> {
+            spans.iter().map(|span| span.start).collect()
+        };
+        assert_eq!(
+            starts(analysis.references(call, true)),
+            vec![declaration, offset_of(source, "add", 1), call]
+        );
+        assert_eq!(
+            starts(analysis.references(offset_of(source, "x", 0), false)),
+            vec![offset_of(source, "x", 1), offset_of(source, "x", 2)]
+        );
+
+        let hover = |offset| analysis.hover(offset).map(|(text, _)| text);
+        assert_eq!(hover(call).as_deref(), Some("fn add(a, b)"));
+        assert_eq!(
+            hover(offset_of(source, "a +", 0)).as_deref(),
+            Some("(parameter) a")
+        );
+        assert_eq!(
+            hover(offset_of(source, "print", 0)).as_deref(),
+            Some("(builtin) fn print")
+        );
+        assert_eq!(hover(offset_of(source, "1", 0)), None);
+    }
+
+    #[test]
+    fn utf16_positions() {
+        let document = Document::new(
+            &mut database(),
+            "a.apl".to_string(),
+            "let ø = 1;\nlet 𝔸 = ø;".to_string(),
+            1,
+        );
+
+        // `ø` is two bytes and one UTF-16 unit, `𝔸` four bytes and two units
+        let offset = "let ø = 1;\nlet 𝔸".len();
+        assert_eq!(document.position(offset), Position::new(1, 6));
+        assert_eq!(document.offset(Position::new(1, 6)), offset);
+        assert_eq!(document.offset(Position::new(0, 99)), "let ø = 1;".len());
+        assert_eq!(
+            document.offset(Position::new(5, 0)),
+            "let ø = 1;\nlet 𝔸 = ø;".len()
+        );
+    }
+
+    #[test]
+    fn semantic_tokens() {
+        let document = Document::new(
+            &mut database(),
+            "a.apl".to_string(),
+            "/* a\nb */ let ø = f();".to_string(),
+            1,
+        );
+        let tokens: Vec<[u32; 4]> = document
+            .semantic_tokens()
+            .iter()
+            .map(|t| [t.delta_line, t.delta_start, t.length, t.token_type])
+            .collect();
+
+        let index = |category| Category::ALL.iter().position(|&c| c == category).unwrap() as u32;
+        assert_eq!(
+            tokens,
+            vec![
+                // The comment is split at the end of its first line
+                [0, 0, 4, index(Category::Comment)],
+                [1, 0, 4, index(Category::Comment)],
+                [0, 5, 3, index(Category::Keyword)],
+                [0, 4, 1, index(Category::Variable)],
+                [0, 2, 1, index(Category::Operator)],
+                [0, 2, 1, index(Category::Function)],
+                [0, 1, 1, index(Category::Operator)],
+                [0, 1, 1, index(Category::Operator)],
+                [0, 1, 1, index(Category::Operator)],
+            ]
+        );
+    }
+
+    #[test]
+    fn incremental_changes() {
+        use lsp_types::{Range, TextDocumentContentChangeEvent};
+
+        let change = |range: Option<Range>, text: &str| TextDocumentContentChangeEvent {
+            range,
+            range_length: None,
+            text: text.to_string(),
+        };
+        let mut database = database();
+        let mut document = Document::new(
+            &mut database,
+            "a.apl".to_string(),
+            "let a = 1;\nprint(a);".to_string(),
+            1,
+        );
+
+        // Changes apply in order, each to the result of the one before
+        document.apply(
+            &mut database,
+            vec![
+                change(
+                    Some(Range::new(Position::new(1, 6), Position::new(1, 7))),
+                    "b",
+                ),
+                change(
+                    Some(Range::new(Position::new(0, 10), Position::new(0, 10))),
+                    "\nlet ø = 2;",
+                ),
+            ],
+            2,
+        );
+        assert_eq!(document.text(), "let a = 1;\nlet ø = 2;\nprint(b);");
+        assert_eq!(document.version, 2);
+        assert_eq!(
+            document.position(document.text().len()),
+            Position::new(2, 9)
+        );
+        assert_eq!(document.analysis.diagnostics.len(), 1);
+
+        document.apply(
+            &mut database,
+            vec![change(
+                Some(Range::new(Position::new(2, 6), Position::new(2, 7))),
+                "ø",
+            )],
+            3,
+        );
+        assert!(document.analysis.diagnostics.is_empty());
+        assert_eq!(document.analysis.symbols.len(), 2);
+
+        document.apply(&mut database, vec![change(None, "let x = ;")], 4);
+        assert_eq!(document.text(), "let x = ;");
+        assert_eq!(document.analysis.diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn imported_documents() {
+        use lsp_types::TextDocumentContentChangeEvent;
+
+        let mut database = database();
+        let mut lib = Document::new(
+            &mut database,
+            "lib.apl".to_string(),
+            "pub fn add(a, b) {}".to_string(),
+            1,
+        );
+        let mut main = Document::new(
+            &mut database,
+            "main.apl".to_string(),
+            "import \"lib.apl\" as l;\nl.add(1, 2);".to_string(),
+            1,
+        );
+        assert!(main.analysis.diagnostics.is_empty());
+        assert!(!main.refresh(&database));
+
+        // Renaming the export leaves the call in the importing document
+        // pointing at nothing
+        let change = TextDocumentContentChangeEvent {
+            range: Some(lsp_types::Range::new(
+                Position::new(0, 7),
+                Position::new(0, 10),
+            )),
+            range_length: None,
+            text: "sum".to_string(),
+        };
+        lib.apply(&mut database, vec![change], 2);
+        assert!(lib.analysis.diagnostics.is_empty());
+        assert!(main.refresh(&database));
+        assert_eq!(main.analysis.diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn completions() {
+        let source = "fn add(a, b) {\n    let c = 1;\n    if a { let d = 2; } else {  }\n    \n}\nlet e = 3;\nlet f = e.";
+        let analysis = Analysis::new(source);
+
+        let labels = |offset: usize, kind: CompletionKind| -> Vec<String> {
+            analysis
+                .completions(offset)
+                .into_iter()
+                .filter(|c| c.kind == kind)
+                .map(|c| c.label)
+                .collect()
+        };
+
+        // In the else block, `d` is out of scope and `e` isn't declared yet
+        let in_else = offset_of(source, "{  }", 0) + 2;
+        assert_eq!(
+            labels(in_else, CompletionKind::Variable),
+            vec!["a", "b", "c", "false", "true"]
+        );
+        let after_if = offset_of(source, "    \n", 0) + 4;
+        assert_eq!(
+            labels(after_if, CompletionKind::Variable),
+            vec!["a", "b", "c", "false", "true"]
+        );
+        assert!(labels(after_if, CompletionKind::Keyword).contains(&"while".to_string()));
+        assert!(labels(after_if, CompletionKind::Function).contains(&"add".to_string()));
+
+        // After a `.` only functions, the builtins included
+        let functions = labels(source.len(), CompletionKind::Function);
+        assert!(functions.contains(&"add".to_string()) && functions.contains(&"len".to_string()));
+        assert!(labels(source.len(), CompletionKind::Variable).is_empty());
+        assert!(labels(source.len(), CompletionKind::Keyword).is_empty());
+
+        let add = analysis
+            .completions(0)
+            .into_iter()
+            .find(|c| c.label == "add");
+        assert_eq!(add.unwrap().detail.as_deref(), Some("fn add(a, b)"));
+    }
+
+    #[test]
+    fn module_members() {
+        let mut database = database();
+        Document::new(
+            &mut database,
+            "lib.apl".to_string(),
+            "pub fn add(a, b) {}\nfn hidden() {}\npub let zero = 0;".to_string(),
+            1,
+        );
+        let source = "import \"lib.apl\" as l;\nl.add(1, ;\nl.";
+        let main = Document::new(&mut database, "main.apl".to_string(), source.to_string(), 1);
+        let analysis = &main.analysis;
+
+        // Only what the module exports, its variables included
+        let completions: Vec<(String, CompletionKind)> = analysis
+            .completions(source.len())
+            .into_iter()
+            .map(|c| (c.label, c.kind))
+            .collect();
+        assert_eq!(
+            completions,
+            vec![
+                ("add".to_string(), CompletionKind::Function),
+                ("zero".to_string(), CompletionKind::Variable),
+            ]
+        );
+
+        // The alias isn't an argument, so the index isn't shifted
+        let signature = analysis.signature(offset_of(source, ", ;", 0) + 2).unwrap();
+        assert_eq!(signature.detail, "pub fn add(a, b)");
+        assert_eq!(signature.active_param, 1);
+        assert_eq!(
+            analysis
+                .signature(offset_of(source, "1,", 0))
+                .unwrap()
+                .active_param,
+            0
+        );
+    }
+
+    #[test]
+    fn completions_in_incomplete_code() {
+        let source = "fn f(x) {\n    let y = x;\n    if y {\n        pri";
+        let analysis = Analysis::new(source);
+
+        let labels: Vec<String> = analysis
+            .completions(source.len())
+            .into_iter()
+            .filter(|c| c.kind != CompletionKind::Keyword)
+            .map(|c| c.label)
+            .collect();
+        for expected in ["f", "x", "y", "print"] {
+            assert!(
+                labels.contains(&expected.to_string()),
+                "{} in {:?}",
+                expected,
+                labels
+            );
+        }
+    }
+
+    #[test]
+    fn signature_help() {
+        let source = "fn add(a, b) { return a + b; }\nadd(1, [2, 3]\nlet x = 1;\nx.add(";
+        let analysis = Analysis::new(source);
+
+        let active = |offset| {
+            analysis
+                .signature(offset)
+                .map(|s| (s.name, s.params, s.active_param))
+        };
+        let call = offset_of(source, "add(1", 0) + 4;
+        let params = vec!["a".to_string(), "b".to_string()];
+
+        assert_eq!(active(call), Some(("add".to_string(), params.clone(), 0)));
+        // Commas inside nested brackets don't count
+        assert_eq!(active(offset_of(source, "3]", 0)), None);
+        assert_eq!(
+            active(offset_of(source, "3]", 0) + 2),
+            Some(("add".to_string(), params.clone(), 1))
+        );
+        // The object of a method call is the first argument
+        assert_eq!(active(source.len()), Some(("add".to_string(), params, 1)));
+        assert_eq!(active(offset_of(source, "let x", 0) + 5), None);
+
+        let print = Analysis::new("print(1, ").signature(9).unwrap();
+        assert_eq!((print.params.len(), print.active_param), (0, 1));
+    }
+}
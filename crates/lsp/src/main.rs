@@ -0,0 +1,21 @@
+use std::process::ExitCode;
+
+use apl_interpreter::STACK_SIZE;
+
+fn main() -> ExitCode {
+    // Deeply nested documents would overflow the main thread's stack while
+    // they're parsed and resolved, taking the server down with them
+    let thread = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(apl_lsp::run)
+        .expect("failed to spawn the server thread");
+
+    match thread.join() {
+        Ok(Ok(())) => ExitCode::SUCCESS,
+        Ok(Err(e)) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+        Err(_) => ExitCode::FAILURE,
+    }
+}
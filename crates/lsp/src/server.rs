@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use apl_database::Database;
+use apl_error::Severity;
+use apl_loader::ModuleLoader;
+use apl_refactor::Edit;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as LspNotification, PublishDiagnostics,
+};
+use lsp_types::request::{
+    CodeActionRequest, Completion as CompletionRequest, DocumentSymbolRequest, GotoDefinition,
+    HoverRequest, References, Rename, Request as LspRequest, SemanticTokensFullRequest,
+    SignatureHelpRequest,
+};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOptions, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, CodeActionResponse, CompletionItem, CompletionItemKind,
+    CompletionOptions, CompletionParams, CompletionResponse, DiagnosticSeverity, DocumentSymbol,
+    DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse,
+    Hover, HoverContents, HoverParams, HoverProviderCapability, Location, MarkupContent,
+    MarkupKind, NumberOrString, OneOf, ParameterInformation, ParameterLabel,
+    PublishDiagnosticsParams, ReferenceParams, RenameParams, SemanticTokenType, SemanticTokens,
+    SemanticTokensFullOptions, SemanticTokensLegend, SemanticTokensOptions, SemanticTokensParams,
+    SemanticTokensResult, ServerCapabilities, SignatureHelp, SignatureHelpOptions,
+    SignatureHelpParams, SignatureInformation, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Uri, WorkspaceEdit,
+};
+
+use apl_scanner::Category;
+
+use super::{CompletionKind, Document, Signature, Symbol, SymbolKind, database};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+// The result of a request, or the error to answer it with
+type Reply = std::result::Result<serde_json::Value, (ErrorCode, String)>;
+
+/// Serves the language server protocol over stdin and stdout until the
+/// client asks it to exit.
+pub fn run() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::INCREMENTAL,
+        )),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![".".to_string()]),
+            ..CompletionOptions::default()
+        }),
+        signature_help_provider: Some(SignatureHelpOptions {
+            trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+            ..SignatureHelpOptions::default()
+        }),
+        rename_provider: Some(OneOf::Left(true)),
+        code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
+            code_action_kinds: Some(vec![
+                CodeActionKind::REFACTOR_EXTRACT,
+                CodeActionKind::REFACTOR_INLINE,
+            ]),
+            ..CodeActionOptions::default()
+        })),
+        semantic_tokens_provider: Some(
+            SemanticTokensOptions {
+                legend: SemanticTokensLegend {
+                    token_types: Category::ALL
+                        .iter()
+                        .map(|category| SemanticTokenType::new(category.as_str()))
+                        .collect(),
+                    token_modifiers: Vec::new(),
+                },
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            }
+            .into(),
+        ),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    Server {
+        connection: &connection,
+        database: database(),
+        documents: HashMap::new(),
+    }
+    .main_loop()?;
+
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    // The text of every document opened so far
+    database: Database,
+    documents: HashMap<Uri, Document>,
+}
+
+impl Server<'_> {
+    fn main_loop(&mut self) -> Result<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.request(request);
+                    self.connection.sender.send(response.into())?;
+                }
+                Message::Notification(notification) => self.notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            DocumentSymbolRequest::METHOD => {
+                self.dispatch::<DocumentSymbolRequest>(request, Self::document_symbols)
+            }
+            HoverRequest::METHOD => self.dispatch::<HoverRequest>(request, Self::hover),
+            GotoDefinition::METHOD => self.dispatch::<GotoDefinition>(request, Self::definition),
+            References::METHOD => self.dispatch::<References>(request, Self::references),
+            CompletionRequest::METHOD => {
+                self.dispatch::<CompletionRequest>(request, Self::completion)
+            }
+            SignatureHelpRequest::METHOD => {
+                self.dispatch::<SignatureHelpRequest>(request, Self::signature_help)
+            }
+            SemanticTokensFullRequest::METHOD => {
+                self.dispatch::<SemanticTokensFullRequest>(request, Self::semantic_tokens)
+            }
+            Rename::METHOD => self.try_dispatch::<Rename>(request, Self::rename),
+            CodeActionRequest::METHOD => {
+                self.dispatch::<CodeActionRequest>(request, Self::code_actions)
+            }
+            method => {
+                return Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request '{}'", method),
+                );
+            }
+        };
+
+        match result {
+            Ok(value) => Response::new_ok(id, value),
+            Err((code, message)) => Response::new_err(id, code as i32, message),
+        }
+    }
+
+    fn dispatch<R: LspRequest>(
+        &self,
+        request: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Reply {
+        let params = serde_json::from_value(request.params).map_err(invalid_params)?;
+        serde_json::to_value(handler(self, params)).map_err(invalid_params)
+    }
+
+    // Like `dispatch`, for handlers that can refuse a request with a message
+    // to show the user
+    fn try_dispatch<R: LspRequest>(
+        &self,
+        request: Request,
+        handler: fn(&Self, R::Params) -> std::result::Result<R::Result, String>,
+    ) -> Reply {
+        let params = serde_json::from_value(request.params).map_err(invalid_params)?;
+        let result =
+            handler(self, params).map_err(|message| (ErrorCode::RequestFailed, message))?;
+        serde_json::to_value(result).map_err(invalid_params)
+    }
+
+    fn notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: <DidOpenTextDocument as LspNotification>::Params =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.update(document.uri, document.text, document.version)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: <DidChangeTextDocument as LspNotification>::Params =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                if let Some(document) = self.documents.get_mut(&uri) {
+                    let version = params.text_document.version;
+                    document.apply(&mut self.database, params.content_changes, version);
+                    self.publish_diagnostics(uri.clone())?;
+                    self.refresh(&uri)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: <DidCloseTextDocument as LspNotification>::Params =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.publish(PublishDiagnosticsParams::new(uri, Vec::new(), None))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn update(&mut self, uri: Uri, text: String, version: i32) -> Result<()> {
+        // Files are named by their path, so imports in them can be found
+        let name = match file_path(&uri) {
+            Some(path) => path.canonicalize().unwrap_or(path).display().to_string(),
+            None => uri.as_str().to_string(),
+        };
+        let document = Document::new(&mut self.database, name, text, version);
+        self.documents.insert(uri.clone(), document);
+        self.publish_diagnostics(uri.clone())?;
+        self.refresh(&uri)
+    }
+
+    // Analyzes the documents other than `changed` again where it affected
+    // how they resolve
+    fn refresh(&mut self, changed: &Uri) -> Result<()> {
+        let mut refreshed = Vec::new();
+        for (uri, document) in &mut self.documents {
+            if uri != changed && document.refresh(&self.database) {
+                refreshed.push(uri.clone());
+            }
+        }
+
+        for uri in refreshed {
+            self.publish_diagnostics(uri)?;
+        }
+        Ok(())
+    }
+
+    fn publish_diagnostics(&self, uri: Uri) -> Result<()> {
+        let document = &self.documents[&uri];
+        let diagnostics = document
+            .analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(document, diagnostic))
+            .collect();
+
+        self.publish(PublishDiagnosticsParams::new(
+            uri,
+            diagnostics,
+            Some(document.version),
+        ))
+    }
+
+    fn publish(&self, params: PublishDiagnosticsParams) -> Result<()> {
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(notification.into())?;
+        Ok(())
+    }
+
+    // The document and byte offset a request points at
+    fn locate(&self, params: &TextDocumentPositionParams) -> Option<(&Document, usize)> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        Some((document, document.offset(params.position)))
+    }
+
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        let symbols = document
+            .analysis
+            .symbols
+            .iter()
+            .map(|symbol| document_symbol(document, symbol))
+            .collect();
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let (document, offset) = self.locate(&params.text_document_position)?;
+        let items = document
+            .analysis
+            .completions(offset)
+            .into_iter()
+            .map(|completion| CompletionItem {
+                kind: Some(match completion.kind {
+                    CompletionKind::Function => CompletionItemKind::FUNCTION,
+                    CompletionKind::Variable => CompletionItemKind::VARIABLE,
+                    CompletionKind::Keyword => CompletionItemKind::KEYWORD,
+                }),
+                detail: completion.detail,
+                ..CompletionItem::new_simple(completion.label, String::new())
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn signature_help(&self, params: SignatureHelpParams) -> Option<SignatureHelp> {
+        let (document, offset) = self.locate(&params.text_document_position_params)?;
+        let signature = document.analysis.signature(offset)?;
+        let active_parameter = signature.active_param as u32;
+
+        Some(SignatureHelp {
+            signatures: vec![signature_information(signature)],
+            active_signature: Some(0),
+            active_parameter: Some(active_parameter),
+        })
+    }
+
+    fn semantic_tokens(&self, params: SemanticTokensParams) -> Option<SemanticTokensResult> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        let tokens = SemanticTokens {
+            result_id: None,
+            data: document.semantic_tokens(),
+        };
+        Some(tokens.into())
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (document, offset) = self.locate(&params.text_document_position_params)?;
+        let (text, span) = document.analysis.hover(offset)?;
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```apl\n{}\n```", text),
+            }),
+            range: Some(document.range(span)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = params.text_document_position_params;
+        let (document, offset) = self.locate(&position)?;
+        let span = document.analysis.definition(offset)?;
+
+        let location = Location::new(position.text_document.uri, document.range(span));
+        Some(GotoDefinitionResponse::Scalar(location))
+    }
+
+    fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let position = params.text_document_position;
+        let (document, offset) = self.locate(&position)?;
+        let include_declaration = params.context.include_declaration;
+
+        let locations = document
+            .analysis
+            .references(offset, include_declaration)
+            .into_iter()
+            .map(|span| Location::new(position.text_document.uri.clone(), document.range(span)))
+            .collect();
+        Some(locations)
+    }
+
+    // Renames across the document and the modules it imports, which are
+    // read from disk. `Uri` caches parts of itself, but hashes by its text.
+    #[allow(clippy::mutable_key_type)]
+    fn rename(&self, params: RenameParams) -> std::result::Result<Option<WorkspaceEdit>, String> {
+        let position = params.text_document_position;
+        let Some((document, offset)) = self.locate(&position) else {
+            return Ok(None);
+        };
+        let uri = position.text_document.uri;
+
+        let mut loader = ModuleLoader::new();
+        let text = document.text().to_string();
+        let module = match file_path(&uri) {
+            Some(path) => loader.load_file_source(&path, text),
+            None => loader.load_source(uri.as_str(), text),
+        }
+        .map_err(|e| e.to_string())?;
+        let file = module.file;
+
+        let edits = apl_refactor::rename(&loader, file, offset, &params.new_name)
+            .map_err(|e| e.to_string())?;
+
+        let mut documents = HashMap::new();
+        let mut changes: HashMap<Uri, Vec<TextEdit>> = HashMap::new();
+        for edit in edits {
+            if edit.span.file == file {
+                changes
+                    .entry(uri.clone())
+                    .or_default()
+                    .push(text_edit(document, edit));
+                continue;
+            }
+
+            let source_file = loader.source_map().file(edit.span.file);
+            let uri = file_uri(Path::new(source_file.name()))
+                .ok_or_else(|| format!("Can't refer to {} by URI", source_file.name()))?;
+            let (name, source) = (
+                source_file.name().to_string(),
+                source_file.source().to_string(),
+            );
+            let document = documents
+                .entry(edit.span.file)
+                .or_insert_with(|| Document::new(loader.database_mut(), name, source, 0));
+            changes
+                .entry(uri)
+                .or_default()
+                .push(text_edit(document, edit));
+        }
+
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    // Offers to extract the selection into a variable, and to inline the
+    // variable at the cursor
+    fn code_actions(&self, params: CodeActionParams) -> Option<CodeActionResponse> {
+        let uri = params.text_document.uri;
+        let document = self.documents.get(&uri)?;
+        let text = document.text();
+        let start = document.offset(params.range.start);
+        let end = document.offset(params.range.end);
+
+        let mut actions = Vec::new();
+        if start < end {
+            let name = apl_refactor::unused_name(text, "value");
+            if let Ok(edits) = apl_refactor::extract_variable(text, start..end, &name) {
+                actions.push(code_action(
+                    "Extract into a variable",
+                    CodeActionKind::REFACTOR_EXTRACT,
+                    &uri,
+                    document,
+                    edits,
+                ));
+            }
+        }
+        if let Ok(edits) = apl_refactor::inline_variable(text, start) {
+            actions.push(code_action(
+                "Inline variable",
+                CodeActionKind::REFACTOR_INLINE,
+                &uri,
+                document,
+                edits,
+            ));
+        }
+        Some(actions)
+    }
+}
+
+fn invalid_params(error: serde_json::Error) -> (ErrorCode, String) {
+    (ErrorCode::InvalidParams, error.to_string())
+}
+
+fn lsp_diagnostic(
+    document: &Document,
+    diagnostic: &apl_error::Diagnostic,
+) -> lsp_types::Diagnostic {
+    let range = diagnostic
+        .primary_span()
+        .map(|span| document.range(span))
+        .unwrap_or_default();
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+        Severity::Note => DiagnosticSeverity::INFORMATION,
+    };
+
+    let mut message = diagnostic.message.clone();
+    if let Some(help) = &diagnostic.help {
+        message.push_str(&format!("\nhelp: {}", help));
+    }
+
+    lsp_types::Diagnostic {
+        range,
+        severity: Some(severity),
+        code: diagnostic.code.clone().map(NumberOrString::String),
+        source: Some("apl".to_string()),
+        message,
+        ..lsp_types::Diagnostic::default()
+    }
+}
+
+fn document_symbol(document: &Document, symbol: &Symbol) -> DocumentSymbol {
+    let kind = match symbol.kind {
+        SymbolKind::Function => lsp_types::SymbolKind::FUNCTION,
+        SymbolKind::Variable => lsp_types::SymbolKind::VARIABLE,
+    };
+    let children = symbol
+        .children
+        .iter()
+        .map(|child| document_symbol(document, child))
+        .collect();
+
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name: symbol.name.clone(),
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: document.range(symbol.span),
+        selection_range: document.range(symbol.name_span),
+        children: Some(children),
+    }
+}
+
+// Labels a signature `name(a, b)`, pointing at each parameter by its UTF-16
+// offsets in the label
+fn signature_information(signature: Signature) -> SignatureInformation {
+    let utf16_len = |text: &str| text.encode_utf16().count() as u32;
+
+    let mut parameters = Vec::new();
+    let mut offset = utf16_len(&signature.name) + 1;
+    for param in &signature.params {
+        let end = offset + utf16_len(param);
+        parameters.push(ParameterInformation {
+            label: ParameterLabel::LabelOffsets([offset, end]),
+            documentation: None,
+        });
+        offset = end + 2;
+    }
+
+    SignatureInformation {
+        label: format!("{}({})", signature.name, signature.params.join(", ")),
+        documentation: Some(lsp_types::Documentation::String(signature.detail)),
+        parameters: Some(parameters),
+        active_parameter: None,
+    }
+}
+
+fn text_edit(document: &Document, edit: Edit) -> TextEdit {
+    TextEdit::new(document.range(edit.span), edit.text)
+}
+
+fn code_action(
+    title: &str,
+    kind: CodeActionKind,
+    uri: &Uri,
+    document: &Document,
+    edits: Vec<Edit>,
+) -> CodeActionOrCommand {
+    let edits = edits
+        .into_iter()
+        .map(|edit| text_edit(document, edit))
+        .collect();
+
+    CodeActionOrCommand::CodeAction(CodeAction {
+        title: title.to_string(),
+        kind: Some(kind),
+        edit: Some(WorkspaceEdit::new(HashMap::from([(uri.clone(), edits)]))),
+        ..CodeAction::default()
+    })
+}
+
+// The path of a `file:` URI
+fn file_path(uri: &Uri) -> Option<PathBuf> {
+    if uri.scheme()?.as_str() != "file" {
+        return None;
+    }
+    let path = uri.path().as_estr().decode().into_string().ok()?;
+    Some(PathBuf::from(path.into_owned()))
+}
+
+// A `file:` URI for an absolute path, percent-encoding anything but
+// unreserved characters and separators
+fn file_uri(path: &Path) -> Option<Uri> {
+    let mut uri = String::from("file://");
+    for byte in path.to_str()?.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri.parse().ok()
+}
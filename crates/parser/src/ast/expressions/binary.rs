@@ -3,14 +3,14 @@ use apl_derive::New;
 
 use super::Expr;
 
-#[derive(New, PartialEq, Debug)]
+#[derive(New, PartialEq, Debug, Clone)]
 pub struct BinaryExpr {
-    left: Box<Expr>,
-    op: BinaryOp,
-    right: Box<Expr>,
+    pub left: Box<Expr>,
+    pub op: BinaryOp,
+    pub right: Box<Expr>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum BinaryOp {
     // Math
     Add,
@@ -47,4 +47,4 @@ impl BinaryOp {
             _ => Err("Unknown binary operator".to_string()),
         }
     }
-}
\ No newline at end of file
+}
@@ -6,7 +6,12 @@ pub use binary::{BinaryExpr, BinaryOp};
 pub use unary::{UnaryExpr, UnaryOp};
 pub use variable::Variable;
 
-#[derive(Debug, PartialEq)]
+use apl_source::{Span, Symbol};
+use num_bigint::BigInt;
+
+use super::Ident;
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Expr {
     Literal(Literal),
     Variable(Variable),
@@ -14,7 +19,7 @@ pub enum Expr {
     Unary(UnaryExpr),
     Grouping(Box<Expr>),
     Assignment {
-        name: String,
+        name: Ident,
         value: Box<Expr>,
     },
     Call {
@@ -27,13 +32,61 @@ pub enum Expr {
     },
     PropertyAccess {
         object: Box<Expr>,
-        propert: String,
+        propert: Ident,
+    },
+    Identifier(Symbol),
+    Array(Vec<Expr>),
+    Tuple(Vec<Expr>),
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
     },
-    Identifier(String),
+    // `"text ${expr} text"`, spanning its quotes
+    Interpolated {
+        parts: Vec<Part>,
+        span: Span,
+    },
+}
+
+/// A piece of an interpolated string, empty text between expressions is
+/// left out.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Part {
+    Text(String),
+    Expr(Expr),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
-    Integer(i32),
-    Float(f32),
+    // Numbers keep the source they're written as, so their radix,
+    // separators and exponent can be written back the same way
+    Integer {
+        value: i64,
+        lexeme: String,
+    },
+    // Integers that don't fit in an `i64`
+    BigInteger {
+        value: BigInt,
+        lexeme: String,
+    },
+    Float {
+        value: f32,
+        lexeme: String,
+    },
+    // `mantissa / 10^scale`, written with a `d` suffix
+    Decimal {
+        mantissa: BigInt,
+        scale: u32,
+        lexeme: String,
+    },
+    // The value after escapes, and the source it's written as so a raw or
+    // multi-line string can be written back the same way
+    String {
+        value: String,
+        lexeme: String,
+    },
+    Char {
+        value: char,
+        lexeme: String,
+    },
 }
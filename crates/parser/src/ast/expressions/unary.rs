@@ -3,13 +3,13 @@ use apl_scanner::TokenType;
 
 use super::Expr;
 
-#[derive(New, PartialEq, Debug)]
+#[derive(New, PartialEq, Debug, Clone)]
 pub struct UnaryExpr {
-    op: UnaryOp,
-    right: Box<Expr>,
+    pub op: UnaryOp,
+    pub right: Box<Expr>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum UnaryOp {
     Not,
     Negative,
@@ -23,4 +23,4 @@ impl UnaryOp {
             _ => Err("Invalid unary operator".to_string()),
         }
     }
-}
\ No newline at end of file
+}
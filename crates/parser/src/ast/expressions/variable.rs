@@ -1,17 +1,18 @@
 use apl_derive::New;
 
+use crate::ast::Ident;
 use crate::ast::node::{AstNode, Statement};
 
-#[derive(Debug, New, PartialEq)]
+#[derive(Debug, New, PartialEq, Clone)]
 pub struct Variable {
-    pub name: String,
+    pub name: Ident,
 }
 
 impl AstNode for Variable {
     fn visit(&self) {
         todo!()
     }
-    
+
     fn accept(&self) {
         todo!()
     }
@@ -21,4 +22,4 @@ impl Statement for Variable {
     fn execute(&self) {
         todo!()
     }
-}
\ No newline at end of file
+}
@@ -1,6 +1,13 @@
 mod expressions;
+mod ident;
 pub mod node;
+mod pattern;
 mod statements;
 
-pub use expressions::{BinaryExpr, BinaryOp, UnaryExpr, UnaryOp, Expr, Literal, Variable};
-pub use statements::{Stmt, VariableDecl, Function};
+pub use expressions::{BinaryExpr, BinaryOp, Expr, Literal, Part, UnaryExpr, UnaryOp, Variable};
+pub use ident::Ident;
+pub use pattern::{FieldPattern, Pattern};
+pub use statements::{
+    ForLoop, Function, FunctionCall, IfStmt, Import, MethodCall, Stmt, Use, VariableDecl,
+    Visibility, WhileLoop,
+};
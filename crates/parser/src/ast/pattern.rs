@@ -0,0 +1,63 @@
+use apl_derive::New;
+
+use super::{Ident, Literal};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    Identifier(Ident),
+    Wildcard,
+    Literal(Literal),
+    // `..rest` or `..` inside an array or tuple pattern
+    Rest(Option<Ident>),
+    Array(Vec<Pattern>),
+    Tuple(Vec<Pattern>),
+    Struct {
+        name: Ident,
+        fields: Vec<FieldPattern>,
+    },
+}
+
+#[derive(New, Debug, PartialEq, Clone)]
+pub struct FieldPattern {
+    pub name: Ident,
+    pub pattern: Pattern,
+}
+
+impl Pattern {
+    /// Names bound by this pattern, in source order.
+    pub fn bindings(&self) -> Vec<&Ident> {
+        let mut names = Vec::new();
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings<'a>(&'a self, names: &mut Vec<&'a Ident>) {
+        match self {
+            Pattern::Identifier(name) | Pattern::Rest(Some(name)) => names.push(name),
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Rest(None) => {}
+            Pattern::Array(elements) | Pattern::Tuple(elements) => {
+                for element in elements {
+                    element.collect_bindings(names);
+                }
+            }
+            Pattern::Struct { fields, .. } => {
+                for field in fields {
+                    field.pattern.collect_bindings(names);
+                }
+            }
+        }
+    }
+
+    /// A pattern is refutable when it can fail for a value of the right shape,
+    /// which makes it unusable in `let` and `for` bindings.
+    pub fn is_refutable(&self) -> bool {
+        match self {
+            Pattern::Literal(_) => true,
+            Pattern::Identifier(_) | Pattern::Wildcard | Pattern::Rest(_) => false,
+            Pattern::Array(elements) | Pattern::Tuple(elements) => {
+                elements.iter().any(Pattern::is_refutable)
+            }
+            Pattern::Struct { fields, .. } => fields.iter().any(|f| f.pattern.is_refutable()),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use apl_derive::New;
+
+use crate::ast::Expr;
+
+use super::Stmt;
+
+#[derive(New, PartialEq, Debug, Clone)]
+pub struct IfStmt {
+    pub condition: Expr,
+    pub then_branch: Vec<Stmt>,
+    // `else if` is an `else` branch holding a single `if` statement
+    pub else_branch: Option<Vec<Stmt>>,
+}
+
+#[derive(New, PartialEq, Debug, Clone)]
+pub struct WhileLoop {
+    pub condition: Expr,
+    pub body: Vec<Stmt>,
+}
@@ -0,0 +1,14 @@
+use apl_derive::New;
+use apl_source::Span;
+
+use crate::ast::{Expr, Pattern};
+
+use super::Stmt;
+
+#[derive(New, PartialEq, Debug, Clone)]
+pub struct ForLoop {
+    pub pattern: Pattern,
+    pub pattern_span: Span,
+    pub iterable: Expr,
+    pub body: Vec<Stmt>,
+}
@@ -1,25 +1,26 @@
 use apl_derive::New;
 
-use crate::ast::Expr;
+use crate::ast::{Expr, Ident};
 
-use super::Stmt;
+use super::{Stmt, Visibility};
 
-#[derive(New, PartialEq, Debug)]
+#[derive(New, PartialEq, Debug, Clone)]
 pub struct Function {
-    name: String,
-    params: Vec<String>,
-    statements: Vec<Stmt>,
+    pub name: Ident,
+    pub params: Vec<Ident>,
+    pub statements: Vec<Stmt>,
+    pub visibility: Visibility,
 }
 
-#[derive(New, PartialEq, Debug)]
+#[derive(New, PartialEq, Debug, Clone)]
 pub struct FunctionCall {
     name: String,
     params: Vec<Expr>,
 }
 
-#[derive(New, PartialEq, Debug)]
+#[derive(New, PartialEq, Debug, Clone)]
 pub struct MethodCall {
     obj_name: String,
     name: String,
     params: Vec<Expr>,
-}
\ No newline at end of file
+}
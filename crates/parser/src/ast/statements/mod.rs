@@ -1,14 +1,35 @@
-mod variable_decl;
+mod control_flow;
+mod for_loop;
 mod function;
+mod module;
+mod variable_decl;
 
-pub use function::Function;
+pub use control_flow::{IfStmt, WhileLoop};
+pub use for_loop::ForLoop;
+pub use function::{Function, FunctionCall, MethodCall};
+pub use module::{Import, Use};
 pub use variable_decl::VariableDecl;
 
 use super::Expr;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Stmt {
     VariableDecl(VariableDecl),
     Expression(Expr),
     FunctionDecl(Function),
-}
\ No newline at end of file
+    For(ForLoop),
+    If(IfStmt),
+    While(WhileLoop),
+    Loop(Vec<Stmt>),
+    Break,
+    Continue,
+    Return(Option<Expr>),
+    Import(Import),
+    Use(Use),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Visibility {
+    Private,
+    Public,
+}
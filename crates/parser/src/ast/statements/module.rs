@@ -0,0 +1,15 @@
+use apl_derive::New;
+
+use crate::ast::Ident;
+
+#[derive(New, PartialEq, Debug, Clone)]
+pub struct Import {
+    pub path: String,
+    pub alias: Ident,
+}
+
+#[derive(New, PartialEq, Debug, Clone)]
+pub struct Use {
+    pub module: Ident,
+    pub names: Vec<Ident>,
+}
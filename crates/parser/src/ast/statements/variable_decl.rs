@@ -1,13 +1,29 @@
-use super::super::Expr;
+use apl_source::Span;
 
-#[derive(Debug, PartialEq)]
+use super::super::{Expr, Pattern};
+use super::Visibility;
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct VariableDecl {
-    name: String,
-    initializer: Expr,
+    pub pattern: Pattern,
+    // The whole pattern, which names alone don't cover
+    pub pattern_span: Span,
+    pub initializer: Expr,
+    pub visibility: Visibility,
 }
 
 impl VariableDecl {
-    pub fn new(name: String, initializer: Expr) -> Self {
-        Self { name, initializer }
+    pub fn new(
+        pattern: Pattern,
+        pattern_span: Span,
+        initializer: Expr,
+        visibility: Visibility,
+    ) -> Self {
+        Self {
+            pattern,
+            pattern_span,
+            initializer,
+            visibility,
+        }
     }
-}
\ No newline at end of file
+}
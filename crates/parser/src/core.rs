@@ -1,51 +1,245 @@
+use std::collections::VecDeque;
+
+use apl_error::{ParseError, parseerror::ParseErrorKind};
 use apl_scanner::{Token, TokenType};
+use apl_source::Span;
+
+use super::{Ident, Stmt};
 
-use super::Stmt;
+// How many tokens past the current one the parser looks at
+const LOOKAHEAD: usize = 1;
 
-pub struct Parser {
-    tokens: Vec<Token>,
-    current: usize,
+/// Parses tokens as it pulls them from an iterator, e.g. a `Scanner`, so the
+/// whole module's tokens never have to be held at once.
+pub struct Parser<'a> {
+    tokens: Box<dyn Iterator<Item = Token<'a>> + 'a>,
+    // The current token and up to `LOOKAHEAD` tokens after it
+    lookahead: VecDeque<Token<'a>>,
+    previous: Option<Token<'a>>,
+    // How many tokens were consumed
+    position: usize,
+    // Nesting used to reject `break` and `return` where they can't apply
+    pub(crate) loop_depth: usize,
+    pub(crate) function_depth: usize,
+    statement_spans: Vec<Span>,
+    // Set by `parse_recovering`, statements that fail to parse are recorded
+    // in `errors` and skipped instead of ending the parse
+    recovering: bool,
+    errors: Vec<ParseError>,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+impl<'a> Parser<'a> {
+    /// A parser over `tokens`, which end with `Eof`. Passing `&mut scanner`
+    /// leaves the scanner to read its errors from after parsing.
+    pub fn new<'s: 'a, I>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = Token<'s>>,
+        I::IntoIter: 'a,
+    {
+        // Shortens the tokens' lifetime to the parser's, so `&mut scanner`
+        // only has to be borrowed for as long as the parser is used
+        #[allow(clippy::map_identity)]
+        let tokens = tokens.into_iter().map(|token| -> Token<'a> { token });
+        let mut parser = Self {
+            tokens: Box::new(tokens),
+            lookahead: VecDeque::with_capacity(LOOKAHEAD + 1),
+            previous: None,
+            position: 0,
+            loop_depth: 0,
+            function_depth: 0,
+            statement_spans: Vec::new(),
+            recovering: false,
+            errors: Vec::new(),
+        };
+        parser.fill();
+        parser
     }
 
     // Should return an AST
     pub fn parse(&mut self) -> Vec<Stmt> {
+        match self.try_parse() {
+            Ok(statements) => statements,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Parses a whole module, returning the first error instead of panicking.
+    pub fn try_parse(&mut self) -> Result<Vec<Stmt>, ParseError> {
         let mut statements = Vec::new();
 
         while !self.is_at_end() {
-            let stmt = match self.statement() {
-                Ok(stmt) => stmt,
-                Err(e) => panic!("{}", e)
-            };
-            statements.push(stmt);
+            statements.extend(self.recover(Self::declaration)?);
         }
 
-        statements
+        Ok(statements)
+    }
+
+    /// Parses a whole module, skipping statements that fail to parse so the
+    /// rest of it is still parsed. Blocks left open at the end of the input
+    /// keep the statements parsed so far. Returns every statement that parsed
+    /// along with all the errors, for tools that work on incomplete code.
+    pub fn parse_recovering(&mut self) -> (Vec<Stmt>, Vec<ParseError>) {
+        self.recovering = true;
+        let statements = self
+            .try_parse()
+            .expect("errors are recorded while recovering");
+        (statements, std::mem::take(&mut self.errors))
+    }
+
+    // A recovering parser over the tokens of a module from the one at
+    // `position`, for reparsing part of it
+    pub(crate) fn resume<'s: 'a, I>(tokens: I, position: usize) -> Self
+    where
+        I: IntoIterator<Item = Token<'s>>,
+        I::IntoIter: 'a,
+    {
+        Self {
+            position,
+            recovering: true,
+            ..Self::new(tokens)
+        }
     }
 
-    pub(crate) fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+    // Parses one declaration of a module while recovering, returning it
+    // (unless it failed to parse) with the spans and errors recorded for it
+    pub(crate) fn next_declaration(&mut self) -> (Option<Stmt>, Vec<Span>, Vec<ParseError>) {
+        let statement = self
+            .recover(Self::declaration)
+            .expect("errors are recorded while recovering");
+        (
+            statement,
+            std::mem::take(&mut self.statement_spans),
+            std::mem::take(&mut self.errors),
+        )
     }
 
-    pub(crate) fn peek_n(&self, n: usize) -> Option<&Token> {
-        self.tokens.get(self.current + n)
+    pub(crate) fn position(&self) -> usize {
+        self.position
     }
 
-    pub(crate) fn previous(&self) -> &Token {
-        &self.tokens[self.current - 1]
+    /// Where each statement parsed so far starts and ends, in source order.
+    /// Nested statements, including the `if` of an `else if`, follow the
+    /// statement containing them.
+    pub fn statement_spans(&self) -> &[Span] {
+        &self.statement_spans
+    }
+
+    // Parses a statement of a module or block, recording its span
+    pub(crate) fn spanned(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Stmt, ParseError>,
+    ) -> Result<Stmt, ParseError> {
+        let index = self.statement_spans.len();
+        let start = self.peek().span;
+        self.statement_spans.push(start);
+
+        match parse(self) {
+            Ok(stmt) => {
+                self.statement_spans[index] = start.to(self.previous().span);
+                Ok(stmt)
+            }
+            // Drop the spans of the statement and anything nested in it
+            Err(error) => {
+                self.statement_spans.truncate(index);
+                Err(error)
+            }
+        }
+    }
+
+    // Parses a statement with `parse`. When recovering, a failed statement's
+    // error is recorded and the rest of it skipped
+    pub(crate) fn recover(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Stmt, ParseError>,
+    ) -> Result<Option<Stmt>, ParseError> {
+        let start = self.position;
+        match self.spanned(parse) {
+            Ok(stmt) => Ok(Some(stmt)),
+            Err(error) if self.recovering => {
+                self.errors.push(error);
+                self.synchronize(start);
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    // Skips to where the next statement likely starts: past a `;`, or up to a
+    // `}` or a keyword starting a statement. Always moves past the token the
+    // failed statement started at, so recovery can't get stuck.
+    fn synchronize(&mut self, start: usize) {
+        if self.position == start {
+            self.advance();
+        }
+
+        while !self.is_at_end() && self.previous().token_type != TokenType::SemiColon {
+            match self.peek().token_type {
+                TokenType::CurlyClose
+                | TokenType::Let
+                | TokenType::Function
+                | TokenType::For
+                | TokenType::While
+                | TokenType::Loop
+                | TokenType::If
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Pub
+                | TokenType::Import
+                | TokenType::Use => return,
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    // Whether a block left open at the end of the input should end there
+    pub(crate) fn recover_unclosed_block(&mut self) -> bool {
+        if self.recovering && self.is_at_end() {
+            self.errors.push(self.error(
+                ParseErrorKind::UnexpectedToken,
+                "Expected '}' before the end of the input",
+            ));
+            true
+        } else {
+            false
+        }
+    }
+
+    // Pulls tokens until the lookahead is full or the tokens ran out
+    fn fill(&mut self) {
+        while self.lookahead.len() <= LOOKAHEAD {
+            match self.tokens.next() {
+                Some(token) => self.lookahead.push_back(token),
+                None => break,
+            }
+        }
+    }
+
+    pub(crate) fn peek(&self) -> &Token<'a> {
+        self.lookahead.front().expect("tokens end with Eof")
+    }
+
+    // `n` is at most `LOOKAHEAD`
+    pub(crate) fn peek_n(&self, n: usize) -> Option<&Token<'a>> {
+        debug_assert!(n <= LOOKAHEAD);
+        self.lookahead.get(n)
+    }
+
+    pub(crate) fn previous(&self) -> &Token<'a> {
+        self.previous.as_ref().expect("a token was consumed")
     }
 
     pub(crate) fn is_at_end(&self) -> bool {
         self.peek().token_type == TokenType::Eof
     }
 
-    pub(crate) fn advance(&mut self) -> &Token {
+    pub(crate) fn advance(&mut self) -> &Token<'a> {
         if !self.is_at_end() {
-            self.current += 1;
+            self.previous = self.lookahead.pop_front();
+            self.position += 1;
+            self.fill();
         }
         self.previous()
     }
@@ -55,9 +249,13 @@ impl Parser {
             return false;
         }
 
-        match (&self.peek().token_type, &token_type) {
+        Self::same_kind(&self.peek().token_type, token_type)
+    }
+
+    fn same_kind(a: &TokenType, b: &TokenType) -> bool {
+        match (a, b) {
             (TokenType::Identifier(_), TokenType::Identifier(_)) => true,
-            (a, b) => a == *b,
+            (a, b) => a == b,
         }
     }
 
@@ -65,34 +263,45 @@ impl Parser {
         &mut self,
         token_type: TokenType,
         message: &str,
-    ) -> Result<&Token, String> {
+    ) -> Result<&Token<'a>, ParseError> {
         if self.check(&token_type) {
             Ok(self.advance())
         } else {
-            Err(format!(
-                "{} at line {}. Expected {:?}, found {:?}",
-                message,
-                self.peek().line,
-                token_type,
-                self.peek().token_type
+            Err(self.error(
+                ParseErrorKind::UnexpectedToken,
+                format!(
+                    "{}. Expected {:?}, found {:?}",
+                    message,
+                    token_type,
+                    self.peek().token_type
+                ),
             ))
         }
     }
 
+    /// An error reported at the current token.
+    pub(crate) fn error(&self, kind: ParseErrorKind, message: impl Into<String>) -> ParseError {
+        ParseError::new(kind, message, self.peek().span)
+    }
+
+    /// An error reported at the most recently consumed token.
+    pub(crate) fn error_at_previous(
+        &self,
+        kind: ParseErrorKind,
+        message: impl Into<String>,
+    ) -> ParseError {
+        ParseError::new(kind, message, self.previous().span)
+    }
+
     pub(crate) fn check_sequence(&self, sequence: &[TokenType]) -> bool {
-        sequence
-            .iter()
-            .enumerate()
-            .all(|(i, tt)| self.peek_n(i).map(|t| &t.token_type) == Some(tt))
-    }
-
-    pub(crate) fn parse_identifier(&mut self, message: &str) -> Result<String, String> {
-        Ok(self
-            .consume(
-                TokenType::Identifier("".to_string()),
-                message,
-            )?
-            .lexeme
-            .clone())
+        sequence.iter().enumerate().all(|(i, tt)| {
+            self.peek_n(i)
+                .is_some_and(|t| Self::same_kind(&t.token_type, tt))
+        })
+    }
+
+    pub(crate) fn parse_identifier(&mut self, message: &str) -> Result<Ident, ParseError> {
+        let token = self.consume(TokenType::Identifier("".into()), message)?;
+        Ok(Ident::new(token.lexeme, token.span))
     }
 }
@@ -1,22 +1,38 @@
-use super::{Parser, Stmt, VariableDecl};
-use apl_scanner::{Token, TokenType};
+use super::{Import, ParseError, ParseErrorKind, Parser, Stmt, Use, VariableDecl, Visibility};
+use apl_scanner::TokenType;
 
-impl Parser {
-    pub(crate) fn variable_declaration(&mut self) -> Result<Stmt, String> {
+impl Parser<'_> {
+    /// Parses a top-level declaration. Module items (`import`, `use` and
+    /// `pub` declarations) are only valid here, everything else is a statement.
+    pub(crate) fn declaration(&mut self) -> Result<Stmt, ParseError> {
+        match &self.peek().token_type {
+            TokenType::Import => self.import_declaration(),
+            TokenType::Use => self.use_declaration(),
+            TokenType::Pub => {
+                self.advance(); // Consume pub
+
+                match &self.peek().token_type {
+                    TokenType::Let => self.variable_declaration(Visibility::Public),
+                    TokenType::Function => self.parse_function_declaration(Visibility::Public),
+                    _ => Err(self.error(
+                        ParseErrorKind::ExpectedDeclaration,
+                        "Expected 'fn' or 'let' after 'pub'",
+                    )),
+                }
+            }
+            _ => self.statement(),
+        }
+    }
+
+    pub(crate) fn variable_declaration(
+        &mut self,
+        visibility: Visibility,
+    ) -> Result<Stmt, ParseError> {
         self.advance(); // Consume let
 
-        let name = match self.consume(
-            TokenType::Identifier("".to_string()),
-            "Expected variable name",
-        ) {
-            Ok(Token {
-                token_type: TokenType::Identifier(name),
-                ..
-            }) => name.clone(),
-            _ => return Err("Expected identifier".to_string()),
-        };
+        let (pattern, pattern_span) = self.binding_pattern("let")?;
 
-        self.consume(TokenType::Equals, "Expected '=' after variable name")?;
+        self.consume(TokenType::Equals, "Expected '=' after variable pattern")?;
 
         let initializer = self.expression()?;
 
@@ -25,6 +41,60 @@ impl Parser {
             "Expected ';' after variable declaration",
         )?;
 
-        Ok(Stmt::VariableDecl(VariableDecl::new(name, initializer)))
+        Ok(Stmt::VariableDecl(VariableDecl::new(
+            pattern,
+            pattern_span,
+            initializer,
+            visibility,
+        )))
+    }
+
+    fn import_declaration(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::Import, "Expected import keyword")?;
+
+        let token = self.advance();
+        let path = match token.token_type {
+            TokenType::String(_) => token.text().unwrap_or_default().to_string(),
+            _ => {
+                return Err(self.error_at_previous(
+                    ParseErrorKind::ExpectedModulePath,
+                    "Expected module path string after 'import'",
+                ));
+            }
+        };
+
+        self.consume(TokenType::As, "Expected 'as' after module path")?;
+        let alias = self.parse_identifier("Expected module alias after 'as'")?;
+        self.consume(TokenType::SemiColon, "Expected ';' after import")?;
+
+        Ok(Stmt::Import(Import::new(path, alias)))
+    }
+
+    fn use_declaration(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::Use, "Expected use keyword")?;
+
+        let module = self.parse_identifier("Expected module name after 'use'")?;
+        self.consume(TokenType::Dot, "Expected '.' after module name")?;
+
+        // Either `use m.a;` or `use m.{a, b};`
+        let mut names = Vec::new();
+        if self.check(&TokenType::CurlyOpen) {
+            self.advance();
+            while !self.check(&TokenType::CurlyClose) {
+                names.push(self.parse_identifier("Expected imported name")?);
+
+                if !self.check(&TokenType::Comma) {
+                    break;
+                }
+                self.advance(); // Consume comma
+            }
+            self.consume(TokenType::CurlyClose, "Expected '}' after imported names")?;
+        } else {
+            names.push(self.parse_identifier("Expected imported name")?);
+        }
+
+        self.consume(TokenType::SemiColon, "Expected ';' after use")?;
+
+        Ok(Stmt::Use(Use::new(module, names)))
     }
-}
\ No newline at end of file
+}
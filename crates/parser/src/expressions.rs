@@ -1,14 +1,15 @@
-use crate::ast::{UnaryExpr, UnaryOp};
+use super::{
+    BinaryExpr, BinaryOp, Expr, Ident, Literal, ParseError, ParseErrorKind, Parser, Part,
+    UnaryExpr, UnaryOp, Variable,
+};
+use apl_scanner::{Number, TokenType};
 
-use super::{BinaryExpr, BinaryOp, Expr, Literal, Parser, Variable};
-use apl_scanner::TokenType;
-
-impl Parser {
-    pub(crate) fn expression(&mut self) -> Result<Expr, String> {
+impl Parser<'_> {
+    pub(crate) fn expression(&mut self) -> Result<Expr, ParseError> {
         self.assignment()
     }
 
-    fn assignment(&mut self) -> Result<Expr, String> {
+    fn assignment(&mut self) -> Result<Expr, ParseError> {
         let expr = self.equality()?;
 
         if self.check(&TokenType::Equals) {
@@ -22,13 +23,16 @@ impl Parser {
                 });
             }
 
-            return Err("Invalid assignment target".to_string());
+            return Err(self.error_at_previous(
+                ParseErrorKind::InvalidAssignmentTarget,
+                "Invalid assignment target",
+            ));
         }
 
         Ok(expr)
     }
 
-    fn equality(&mut self) -> Result<Expr, String> {
+    fn equality(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.comparison()?;
 
         while self.check(&TokenType::EqualsEquals) || self.check(&TokenType::BangEquals) {
@@ -37,7 +41,8 @@ impl Parser {
 
             expr = Expr::Binary(BinaryExpr::new(
                 Box::new(expr),
-                BinaryOp::new(operator.token_type)?,
+                BinaryOp::new(operator.token_type)
+                    .map_err(|e| self.error_at_previous(ParseErrorKind::InvalidOperator, e))?,
                 Box::new(right),
             ));
         }
@@ -46,7 +51,7 @@ impl Parser {
     }
 
     // Add comparison operators (>, >=, <, <=)
-    fn comparison(&mut self) -> Result<Expr, String> {
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.term()?;
 
         while self.check(&TokenType::GreaterThan)
@@ -58,7 +63,8 @@ impl Parser {
             let right = self.term()?;
             expr = Expr::Binary(BinaryExpr::new(
                 Box::new(expr),
-                BinaryOp::new(operator.token_type)?,
+                BinaryOp::new(operator.token_type)
+                    .map_err(|e| self.error_at_previous(ParseErrorKind::InvalidOperator, e))?,
                 Box::new(right),
             ));
         }
@@ -66,7 +72,7 @@ impl Parser {
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, String> {
+    fn term(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.factor()?;
 
         while self.check(&TokenType::Plus) || self.check(&TokenType::Minus) {
@@ -74,7 +80,8 @@ impl Parser {
             let right = self.factor()?;
             expr = Expr::Binary(BinaryExpr::new(
                 Box::new(expr),
-                BinaryOp::new(operator.token_type)?,
+                BinaryOp::new(operator.token_type)
+                    .map_err(|e| self.error_at_previous(ParseErrorKind::InvalidOperator, e))?,
                 Box::new(right),
             ))
         }
@@ -82,7 +89,7 @@ impl Parser {
         Ok(expr)
     }
 
-    fn factor(&mut self) -> Result<Expr, String> {
+    fn factor(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.unary()?;
 
         while self.check(&TokenType::Multiply)
@@ -93,7 +100,8 @@ impl Parser {
             let right = self.unary()?;
             expr = Expr::Binary(BinaryExpr::new(
                 Box::new(expr),
-                BinaryOp::new(operator.token_type)?,
+                BinaryOp::new(operator.token_type)
+                    .map_err(|e| self.error_at_previous(ParseErrorKind::InvalidOperator, e))?,
                 Box::new(right),
             ))
         }
@@ -101,87 +109,224 @@ impl Parser {
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, String> {
+    fn unary(&mut self) -> Result<Expr, ParseError> {
         if self.check(&TokenType::Bang) || self.check(&TokenType::Minus) {
             let operator = self.advance().clone();
             let right = self.unary()?;
             Ok(Expr::Unary(UnaryExpr::new(
-                UnaryOp::new(operator.token_type)?,
+                UnaryOp::new(operator.token_type)
+                    .map_err(|e| self.error_at_previous(ParseErrorKind::InvalidOperator, e))?,
                 Box::new(right),
             )))
         } else {
-            self.primary()
+            self.postfix()
+        }
+    }
+
+    // Method and property accesses and indexing can be chained, e.g. `a.b()[0].c`
+    fn postfix(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.check(&TokenType::Dot) {
+                expr = self.parse_method_access(expr)?;
+            } else if self.check(&TokenType::BracketOpen) {
+                self.advance();
+                let index = self.expression()?;
+                self.consume(TokenType::BracketClose, "Expected ']' after index")?;
+                expr = Expr::Index {
+                    object: Box::new(expr),
+                    index: Box::new(index),
+                };
+            } else {
+                break;
+            }
         }
+
+        Ok(expr)
     }
 
-    fn primary(&mut self) -> Result<Expr, String> {
+    fn primary(&mut self) -> Result<Expr, ParseError> {
         match &self.peek().token_type {
             TokenType::Number(n) => {
-                let num = n
-                    .parse()
-                    .map_err(|_| "Invalid number literal".to_string())?;
+                let literal = self.number_literal(n.clone())?;
                 self.advance();
-                Ok(Expr::Literal(Literal::Integer(num)))
+                Ok(Expr::Literal(literal))
+            }
+            TokenType::String(_) => {
+                let token = self.advance();
+                Ok(Expr::Literal(Literal::String {
+                    value: token.text().unwrap_or_default().to_string(),
+                    lexeme: token.lexeme.to_string(),
+                }))
+            }
+            TokenType::StringStart(_) => self.interpolated_string(),
+            TokenType::Char(c) => {
+                let value = *c;
+                let lexeme = self.advance().lexeme.to_string();
+                Ok(Expr::Literal(Literal::Char { value, lexeme }))
             }
             // Check if this is a function call
             TokenType::Identifier(name) => {
-                let name_clone = name.clone();
+                let name = Ident::new(*name, self.peek().span);
                 self.advance();
 
-                if self.check(&TokenType::Dot) {
-                    self.parse_method_access(Expr::Variable(Variable { name: name_clone }))
-                } else if self.check(&TokenType::ParenthesesOpen){
-                    self.parse_call_expression(name_clone)
+                if self.check(&TokenType::ParenthesesOpen) {
+                    self.parse_call_expression(name)
                 } else {
-                    Ok(Expr::Variable(Variable { name: name_clone }))
+                    Ok(Expr::Variable(Variable::new(name)))
                 }
             }
+            TokenType::BracketOpen => {
+                self.advance();
+                let (elements, _) = self.expression_list(TokenType::BracketClose)?;
+                Ok(Expr::Array(elements))
+            }
             TokenType::ParenthesesOpen => {
                 self.advance();
-                let expr = self.expression()?;
-                self.consume(TokenType::ParenthesesClose, "Expected ')' after expression")?;
-                Ok(Expr::Grouping(Box::new(expr)))
+                let (mut elements, trailing_comma) =
+                    self.expression_list(TokenType::ParenthesesClose)?;
+
+                // `(a)` is a grouping, `()`, `(a,)` and `(a, b)` are tuples
+                if elements.len() == 1 && !trailing_comma {
+                    Ok(Expr::Grouping(Box::new(elements.remove(0))))
+                } else {
+                    Ok(Expr::Tuple(elements))
+                }
             }
-            _ => Err("Expected expression".to_string()),
+            _ => Err(self.error(ParseErrorKind::ExpectedExpression, "Expected expression")),
         }
     }
 
-    pub(crate) fn parse_call_expression(&mut self, callee: String) -> Result<Expr, String> {
-        self.consume(TokenType::ParenthesesOpen, "Expected '(' after function name")?;
+    // `StringStart expression (StringMiddle expression)* StringEnd`
+    fn interpolated_string(&mut self) -> Result<Expr, ParseError> {
+        let mut parts = Vec::new();
+        let start = self.advance();
+        let span = start.span;
+        let mut text = match start.token_type {
+            TokenType::StringStart(_) => start.text().unwrap_or_default().to_string(),
+            _ => unreachable!("called at the start of an interpolated string"),
+        };
+
+        loop {
+            if !text.is_empty() {
+                parts.push(Part::Text(text));
+            }
+            parts.push(Part::Expr(self.expression()?));
 
-        let mut arguments = Vec::new();
-        if !self.check(&TokenType::ParenthesesClose) {
-            loop {
-                arguments.push(self.expression()?);
-                if !self.check(&TokenType::Comma) {
-                    break;
+            let token = self.peek();
+            text = match token.token_type {
+                TokenType::StringMiddle(_) => token.text().unwrap_or_default().to_string(),
+                TokenType::StringEnd(_) => {
+                    let text = token.text().unwrap_or_default();
+                    if !text.is_empty() {
+                        parts.push(Part::Text(text.to_string()));
+                    }
+                    let span = span.to(self.advance().span);
+                    return Ok(Expr::Interpolated { parts, span });
                 }
-                self.advance();
+                _ => {
+                    return Err(self.error(
+                        ParseErrorKind::UnexpectedToken,
+                        "Expected '}' after interpolated expression",
+                    ));
+                }
+            };
+            self.advance();
+        }
+    }
+
+    // The literal for `number`, the value of the current token
+    pub(crate) fn number_literal(&self, number: Number) -> Result<Literal, ParseError> {
+        let lexeme = self.peek().lexeme.to_string();
+        match number {
+            Number::Integer(value) => Ok(match i64::try_from(value) {
+                Ok(value) => Literal::Integer { value, lexeme },
+                Err(_) => Literal::BigInteger {
+                    value: value.into(),
+                    lexeme,
+                },
+            }),
+            Number::BigInteger(value) => Ok(Literal::BigInteger {
+                value: value.into(),
+                lexeme,
+            }),
+            Number::Float(value) if (value as f32).is_infinite() => Err(self.error(
+                ParseErrorKind::InvalidNumberLiteral,
+                "Float literal is too large",
+            )),
+            Number::Float(value) => Ok(Literal::Float {
+                value: value as f32,
+                lexeme,
+            }),
+            Number::Decimal { mantissa, scale } => Ok(Literal::Decimal {
+                mantissa: mantissa.into(),
+                scale,
+                lexeme,
+            }),
+        }
+    }
+
+    /// Parses comma separated expressions up to and including `close`,
+    /// reporting whether the list ended with a trailing comma.
+    fn expression_list(&mut self, close: TokenType) -> Result<(Vec<Expr>, bool), ParseError> {
+        let mut elements = Vec::new();
+        let mut trailing_comma = false;
+
+        while !self.check(&close) {
+            elements.push(self.expression()?);
+
+            trailing_comma = self.check(&TokenType::Comma);
+            if !trailing_comma {
+                break;
             }
+            self.advance(); // Consume comma
         }
 
-        self.consume(TokenType::ParenthesesClose, "Expected ')' after arguments")?;
+        self.consume(close, "Expected end of list")?;
+
+        Ok((elements, trailing_comma))
+    }
+
+    pub(crate) fn parse_call_expression(&mut self, callee: Ident) -> Result<Expr, ParseError> {
+        self.consume(
+            TokenType::ParenthesesOpen,
+            "Expected '(' after function name",
+        )?;
+
+        // A trailing comma is allowed, e.g. when arguments are one per line
+        let (arguments, _) = self.expression_list(TokenType::ParenthesesClose)?;
 
         Ok(Expr::Call {
-            callee: Box::new(Expr::Variable(Variable { name: callee })),
+            callee: Box::new(Expr::Variable(Variable::new(callee))),
             arguments,
         })
     }
 
-    fn parse_method_access(&mut self, object: Expr) -> Result<Expr, String> {
+    fn parse_method_access(&mut self, object: Expr) -> Result<Expr, ParseError> {
         self.consume(TokenType::Dot, "Expected '.' after object")?;
 
         let method = match self.advance().token_type.clone() {
-            TokenType::Identifier(name) => name,
-            _ => return Err("Expected method name after '.'".to_string()),
+            TokenType::Identifier(name) => Ident::new(name, self.previous().span),
+            _ => {
+                return Err(self.error_at_previous(
+                    ParseErrorKind::UnexpectedToken,
+                    "Expected method name after '.'",
+                ));
+            }
         };
-        
+
         if self.check(&TokenType::ParenthesesOpen) {
-            self.parse_call_expression(method).map(|call_expr| {
-                Expr::MethodAccess { object: Box::new(object), method: Box::new(call_expr) }
-            })
+            self.parse_call_expression(method)
+                .map(|call_expr| Expr::MethodAccess {
+                    object: Box::new(object),
+                    method: Box::new(call_expr),
+                })
         } else {
-            Ok(Expr::PropertyAccess { object: Box::new(object), propert: method })
+            Ok(Expr::PropertyAccess {
+                object: Box::new(object),
+                propert: method,
+            })
         }
     }
 }
@@ -0,0 +1,399 @@
+use std::io::{self, BufRead};
+use std::ops::Range;
+
+use apl_error::{LexError, ParseError};
+use apl_scanner::{Scan, ScannedToken, Scanner, is_incomplete};
+use apl_source::{FileId, Span};
+
+use super::{Expr, Parser, Part, Pattern, Stmt};
+
+// How many tokens past the end of a declaration the parser may have looked
+// at to decide where it ends, e.g. for an `else` after an `if`
+const LOOKAHEAD: usize = 2;
+
+/// A module's tokens and statements, kept up to date as its source is
+/// edited. An edit only rescans the tokens around it and only reparses the
+/// top-level declarations it touched, the rest are reused.
+///
+/// Parsing recovers from errors like `Parser::parse_recovering`, and the
+/// result is always the same as parsing the edited source from scratch.
+///
+/// Sources that are only read can be parsed as they're scanned instead, see
+/// [`SyntaxTree::parse`], without keeping their tokens.
+#[derive(Clone)]
+pub struct SyntaxTree {
+    source: String,
+    scan: Scan,
+    statements: Vec<Stmt>,
+    declarations: Vec<Declaration>,
+}
+
+// A top-level declaration, or the tokens skipped after one that failed
+#[derive(Clone)]
+struct Declaration {
+    // Indices into the tokens
+    tokens: Range<usize>,
+    parsed: bool,
+    // The spans `Parser::statement_spans` would have recorded for it
+    spans: Vec<Span>,
+    errors: Vec<ParseError>,
+}
+
+impl SyntaxTree {
+    pub fn new(source: String, file: FileId) -> Self {
+        let scan = Scan::new(&source, file);
+        let tokens = scan.tokens.iter().map(|token| token.token(&source));
+        let mut parser = Parser::resume(tokens, 0);
+
+        let mut statements = Vec::new();
+        let mut declarations = Vec::new();
+        while !parser.is_at_end() {
+            let (statement, declaration) = Self::parse_declaration(&mut parser);
+            statements.extend(statement);
+            declarations.push(declaration);
+        }
+        drop(parser);
+
+        Self {
+            source,
+            scan,
+            statements,
+            declarations,
+        }
+    }
+
+    /// Parses `source` as it's scanned, so its tokens are never all held at
+    /// once and aren't kept. The first edit scans it in full.
+    pub fn parse(source: String, file: FileId) -> Self {
+        let mut tree = Self::unscanned(source, file);
+        tree.parse_from(0, 1, 1, tree.source.len());
+        tree
+    }
+
+    /// Parses what `reader` reads like [`SyntaxTree::parse`], a line at a
+    /// time: the declarations read so far are parsed once the next line
+    /// shows they're complete, so parsing keeps up with reading.
+    pub fn read(mut reader: impl BufRead, file: FileId) -> io::Result<Self> {
+        let mut tree = Self::unscanned(String::new(), file);
+        // Where parsing stopped, and where the text read after it last ended
+        // with complete declarations
+        let (mut start, mut line, mut column) = (0, 1, 1);
+        let mut complete = 0;
+
+        loop {
+            let end = tree.source.len();
+            let read = reader.read_line(&mut tree.source)?;
+            let next = tree.source[end..].trim();
+
+            // A line starting with `else` continues the `if` before it, and a
+            // blank or comment line doesn't tell yet
+            let continued = next.is_empty() || next.starts_with("//") || next.starts_with("else");
+            if complete > start && (read == 0 || !continued) {
+                (line, column) = tree.parse_from(start, line, column, complete);
+                start = complete;
+            }
+            if read == 0 {
+                break;
+            }
+
+            let pending = &tree.source[start..];
+            if pending.trim_end().ends_with([';', '}']) && !is_incomplete(pending) {
+                complete = tree.source.len();
+            } else if !continued {
+                complete = start;
+            }
+        }
+
+        if start < tree.source.len() {
+            tree.parse_from(start, line, column, tree.source.len());
+        }
+        Ok(tree)
+    }
+
+    // A tree with no tokens, statements or errors yet
+    fn unscanned(source: String, file: FileId) -> Self {
+        Self {
+            source,
+            scan: Scan {
+                tokens: Vec::new(),
+                errors: Vec::new(),
+                comments: Vec::new(),
+                file,
+            },
+            statements: Vec::new(),
+            declarations: Vec::new(),
+        }
+    }
+
+    // Parses the source from `start`, at `line` and `column`, to `end` as
+    // it's scanned, returning the line and column `end` is at
+    fn parse_from(
+        &mut self,
+        start: usize,
+        line: usize,
+        column: usize,
+        end: usize,
+    ) -> (usize, usize) {
+        let source = &self.source[..end];
+        let mut scanner = Scanner::resume(source, self.scan.file, start, line, column);
+        let position = self.declarations.last().map_or(0, |d| d.tokens.end);
+        let mut parser = Parser::resume(&mut scanner, position);
+
+        while !parser.is_at_end() {
+            let (statement, declaration) = Self::parse_declaration(&mut parser);
+            self.statements.extend(statement);
+            self.declarations.push(declaration);
+        }
+        let eof = parser.peek();
+        let end = (eof.line, eof.column);
+        drop(parser);
+
+        self.scan.errors.append(&mut scanner.errors);
+        self.scan.comments.append(&mut scanner.comments);
+        end
+    }
+
+    /// Replaces `range` of the source with `text`.
+    pub fn edit(&mut self, range: Range<usize>, text: &str) {
+        self.source.replace_range(range.clone(), text);
+        // A tree parsed as it was scanned has no tokens to reuse, an empty
+        // source still scans to `Eof`
+        if self.scan.tokens.is_empty() {
+            *self = Self::new(std::mem::take(&mut self.source), self.scan.file);
+            return;
+        }
+
+        let delta = text.len() as isize - range.len() as isize;
+        let change = self.scan.edit(&self.source, range, text.len());
+        let tokens_delta = change.inserted as isize - change.removed as isize;
+        // The first token after the change, in the new tokens
+        let unchanged = change.start + change.inserted;
+
+        // Declarations the parser didn't look at the changed tokens for
+        let kept = self
+            .declarations
+            .partition_point(|d| d.tokens.end + LOOKAHEAD <= change.start);
+        let start = kept
+            .checked_sub(1)
+            .map_or(0, |i| self.declarations[i].tokens.end);
+        let tokens = self.scan.tokens[start..].iter();
+        let mut parser = Parser::resume(tokens.map(|token| token.token(&self.source)), start);
+
+        // Parsing at the top level doesn't depend on what came before, so
+        // once a declaration starts where one did before the change, it and
+        // everything after it can be reused
+        let mut statements = Vec::new();
+        let mut declarations = Vec::new();
+        let reused = loop {
+            if parser.is_at_end() {
+                break self.declarations.len();
+            }
+            let old_position = parser.position().wrapping_add_signed(-tokens_delta);
+            if parser.position() >= unchanged
+                && let Ok(i) = self.declarations[kept..]
+                    .binary_search_by_key(&old_position, |d| d.tokens.start)
+            {
+                break kept + i;
+            }
+
+            let (statement, declaration) = Self::parse_declaration(&mut parser);
+            statements.extend(statement);
+            declarations.push(declaration);
+        };
+
+        let first = self.declarations[..kept]
+            .iter()
+            .filter(|d| d.parsed)
+            .count();
+        let last = first
+            + self.declarations[kept..reused]
+                .iter()
+                .filter(|d| d.parsed)
+                .count();
+        let (parsed, reparsed) = (statements.len(), declarations.len());
+        self.statements.splice(first..last, statements);
+        self.declarations.splice(kept..reused, declarations);
+
+        for statement in &mut self.statements[first + parsed..] {
+            statement_spans(statement, &mut |span| *span = span.shifted(delta));
+        }
+        for declaration in &mut self.declarations[kept + reparsed..] {
+            let tokens = &declaration.tokens;
+            declaration.tokens = tokens.start.wrapping_add_signed(tokens_delta)
+                ..tokens.end.wrapping_add_signed(tokens_delta);
+            for span in &mut declaration.spans {
+                *span = span.shifted(delta);
+            }
+            for error in &mut declaration.errors {
+                error.span = error.span.shifted(delta);
+            }
+        }
+    }
+
+    fn parse_declaration(parser: &mut Parser) -> (Option<Stmt>, Declaration) {
+        let start = parser.position();
+        let (statement, spans, errors) = parser.next_declaration();
+
+        let declaration = Declaration {
+            tokens: start..parser.position(),
+            parsed: statement.is_some(),
+            spans,
+            errors,
+        };
+        (statement, declaration)
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The tokens without their lexemes, which [`ScannedToken::token`] reads
+    /// back from [`SyntaxTree::source`].
+    pub fn tokens(&self) -> &[ScannedToken] {
+        &self.scan.tokens
+    }
+
+    pub fn lex_errors(&self) -> &[LexError] {
+        &self.scan.errors
+    }
+
+    /// The spans of the comments, which aren't tokens.
+    pub fn comments(&self) -> &[Span] {
+        &self.scan.comments
+    }
+
+    pub fn statements(&self) -> &[Stmt] {
+        &self.statements
+    }
+
+    /// The span of every statement, in the order `Parser::statement_spans`
+    /// has them.
+    pub fn statement_spans(&self) -> Vec<Span> {
+        self.declarations
+            .iter()
+            .flat_map(|d| d.spans.iter().copied())
+            .collect()
+    }
+
+    pub fn parse_errors(&self) -> impl Iterator<Item = &ParseError> {
+        self.declarations.iter().flat_map(|d| &d.errors)
+    }
+}
+
+// Visits every span in a statement, for moving them after an edit
+fn statement_spans(statement: &mut Stmt, visit: &mut impl FnMut(&mut Span)) {
+    match statement {
+        Stmt::VariableDecl(decl) => {
+            visit(&mut decl.pattern_span);
+            pattern_spans(&mut decl.pattern, visit);
+            expression_spans(&mut decl.initializer, visit);
+        }
+        Stmt::Expression(expr) | Stmt::Return(Some(expr)) => expression_spans(expr, visit),
+        Stmt::FunctionDecl(function) => {
+            visit(&mut function.name.span);
+            for param in &mut function.params {
+                visit(&mut param.span);
+            }
+            block_spans(&mut function.statements, visit);
+        }
+        Stmt::For(for_loop) => {
+            visit(&mut for_loop.pattern_span);
+            pattern_spans(&mut for_loop.pattern, visit);
+            expression_spans(&mut for_loop.iterable, visit);
+            block_spans(&mut for_loop.body, visit);
+        }
+        Stmt::If(if_stmt) => {
+            expression_spans(&mut if_stmt.condition, visit);
+            block_spans(&mut if_stmt.then_branch, visit);
+            if let Some(else_branch) = &mut if_stmt.else_branch {
+                block_spans(else_branch, visit);
+            }
+        }
+        Stmt::While(while_loop) => {
+            expression_spans(&mut while_loop.condition, visit);
+            block_spans(&mut while_loop.body, visit);
+        }
+        Stmt::Loop(body) => block_spans(body, visit),
+        Stmt::Import(import) => visit(&mut import.alias.span),
+        Stmt::Use(use_decl) => {
+            visit(&mut use_decl.module.span);
+            for name in &mut use_decl.names {
+                visit(&mut name.span);
+            }
+        }
+        Stmt::Return(None) | Stmt::Break | Stmt::Continue => {}
+    }
+}
+
+fn block_spans(statements: &mut [Stmt], visit: &mut impl FnMut(&mut Span)) {
+    for statement in statements {
+        statement_spans(statement, visit);
+    }
+}
+
+fn expression_spans(expr: &mut Expr, visit: &mut impl FnMut(&mut Span)) {
+    match expr {
+        Expr::Literal(_) | Expr::Identifier(_) => {}
+        Expr::Variable(variable) => visit(&mut variable.name.span),
+        Expr::Binary(binary) => {
+            expression_spans(&mut binary.left, visit);
+            expression_spans(&mut binary.right, visit);
+        }
+        Expr::Unary(unary) => expression_spans(&mut unary.right, visit),
+        Expr::Grouping(inner) => expression_spans(inner, visit),
+        Expr::Assignment { name, value } => {
+            visit(&mut name.span);
+            expression_spans(value, visit);
+        }
+        Expr::Call { callee, arguments } => {
+            expression_spans(callee, visit);
+            for argument in arguments {
+                expression_spans(argument, visit);
+            }
+        }
+        Expr::MethodAccess { object, method } => {
+            expression_spans(object, visit);
+            expression_spans(method, visit);
+        }
+        Expr::PropertyAccess { object, propert } => {
+            expression_spans(object, visit);
+            visit(&mut propert.span);
+        }
+        Expr::Array(elements) | Expr::Tuple(elements) => {
+            for element in elements {
+                expression_spans(element, visit);
+            }
+        }
+        Expr::Index { object, index } => {
+            expression_spans(object, visit);
+            expression_spans(index, visit);
+        }
+        Expr::Interpolated { parts, span } => {
+            visit(span);
+            for part in parts {
+                if let Part::Expr(expr) = part {
+                    expression_spans(expr, visit);
+                }
+            }
+        }
+    }
+}
+
+fn pattern_spans(pattern: &mut Pattern, visit: &mut impl FnMut(&mut Span)) {
+    match pattern {
+        Pattern::Identifier(name) | Pattern::Rest(Some(name)) => visit(&mut name.span),
+        Pattern::Wildcard | Pattern::Literal(_) | Pattern::Rest(None) => {}
+        Pattern::Array(elements) | Pattern::Tuple(elements) => {
+            for element in elements {
+                pattern_spans(element, visit);
+            }
+        }
+        Pattern::Struct { name, fields } => {
+            visit(&mut name.span);
+            for field in fields {
+                visit(&mut field.name.span);
+                pattern_spans(&mut field.pattern, visit);
+            }
+        }
+    }
+}
@@ -2,24 +2,49 @@ pub mod ast;
 pub mod core;
 mod declarations;
 mod expressions;
+mod incremental;
+mod patterns;
 mod statements;
 
-use ast::{BinaryExpr, BinaryOp, UnaryExpr, UnaryOp};
-use ast::{Expr, Literal, Stmt};
-use ast::{Function, Variable, VariableDecl};
+use ast::{BinaryExpr, BinaryOp, Part, UnaryExpr, UnaryOp};
+use ast::{Expr, Ident, Literal, Stmt};
+use ast::{FieldPattern, ForLoop, Function, IfStmt, Import, Pattern, Use, Variable, VariableDecl};
+use ast::{Visibility, WhileLoop};
+use apl_error::{ParseError, parseerror::ParseErrorKind};
+pub use incremental::SyntaxTree;
 use core::Parser;
 
 #[cfg(test)]
 mod tests {
     use apl_scanner::Scanner;
+    use apl_source::Span;
+    use num_bigint::BigInt;
 
     use super::*;
 
-    fn parse_expr(input: &str) -> Result<Expr, String> {
+    fn parse_expr(input: &str) -> Result<Expr, ParseError> {
         let mut scanner = Scanner::new(input);
         let tokens = scanner.scan_tokens();
         let mut parser = Parser::new(tokens);
-        parser.expression()
+        let expr = parser.expression()?;
+
+        if !parser.is_at_end() {
+            return Err(parser.error(
+                ParseErrorKind::UnexpectedToken,
+                format!("Unexpected token {:?}", parser.peek().token_type),
+            ));
+        }
+        Ok(expr)
+    }
+
+    fn span(start: usize, end: usize) -> Span {
+        Span::new(Default::default(), start, end)
+    }
+
+    fn parse_stmt(input: &str) -> Result<Stmt, ParseError> {
+        let tokens = Scanner::new(input).scan_tokens();
+        let mut parser = Parser::new(tokens);
+        parser.statement()
     }
 
     #[test]
@@ -33,9 +58,10 @@ mod tests {
         assert_eq!(
             result,
             vec![Stmt::FunctionDecl(Function::new(
-                "test".to_string(),
-                vec!["a".to_string(), "b".to_string()],
-                Vec::new()
+                "test".into(),
+                vec!["a".into(), "b".into()],
+                Vec::new(),
+                Visibility::Private
             ))]
         );
     }
@@ -51,12 +77,20 @@ mod tests {
         assert_eq!(
             result,
             vec![Stmt::VariableDecl(VariableDecl::new(
-                "a".to_string(),
+                Pattern::Identifier("a".into()),
+                span(4, 5),
                 Expr::Binary(BinaryExpr::new(
-                    Box::new(Expr::Literal(Literal::Integer(1))),
+                    Box::new(Expr::Literal(Literal::Integer {
+                        value: 1,
+                        lexeme: "1".into()
+                    })),
                     BinaryOp::Equal,
-                    Box::new(Expr::Literal(Literal::Integer(2)))
-                ))
+                    Box::new(Expr::Literal(Literal::Integer {
+                        value: 2,
+                        lexeme: "2".into()
+                    }))
+                )),
+                Visibility::Private
             ))]
         );
     }
@@ -67,6 +101,70 @@ mod tests {
         assert!(parse_expr("(1 + 2) * 3").is_ok());
     }
 
+    #[test]
+    fn number_literals() {
+        let int = |value, lexeme: &str| {
+            Ok(Expr::Literal(Literal::Integer {
+                value,
+                lexeme: lexeme.into(),
+            }))
+        };
+        assert_eq!(parse_expr("0xFF"), int(255, "0xFF"));
+        assert_eq!(parse_expr("1_000"), int(1000, "1_000"));
+        assert_eq!(
+            parse_expr("2.5e1"),
+            Ok(Expr::Literal(Literal::Float {
+                value: 25.0,
+                lexeme: "2.5e1".into()
+            }))
+        );
+        assert_eq!(parse_expr("0x1_0000_0000"), int(1 << 32, "0x1_0000_0000"));
+        assert_eq!(
+            parse_expr("9223372036854775808"),
+            Ok(Expr::Literal(Literal::BigInteger {
+                value: BigInt::from(i64::MAX) + 1,
+                lexeme: "9223372036854775808".into()
+            }))
+        );
+        assert_eq!(
+            parse_expr("1e100").unwrap_err().kind,
+            ParseErrorKind::InvalidNumberLiteral
+        );
+    }
+
+    #[test]
+    fn interpolated_strings() {
+        let Ok(Expr::Interpolated { parts, .. }) =
+            parse_expr(r#""Hello ${name}, you have ${n + 1} items""#)
+        else {
+            panic!("expected an interpolated string");
+        };
+        assert_eq!(parts.len(), 5);
+        assert_eq!(parts[0], Part::Text("Hello ".to_string()));
+        assert!(matches!(&parts[1], Part::Expr(Expr::Variable(_))));
+        assert_eq!(parts[2], Part::Text(", you have ".to_string()));
+        assert!(matches!(&parts[3], Part::Expr(Expr::Binary(_))));
+        assert_eq!(parts[4], Part::Text(" items".to_string()));
+
+        // Empty text around expressions is left out
+        let Ok(Expr::Interpolated { parts, .. }) = parse_expr(r#""${a}${"${b}"}""#) else {
+            panic!("expected an interpolated string");
+        };
+        assert_eq!(parts.len(), 2);
+        assert!(
+            matches!(&parts[1], Part::Expr(Expr::Interpolated { parts: inner, .. }) if inner.len() == 1)
+        );
+
+        assert_eq!(
+            parse_expr(r#""a ${}""#).unwrap_err().kind,
+            ParseErrorKind::ExpectedExpression
+        );
+        assert_eq!(
+            parse_expr(r#""a ${b c}""#).unwrap_err().kind,
+            ParseErrorKind::UnexpectedToken
+        );
+    }
+
     #[test]
     fn test_function_calls() {
         assert!(parse_expr("foo()").is_ok());
@@ -105,4 +203,327 @@ mod tests {
         assert!(parse_expr("(a + b) * c.method(d)").is_ok());
         assert!(parse_expr("Math.max(score1, score2) + bonus").is_ok());
     }
+
+    #[test]
+    fn parse_destructuring_let() {
+        assert_eq!(
+            parse_stmt("let [a, _, ..rest] = pair;"),
+            Ok(Stmt::VariableDecl(VariableDecl::new(
+                Pattern::Array(vec![
+                    Pattern::Identifier("a".into()),
+                    Pattern::Wildcard,
+                    Pattern::Rest(Some("rest".into())),
+                ]),
+                span(4, 18),
+                Expr::Variable(Variable::new("pair".into())),
+                Visibility::Private
+            )))
+        );
+
+        assert_eq!(
+            parse_stmt("let Point { x, y: py } = p;"),
+            Ok(Stmt::VariableDecl(VariableDecl::new(
+                Pattern::Struct {
+                    name: "Point".into(),
+                    fields: vec![
+                        FieldPattern::new("x".into(), Pattern::Identifier("x".into())),
+                        FieldPattern::new("y".into(), Pattern::Identifier("py".into())),
+                    ],
+                },
+                span(4, 22),
+                Expr::Variable(Variable::new("p".into())),
+                Visibility::Private
+            )))
+        );
+    }
+
+    #[test]
+    fn parse_destructuring_for() {
+        assert_eq!(
+            parse_stmt("for (k, v) in pairs { print(k); }"),
+            Ok(Stmt::For(ForLoop::new(
+                Pattern::Tuple(vec![
+                    Pattern::Identifier("k".into()),
+                    Pattern::Identifier("v".into()),
+                ]),
+                span(4, 10),
+                Expr::Variable(Variable::new("pairs".into())),
+                vec![Stmt::Expression(Expr::Call {
+                    callee: Box::new(Expr::Variable(Variable::new("print".into()))),
+                    arguments: vec![Expr::Variable(Variable::new("k".into()))],
+                })]
+            )))
+        );
+
+        // A parenthesised single pattern is not a tuple
+        assert!(matches!(parse_stmt("for (x) in xs {}"), Ok(Stmt::For(_))));
+    }
+
+    #[test]
+    fn pattern_errors() {
+        // Literal patterns can fail to match
+        assert!(parse_stmt("let [a, 1] = pair;").is_err());
+        assert!(parse_stmt("for 0 in xs {}").is_err());
+
+        // The same name can't be bound twice
+        assert!(parse_stmt("let [a, a] = pair;").is_err());
+        assert!(parse_stmt("let Point { x, y: x } = p;").is_err());
+
+        // Rest patterns
+        assert!(parse_stmt("let [..a, ..b] = xs;").is_err());
+        assert!(parse_stmt("let ..a = xs;").is_err());
+    }
+
+    #[test]
+    fn parse_module_declarations() {
+        let tokens = Scanner::new(
+            r#"
+import "lib/math.apl" as m;
+use m.{add, pi};
+use m.sub;
+pub fn double(x) {}
+pub let answer = 42;
+"#,
+        )
+        .scan_tokens();
+
+        let result = Parser::new(tokens).try_parse().unwrap();
+
+        assert_eq!(
+            result,
+            vec![
+                Stmt::Import(Import::new("lib/math.apl".into(), "m".into())),
+                Stmt::Use(Use::new("m".into(), vec!["add".into(), "pi".into()])),
+                Stmt::Use(Use::new("m".into(), vec!["sub".into()])),
+                Stmt::FunctionDecl(Function::new(
+                    "double".into(),
+                    vec!["x".into()],
+                    Vec::new(),
+                    Visibility::Public
+                )),
+                Stmt::VariableDecl(VariableDecl::new(
+                    Pattern::Identifier("answer".into()),
+                    span(85, 91),
+                    Expr::Literal(Literal::Integer {
+                        value: 42,
+                        lexeme: "42".into()
+                    }),
+                    Visibility::Public
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn module_declaration_errors() {
+        let try_parse = |input: &str| Parser::new(Scanner::new(input).scan_tokens()).try_parse();
+
+        assert!(try_parse("import m;").is_err());
+        assert!(try_parse(r#"import "m.apl";"#).is_err());
+        assert!(try_parse("pub x;").is_err());
+        // Module items can't be nested in function bodies
+        assert!(try_parse("fn f() { pub fn g() {} }").is_err());
+        assert!(try_parse(r#"fn f() { import "m.apl" as m; }"#).is_err());
+    }
+
+    #[test]
+    fn parse_error_spans() {
+        let error = parse_stmt("let a = 1 +;").unwrap_err();
+        assert_eq!(error.message, "Expected expression");
+        assert_eq!(error.kind, ParseErrorKind::ExpectedExpression);
+        assert_eq!((error.span.start, error.span.end), (11, 12));
+
+        let error = parse_stmt("let [a, a] = pair;").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::DuplicateBinding);
+        assert_eq!((error.span.start, error.span.end), (4, 10));
+    }
+
+    #[test]
+    fn parse_control_flow() {
+        let try_parse = |input: &str| Parser::new(Scanner::new(input).scan_tokens()).try_parse();
+
+        assert_eq!(
+            parse_stmt("if a { b; } else if c {} else {}"),
+            Ok(Stmt::If(IfStmt::new(
+                Expr::Variable(Variable::new("a".into())),
+                vec![Stmt::Expression(Expr::Variable(Variable::new("b".into())))],
+                Some(vec![Stmt::If(IfStmt::new(
+                    Expr::Variable(Variable::new("c".into())),
+                    Vec::new(),
+                    Some(Vec::new())
+                ))])
+            )))
+        );
+
+        assert_eq!(
+            parse_stmt("while a { loop { break; } continue; }"),
+            Ok(Stmt::While(WhileLoop::new(
+                Expr::Variable(Variable::new("a".into())),
+                vec![Stmt::Loop(vec![Stmt::Break]), Stmt::Continue]
+            )))
+        );
+
+        assert_eq!(
+            try_parse("fn f(x) { return x; return; }"),
+            Ok(vec![Stmt::FunctionDecl(Function::new(
+                "f".into(),
+                vec!["x".into()],
+                vec![
+                    Stmt::Return(Some(Expr::Variable(Variable::new("x".into())))),
+                    Stmt::Return(None)
+                ],
+                Visibility::Private
+            ))])
+        );
+    }
+
+    #[test]
+    fn control_flow_errors() {
+        let kind = |input: &str| {
+            Parser::new(Scanner::new(input).scan_tokens())
+                .try_parse()
+                .unwrap_err()
+                .kind
+        };
+
+        assert_eq!(kind("break;"), ParseErrorKind::BreakOutsideLoop);
+        assert_eq!(kind("if a { continue; }"), ParseErrorKind::BreakOutsideLoop);
+        // A function body can't break out of a loop around it
+        assert_eq!(
+            kind("loop { fn f() { break; } }"),
+            ParseErrorKind::BreakOutsideLoop
+        );
+        assert_eq!(kind("return 1;"), ParseErrorKind::ReturnOutsideFunction);
+        assert_eq!(kind("fn f(a, a) {}"), ParseErrorKind::DuplicateBinding);
+    }
+
+    #[test]
+    fn statement_spans() {
+        let mut parser = Parser::new(Scanner::new("let a = 1;\nfn f() { a; }").scan_tokens());
+        parser.try_parse().unwrap();
+
+        let spans: Vec<(usize, usize)> = parser
+            .statement_spans()
+            .iter()
+            .map(|span| (span.start, span.end))
+            .collect();
+        assert_eq!(spans, vec![(0, 10), (11, 24), (20, 22)]);
+    }
+
+    #[test]
+    fn parse_recovering() {
+        let source = "let a = ;\nfn f(x) {\n    let b = x +;\n    print(b);\n}\n} let c = 2;";
+        let mut parser = Parser::new(Scanner::new(source).scan_tokens());
+        let (statements, errors) = parser.parse_recovering();
+
+        // The broken statements are skipped, everything else is kept
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(&statements[..], [Stmt::FunctionDecl(f), Stmt::VariableDecl(_)] if f.statements.len() == 1));
+        assert_eq!(parser.statement_spans().len(), 3);
+
+        // Blocks left open at the end keep what was parsed
+        let source = "fn f(x) {\n    let y = x;\n    if y { pri";
+        let mut parser = Parser::new(Scanner::new(source).scan_tokens());
+        let (statements, errors) = parser.parse_recovering();
+
+        assert_eq!(errors.len(), 3);
+        let Stmt::FunctionDecl(f) = &statements[0] else {
+            panic!("expected a function, got {:?}", statements);
+        };
+        assert!(matches!(&f.statements[..], [Stmt::VariableDecl(_), Stmt::If(_)]));
+        assert_eq!(parser.statement_spans().len(), 3);
+    }
+
+    #[test]
+    fn incremental_reparse() {
+        let summary = |tree: &SyntaxTree| {
+            format!(
+                "{:?} {:?} {:?} {:?} {:?} {:?}",
+                tree.tokens(),
+                tree.lex_errors(),
+                tree.comments(),
+                tree.statements(),
+                tree.statement_spans(),
+                tree.parse_errors().collect::<Vec<_>>()
+            )
+        };
+
+        let mut tree = SyntaxTree::new(
+            "let a = 1;\nfn f(x) {\n    return x + a;\n}\nif a { print(f(2)); }\nlet b = [a];\n"
+                .to_string(),
+            Default::default(),
+        );
+        // Each edit is applied to the result of the previous one
+        let edits = [
+            ("1", "10"),
+            ("x + a", "x * (a - 1)"),
+            ("}\nif", "}\n// comment\nif"),
+            ("print(f(2)); }", "print(f(2)); } else { print(0); }"),
+            ("let b", "let b = \"unterminated;\nlet c"),
+            ("\"unterminated;", ""),
+            ("= ", "= @"),
+            (";\nfn", "\nfn"),
+            ("fn f", "/* fn f"),
+            ("/* ", ""),
+            ("{\n    return", "{\n    let y = x;\n    return"),
+        ];
+        for (old, new) in edits {
+            let start = tree.source().find(old).unwrap();
+            tree.edit(start..start + old.len(), new);
+
+            let fresh = SyntaxTree::new(tree.source().to_string(), Default::default());
+            assert_eq!(summary(&tree), summary(&fresh), "after replacing {:?}", old);
+        }
+
+        let len = tree.source().len();
+        tree.edit(0..len, "");
+        assert!(tree.statements().is_empty() && tree.tokens().len() == 1);
+        tree.edit(0..0, "let x = 1;");
+        assert_eq!(tree.statements().len(), 1);
+    }
+
+    #[test]
+    fn parse_without_tokens() {
+        let summary = |tree: &SyntaxTree| {
+            format!(
+                "{:?} {:?} {:?} {:?} {:?}",
+                tree.lex_errors(),
+                tree.comments(),
+                tree.statements(),
+                tree.statement_spans(),
+                tree.parse_errors().collect::<Vec<_>>()
+            )
+        };
+
+        let source = "let a = 1;\nfn f(x) {\n    return \"${x}\";\n}\n\nif a {\n    f(1);\n}\n// else\nelse { f(2); }\nlet b = a\n    + 1;\nlet c = @;\n/* end */ f(b)";
+        let file = Default::default();
+        let fresh = SyntaxTree::new(source.to_string(), file);
+        let parsed = SyntaxTree::parse(source.to_string(), file);
+        let read = SyntaxTree::read(source.as_bytes(), file).unwrap();
+        assert_eq!(summary(&parsed), summary(&fresh));
+        assert_eq!(summary(&read), summary(&fresh));
+        assert!(parsed.tokens().is_empty() && read.source() == source);
+
+        // Editing scans it in full first
+        let mut parsed = parsed;
+        parsed.edit(0..0, "let z = 0;\n");
+        let fresh = SyntaxTree::new(parsed.source().to_string(), file);
+        assert_eq!(summary(&parsed), summary(&fresh));
+        assert_eq!(parsed.tokens(), fresh.tokens());
+    }
+
+    #[test]
+    fn streaming_parse() {
+        let source = "let Point { x, y } = p;\nif x { f(x); } else { y; }\nlet q = p.x;";
+        let mut scanner = Scanner::new(source);
+        let mut parser = Parser::new(&mut scanner);
+        let statements = parser.try_parse().unwrap();
+        drop(parser);
+
+        let expected = Parser::new(Scanner::new(source).scan_tokens())
+            .try_parse()
+            .unwrap();
+        assert_eq!(format!("{:?}", statements), format!("{:?}", expected));
+        assert!(scanner.errors.is_empty() && scanner.next().is_none());
+    }
 }
@@ -0,0 +1,158 @@
+use super::{FieldPattern, Ident, ParseError, ParseErrorKind, Parser, Pattern};
+use apl_scanner::TokenType;
+use apl_source::Span;
+
+impl Parser<'_> {
+    /// Parses a pattern for a `let` or `for` binding and its span, rejecting
+    /// patterns that could fail to match or that bind the same name twice.
+    pub(crate) fn binding_pattern(&mut self, context: &str) -> Result<(Pattern, Span), ParseError> {
+        let start = self.peek().span;
+        let pattern = self.pattern()?;
+        let span = start.to(self.previous().span);
+
+        if pattern.is_refutable() {
+            return Err(ParseError::new(
+                ParseErrorKind::RefutablePattern,
+                format!("Refutable pattern in {} binding", context),
+                span,
+            ));
+        }
+
+        let bindings = pattern.bindings();
+        for (i, name) in bindings.iter().enumerate() {
+            if bindings[..i].contains(name) {
+                return Err(ParseError::new(
+                    ParseErrorKind::DuplicateBinding,
+                    format!(
+                        "Identifier '{}' is bound more than once in the same pattern",
+                        name
+                    ),
+                    span,
+                ));
+            }
+        }
+
+        Ok((pattern, span))
+    }
+
+    pub(crate) fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        match self.peek().token_type.clone() {
+            TokenType::Identifier(_)
+                if self.check_sequence(&[
+                    TokenType::Identifier("".into()),
+                    TokenType::CurlyOpen,
+                ]) =>
+            {
+                self.struct_pattern()
+            }
+            TokenType::Identifier(name) => {
+                let span = self.advance().span;
+                if name == "_" {
+                    Ok(Pattern::Wildcard)
+                } else {
+                    Ok(Pattern::Identifier(Ident::new(name, span)))
+                }
+            }
+            TokenType::Number(n) => {
+                let literal = self.number_literal(n)?;
+                self.advance();
+                Ok(Pattern::Literal(literal))
+            }
+            TokenType::BracketOpen => {
+                self.advance();
+                let (elements, _) = self.pattern_list(TokenType::BracketClose)?;
+                Ok(Pattern::Array(elements))
+            }
+            TokenType::ParenthesesOpen => {
+                self.advance();
+                let (mut elements, trailing_comma) =
+                    self.pattern_list(TokenType::ParenthesesClose)?;
+                // `(a)` is a parenthesised pattern, `(a,)` a one element tuple
+                if elements.len() == 1
+                    && !trailing_comma
+                    && !matches!(elements[0], Pattern::Rest(_))
+                {
+                    Ok(elements.remove(0))
+                } else {
+                    Ok(Pattern::Tuple(elements))
+                }
+            }
+            TokenType::Range => Err(self.error(
+                ParseErrorKind::MisplacedRestPattern,
+                "Rest pattern is only allowed inside array or tuple patterns",
+            )),
+            other => Err(self.error(
+                ParseErrorKind::ExpectedPattern,
+                format!("Expected pattern, found {:?}", other),
+            )),
+        }
+    }
+
+    /// Parses comma separated patterns up to and including `close`, reporting
+    /// whether the list ended with a trailing comma.
+    fn pattern_list(&mut self, close: TokenType) -> Result<(Vec<Pattern>, bool), ParseError> {
+        let mut elements = Vec::new();
+        let mut has_rest = false;
+        let mut trailing_comma = false;
+
+        while !self.check(&close) {
+            let element = if self.check(&TokenType::Range) {
+                self.advance();
+                if has_rest {
+                    return Err(self.error_at_previous(
+                        ParseErrorKind::MultipleRestPatterns,
+                        "Only one rest pattern is allowed",
+                    ));
+                }
+                has_rest = true;
+
+                if self.check(&TokenType::Identifier("".into())) {
+                    Pattern::Rest(Some(self.parse_identifier("Expected rest binding name")?))
+                } else {
+                    Pattern::Rest(None)
+                }
+            } else {
+                self.pattern()?
+            };
+            elements.push(element);
+
+            trailing_comma = self.check(&TokenType::Comma);
+            if !trailing_comma {
+                break;
+            }
+            self.advance(); // Consume comma
+        }
+
+        self.consume(close, "Expected end of pattern")?;
+
+        Ok((elements, trailing_comma))
+    }
+
+    fn struct_pattern(&mut self) -> Result<Pattern, ParseError> {
+        let name = self.parse_identifier("Expected struct name")?;
+        self.consume(TokenType::CurlyOpen, "Expected '{' after struct name")?;
+
+        let mut fields = Vec::new();
+        while !self.check(&TokenType::CurlyClose) {
+            let field = self.parse_identifier("Expected field name")?;
+
+            // `Point { x }` is shorthand for `Point { x: x }`
+            let pattern = if self.check(&TokenType::Colon) {
+                self.advance();
+                self.pattern()?
+            } else {
+                Pattern::Identifier(field.clone())
+            };
+            fields.push(FieldPattern::new(field, pattern));
+
+            if !self.check(&TokenType::Comma) {
+                break;
+            }
+            self.advance(); // Consume comma
+        }
+
+        self.consume(TokenType::CurlyClose, "Expected '}' after struct pattern")?;
+
+        Ok(Pattern::Struct { name, fields })
+    }
+}
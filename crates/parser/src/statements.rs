@@ -1,21 +1,56 @@
-use crate::ast::Function;
+use super::{
+    ForLoop, Function, IfStmt, ParseError, ParseErrorKind, Parser, Stmt, Visibility, WhileLoop,
+};
+use apl_scanner::TokenType;
 
-use super::{Parser, Stmt};
-use apl_scanner::{Token, TokenType};
-
-impl Parser {
-    pub(crate) fn statement(&mut self) -> Result<Stmt, String> {
+impl Parser<'_> {
+    pub(crate) fn statement(&mut self) -> Result<Stmt, ParseError> {
         match &self.peek().token_type {
-            TokenType::Let => self.variable_declaration(),
-            TokenType::Function => self.parse_function_declaration(),
+            TokenType::Let => self.variable_declaration(Visibility::Private),
+            TokenType::Function => self.parse_function_declaration(Visibility::Private),
             TokenType::For => self.parse_for_loop(),
             TokenType::While => self.parse_while_loop(),
             TokenType::Loop => self.parse_loop(),
-            _ => Err("Unexpected statement".to_string()),
+            TokenType::If => self.parse_if_statement(),
+            TokenType::Break | TokenType::Continue => self.parse_loop_control(),
+            TokenType::Return => self.parse_return(),
+            TokenType::Pub | TokenType::Import | TokenType::Use => Err(self.error(
+                ParseErrorKind::MisplacedModuleItem,
+                format!(
+                    "{:?} is only allowed at the top level of a module",
+                    self.peek().token_type
+                ),
+            )),
+            _ => self.expression_statement(),
+        }
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::SemiColon, "Expected ';' after expression")?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut body: Vec<Stmt> = Vec::new();
+
+        self.consume(TokenType::CurlyOpen, "Expected '{'")?;
+        while self.peek().token_type != TokenType::CurlyClose {
+            if self.recover_unclosed_block() {
+                return Ok(body);
+            }
+            body.extend(self.recover(Self::statement)?);
         }
+
+        self.consume(TokenType::CurlyClose, "Expected '}'")?;
+
+        Ok(body)
     }
 
-    fn parse_function_declaration(&mut self) -> Result<Stmt, String> {
+    pub(crate) fn parse_function_declaration(
+        &mut self,
+        visibility: Visibility,
+    ) -> Result<Stmt, ParseError> {
         self.consume(TokenType::Function, "Expected function keyword")?;
 
         let ident = self.parse_identifier("Expected function name")?;
@@ -25,20 +60,20 @@ impl Parser {
         self.consume(TokenType::ParenthesesOpen, "Expected '('")?;
 
         while self.peek().token_type != TokenType::ParenthesesClose {
-            let param = match self.consume(
-                TokenType::Identifier("".to_string()),
-                "Expected parameter name",
-            ) {
-                Ok(Token {
-                    token_type: TokenType::Identifier(name),
-                    ..
-                }) => name,
-                _ => return Err("Expected identifier".to_string()),
-            };
-            params.push(param.clone());
+            let param = self.parse_identifier("Expected parameter name")?;
+            if params.contains(&param) {
+                return Err(self.error_at_previous(
+                    ParseErrorKind::DuplicateBinding,
+                    format!("Parameter '{}' is declared more than once", param),
+                ));
+            }
+            params.push(param);
 
             if !self.check(&TokenType::Comma) && !self.check(&TokenType::ParenthesesClose) {
-                return Err("Expected ',' or ')' after parameter".to_string());
+                return Err(self.error(
+                    ParseErrorKind::UnexpectedToken,
+                    "Expected ',' or ')' after parameter",
+                ));
             }
             if self.check(&TokenType::Comma) {
                 self.advance(); // Consume comma
@@ -46,28 +81,113 @@ impl Parser {
         }
         self.consume(TokenType::ParenthesesClose, "Expected ')'")?;
 
-        let mut body: Vec<Stmt> = Vec::new();
+        // Loops outside the function can't be broken out of from inside it
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+        let body = body?;
 
-        self.consume(TokenType::CurlyOpen, "Expected '{'")?;
-        while self.peek().token_type != TokenType::CurlyClose {
-            let stmt = self.statement()?;
-            body.push(stmt);
-        }
+        Ok(Stmt::FunctionDecl(Function::new(
+            ident, params, body, visibility,
+        )))
+    }
 
-        self.consume(TokenType::CurlyClose, "Expected '}'")?;
+    fn parse_for_loop(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::For, "Expected for keyword")?;
+
+        let (pattern, pattern_span) = self.binding_pattern("for")?;
+        self.consume(TokenType::In, "Expected 'in' after for loop pattern")?;
+        let iterable = self.expression()?;
 
-        Ok(Stmt::FunctionDecl(Function::new(ident, params, body)))
+        let body = self.loop_body()?;
+
+        Ok(Stmt::For(ForLoop::new(
+            pattern,
+            pattern_span,
+            iterable,
+            body,
+        )))
+    }
+
+    fn parse_while_loop(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::While, "Expected while keyword")?;
+
+        let condition = self.expression()?;
+        let body = self.loop_body()?;
+
+        Ok(Stmt::While(WhileLoop::new(condition, body)))
     }
 
-    fn parse_for_loop(&mut self) -> Result<Stmt, String> {
-        todo!()
+    fn parse_loop(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::Loop, "Expected loop keyword")?;
+
+        Ok(Stmt::Loop(self.loop_body()?))
+    }
+
+    fn loop_body(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        self.loop_depth += 1;
+        let body = self.block();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn parse_if_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::If, "Expected if keyword")?;
+
+        let condition = self.expression()?;
+        let then_branch = self.block()?;
+
+        let else_branch = if self.check(&TokenType::Else) {
+            self.advance();
+            if self.check(&TokenType::If) {
+                Some(vec![self.spanned(Self::parse_if_statement)?])
+            } else {
+                Some(self.block()?)
+            }
+        } else {
+            None
+        };
+
+        Ok(Stmt::If(IfStmt::new(condition, then_branch, else_branch)))
     }
 
-    fn parse_while_loop(&mut self) -> Result<Stmt, String> {
-        todo!()
+    fn parse_loop_control(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.advance().token_type.clone();
+
+        if self.loop_depth == 0 {
+            return Err(self.error_at_previous(
+                ParseErrorKind::BreakOutsideLoop,
+                format!("{:?} can only be used inside a loop", keyword),
+            ));
+        }
+        self.consume(TokenType::SemiColon, "Expected ';' after loop control")?;
+
+        if keyword == TokenType::Break {
+            Ok(Stmt::Break)
+        } else {
+            Ok(Stmt::Continue)
+        }
     }
 
-    fn parse_loop(&mut self) -> Result<Stmt, String> {
-        todo!()
+    fn parse_return(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::Return, "Expected return keyword")?;
+
+        if self.function_depth == 0 {
+            return Err(self.error_at_previous(
+                ParseErrorKind::ReturnOutsideFunction,
+                "Return can only be used inside a function",
+            ));
+        }
+
+        let value = if self.check(&TokenType::SemiColon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::SemiColon, "Expected ';' after return")?;
+
+        Ok(Stmt::Return(value))
     }
 }
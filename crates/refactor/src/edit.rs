@@ -0,0 +1,45 @@
+use apl_source::Span;
+
+/// A replacement of the text at `span`, an insertion when the span is empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub span: Span,
+    pub text: String,
+}
+
+impl Edit {
+    pub fn new(span: Span, text: impl Into<String>) -> Self {
+        Self {
+            span,
+            text: text.into(),
+        }
+    }
+}
+
+/// Applies the edits for one file to its source. Edits may come in any
+/// order but must not overlap.
+pub fn apply(source: &str, edits: &[Edit]) -> String {
+    let mut edits: Vec<&Edit> = edits.iter().collect();
+    edits.sort_by_key(|edit| (edit.span.start, edit.span.end));
+
+    let mut result = String::with_capacity(source.len());
+    let mut last = 0;
+    for edit in edits {
+        result.push_str(&source[last..edit.span.start]);
+        result.push_str(&edit.text);
+        last = edit.span.end;
+    }
+    result.push_str(&source[last..]);
+    result
+}
+
+// Where `offset` ends up once `edits` are applied. Insertions at `offset`
+// land before it, and an edit starting at `offset` is where its text starts.
+pub(crate) fn shift(edits: &[Edit], offset: usize) -> usize {
+    edits
+        .iter()
+        .filter(|edit| edit.span.end <= offset)
+        .fold(offset, |offset, edit| {
+            offset + edit.text.len() - (edit.span.end - edit.span.start)
+        })
+}
@@ -0,0 +1,263 @@
+use std::ops::Range;
+
+use apl_error::RefactorError;
+use apl_parser::ast::{Pattern, Stmt};
+use apl_scanner::{Token, TokenType};
+use apl_source::Span;
+
+use super::edit::{Edit, apply, shift};
+use super::syntax::{
+    check_name, first_changed, has_errors, own_expression, parse, resolve, substitute, with_spans,
+};
+
+/// Replaces the expression at `selection` with a new variable `name`,
+/// declared just before the statement the expression is in.
+///
+/// The expression is then evaluated before the rest of its statement, so
+/// extracting is refused where that changes what happens: from a `while`
+/// or `else if` condition, after a call or assignment in the same
+/// statement, or when the expression has calls or assignments of its own
+/// and variables are read before it.
+pub fn extract_variable(
+    source: &str,
+    selection: Range<usize>,
+    name: &str,
+) -> Result<Vec<Edit>, RefactorError> {
+    check_name(name)?;
+    let parsed = parse(source).ok_or_else(has_errors)?;
+    let not_expression = || RefactorError::new("Select a whole expression to extract it", None);
+
+    let selected = &source[selection.clone()];
+    let start = selection.start + (selected.len() - selected.trim_start().len());
+    let end = selection.end - (selected.len() - selected.trim_end().len());
+    let selected = &source[start..end];
+
+    let tokens = tokens_in(&parsed.tokens, start..end);
+    if tokens.first().map(|token| token.span.start) != Some(start)
+        || tokens.last().map(|token| token.span.end) != Some(end)
+    {
+        return Err(not_expression());
+    }
+    let expression = match parse(&format!("{};", selected)).map(|parsed| parsed.statements) {
+        Some(statements) => match statements.as_slice() {
+            [Stmt::Expression(expression)] => expression.clone(),
+            _ => return Err(not_expression()),
+        },
+        None => return Err(not_expression()),
+    };
+
+    // A name that isn't used anywhere can't capture or shadow anything
+    if parsed
+        .tokens
+        .iter()
+        .any(|token| matches!(&token.token_type, TokenType::Identifier(n) if n == name))
+    {
+        return Err(RefactorError::new(
+            format!("'{}' is already used in this file", name),
+            None,
+        ));
+    }
+
+    // The innermost statement around the selection comes last
+    let statements = with_spans(&parsed.statements, &parsed.spans);
+    let (index, &(stmt, stmt_span)) = statements
+        .iter()
+        .enumerate()
+        .rfind(|(_, (_, span))| span.start <= start && end <= span.end)
+        .ok_or_else(not_expression)?;
+    let selection_span = Span::new(stmt_span.file, start, end);
+
+    let statement_tokens = tokens_in(&parsed.tokens, stmt_span.start..stmt_span.end);
+    let evaluated_from = match stmt {
+        Stmt::While(_) => {
+            return Err(RefactorError::new(
+                "A loop condition is evaluated on every iteration, but the variable would only be set once",
+                Some(selection_span),
+            ));
+        }
+        Stmt::If(_) if is_else_if(&parsed.tokens, stmt_span) => {
+            return Err(RefactorError::new(
+                "An `else if` condition is only evaluated when the conditions before it are false",
+                Some(selection_span),
+            ));
+        }
+        Stmt::VariableDecl(_) => after(&statement_tokens, TokenType::Equals),
+        Stmt::For(_) => after(&statement_tokens, TokenType::In),
+        // After the keyword
+        Stmt::If(_) | Stmt::Return(_) => statement_tokens.first().map(|token| token.span.end),
+        Stmt::Expression(_) => Some(stmt_span.start),
+        _ => None,
+    }
+    .ok_or_else(not_expression)?;
+
+    let before = tokens_in(&parsed.tokens, evaluated_from..start);
+    if completes_effect(&before) {
+        return Err(RefactorError::new(
+            "The expression would be evaluated before the calls or assignments that come before it",
+            Some(selection_span),
+        ));
+    }
+    if has_effects(&tokens)
+        && before
+            .iter()
+            .enumerate()
+            .any(|(i, token)| is_variable(token, before.get(i + 1).copied()))
+    {
+        return Err(RefactorError::new(
+            "The expression's calls or assignments would happen before the variables that come before it are read",
+            Some(selection_span),
+        ));
+    }
+
+    let line_start = source[..stmt_span.start].rfind('\n').map_or(0, |i| i + 1);
+    let indent = &source[line_start..stmt_span.start];
+    let declaration = if indent.trim().is_empty() {
+        format!("let {} = {};\n{}", name, selected, indent)
+    } else {
+        format!("let {} = {}; ", name, selected)
+    };
+    let edits = vec![
+        Edit::new(
+            Span::new(stmt_span.file, stmt_span.start, stmt_span.start),
+            declaration,
+        ),
+        Edit::new(selection_span, name),
+    ];
+
+    // The selection is one expression of the statement if putting it back
+    // in place of the variable gives the statement we started with
+    let edited = apply(source, &edits);
+    let after = parse(&edited).ok_or_else(not_expression)?;
+    let new_statements = with_spans(&after.statements, &after.spans);
+    let declared = matches!(
+        new_statements.get(index),
+        Some((Stmt::VariableDecl(decl), _))
+            if decl.pattern == Pattern::Identifier(name.into()) && decl.initializer == expression
+    );
+    let restored = new_statements.get(index + 1).is_some_and(|(new_stmt, _)| {
+        let mut new_stmt = (*new_stmt).clone();
+        own_expression(&mut new_stmt).is_some_and(|expr| substitute(expr, name, &expression) == 1)
+            && new_stmt == *stmt
+    });
+    if !declared || !restored {
+        return Err(not_expression());
+    }
+
+    // The names in the expression resolve the same before the statement
+    let prefix = format!("let {} = ", name).len();
+    let relocate = |offset: usize| {
+        if (start..end).contains(&offset) {
+            vec![stmt_span.start + prefix + offset - start]
+        } else {
+            vec![shift(&edits, offset)]
+        }
+    };
+    let before = resolve(&parsed.statements, Vec::new());
+    let after = resolve(&after.statements, Vec::new());
+    // The expression's references move, and the variable adds two
+    if after.errors != before.errors || after.references.len() != before.references.len() + 2 {
+        return Err(RefactorError::new(
+            "Extracting would change what some names refer to",
+            Some(selection_span),
+        ));
+    }
+    if let Some(span) = first_changed(&before.references, &after.references, relocate) {
+        return Err(RefactorError::new(
+            "Extracting would change what this name refers to",
+            Some(span),
+        ));
+    }
+
+    Ok(edits)
+}
+
+/// `base`, or `base` with a number after it, whichever isn't used in
+/// `source` yet.
+pub fn unused_name(source: &str, base: &str) -> String {
+    let tokens = parse(source)
+        .map(|parsed| parsed.tokens)
+        .unwrap_or_default();
+    let used = |name: &str| {
+        tokens
+            .iter()
+            .any(|token| matches!(&token.token_type, TokenType::Identifier(n) if n == name))
+    };
+
+    let mut name = base.to_string();
+    let mut suffix = 1;
+    while used(&name) {
+        suffix += 1;
+        name = format!("{}{}", base, suffix);
+    }
+    name
+}
+
+fn tokens_in<'t, 'a>(tokens: &'t [Token<'a>], range: Range<usize>) -> Vec<&'t Token<'a>> {
+    tokens
+        .iter()
+        .filter(|token| token.token_type != TokenType::Eof)
+        .filter(|token| range.start <= token.span.start && token.span.end <= range.end)
+        .collect()
+}
+
+// Where evaluation starts in a statement, just after its first `token_type`
+fn after(tokens: &[&Token], token_type: TokenType) -> Option<usize> {
+    tokens
+        .iter()
+        .find(|token| token.token_type == token_type)
+        .map(|token| token.span.end)
+}
+
+fn is_else_if(tokens: &[Token], span: Span) -> bool {
+    let index = tokens.partition_point(|token| token.span.start < span.start);
+    index > 0 && tokens[index - 1].token_type == TokenType::Else
+}
+
+// A `(` right after a name or a closing bracket is a call
+fn is_call(tokens: &[&Token], i: usize) -> bool {
+    tokens[i].token_type == TokenType::ParenthesesOpen
+        && i > 0
+        && matches!(
+            tokens[i - 1].token_type,
+            TokenType::Identifier(_) | TokenType::ParenthesesClose | TokenType::BracketClose
+        )
+}
+
+fn has_effects(tokens: &[&Token]) -> bool {
+    (0..tokens.len()).any(|i| is_call(tokens, i) || tokens[i].token_type == TokenType::Equals)
+}
+
+// Names being called are functions, which don't change while a statement
+// runs
+fn is_variable(token: &Token, next: Option<&Token>) -> bool {
+    matches!(token.token_type, TokenType::Identifier(_))
+        && next.is_none_or(|next| next.token_type != TokenType::ParenthesesOpen)
+}
+
+// Whether evaluating `tokens` from left to right finishes a call or an
+// assignment. Calls and assignments still open at the end hold the
+// selection and happen after it.
+fn completes_effect(tokens: &[&Token]) -> bool {
+    // Open brackets, and whether each is a call
+    let mut brackets: Vec<bool> = Vec::new();
+    // The bracket depth of each unfinished assignment
+    let mut assignments: Vec<usize> = Vec::new();
+
+    for i in 0..tokens.len() {
+        match tokens[i].token_type {
+            TokenType::ParenthesesOpen | TokenType::BracketOpen | TokenType::CurlyOpen => {
+                brackets.push(is_call(tokens, i))
+            }
+            TokenType::ParenthesesClose | TokenType::BracketClose | TokenType::CurlyClose => {
+                let call = brackets.pop() == Some(true);
+                if call || assignments.iter().any(|&depth| depth > brackets.len()) {
+                    return true;
+                }
+            }
+            TokenType::Comma if assignments.contains(&brackets.len()) => return true,
+            TokenType::Equals => assignments.push(brackets.len()),
+            _ => {}
+        }
+    }
+    false
+}
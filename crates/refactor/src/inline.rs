@@ -0,0 +1,194 @@
+use apl_error::RefactorError;
+use apl_parser::ast::{Expr, Pattern, Stmt, Visibility};
+use apl_scanner::TokenType;
+use apl_source::Span;
+
+use super::edit::{Edit, apply, shift};
+use super::syntax::{
+    first_changed, has_errors, parse, resolve, walk_expression, walk_statements, with_spans,
+};
+
+/// Replaces every use of the variable at `offset` with its value and
+/// removes its declaration.
+///
+/// Only variables declared as `let name = value;` and never assigned can be
+/// inlined, and only when the value is the same wherever it's used: it may
+/// not call functions, assign, or read variables that are assigned. A value
+/// creating an array is only inlined into a single use, since every copy
+/// would create its own array.
+pub fn inline_variable(source: &str, offset: usize) -> Result<Vec<Edit>, RefactorError> {
+    let parsed = parse(source).ok_or_else(has_errors)?;
+    let before = resolve(&parsed.statements, Vec::new());
+
+    let declaration = before
+        .references
+        .iter()
+        .find(|r| r.span.start <= offset && offset <= r.span.end)
+        .map(|r| r.declaration)
+        .ok_or_else(|| RefactorError::new("There is no variable to inline here", None))?;
+    let name = &source[declaration.start..declaration.end];
+
+    let (decl, stmt_span) = with_spans(&parsed.statements, &parsed.spans)
+        .into_iter()
+        .find_map(|(stmt, span)| match stmt {
+            Stmt::VariableDecl(decl)
+                if matches!(&decl.pattern, Pattern::Identifier(ident) if ident.span == declaration) =>
+            {
+                Some((decl, span))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            RefactorError::new(
+                "Only variables declared with `let name = value;` can be inlined",
+                Some(declaration),
+            )
+        })?;
+    if decl.visibility == Visibility::Public {
+        return Err(RefactorError::new(
+            format!("'{}' is public and may be used by other modules", name),
+            Some(declaration),
+        ));
+    }
+
+    // The declarations of every name that is assigned to
+    let mut assigned = Vec::new();
+    walk_statements(&parsed.statements, &mut |expr| {
+        if let Expr::Assignment { name, .. } = expr
+            && let Some(reference) = before.references.iter().find(|r| r.span == name.span)
+        {
+            assigned.push(reference.declaration);
+        }
+    });
+    if assigned.contains(&declaration) {
+        return Err(RefactorError::new(
+            format!("'{}' is assigned after its declaration", name),
+            Some(declaration),
+        ));
+    }
+
+    let mut has_effects = false;
+    let mut creates_array = false;
+    walk_expression(&decl.initializer, &mut |expr| match expr {
+        Expr::Call { .. } | Expr::MethodAccess { .. } | Expr::Assignment { .. } => {
+            has_effects = true
+        }
+        Expr::Array(_) => creates_array = true,
+        _ => {}
+    });
+    if has_effects {
+        return Err(RefactorError::new(
+            "The value calls functions or assigns, inlining would change how often that happens",
+            Some(declaration),
+        ));
+    }
+
+    // The value runs from after the `=` to the closing `;`
+    let value_start = parsed
+        .tokens
+        .iter()
+        .find(|token| token.span.start >= stmt_span.start && token.token_type == TokenType::Equals)
+        .map_or(stmt_span.start, |token| token.span.end);
+    let value = source[value_start..stmt_span.end - 1].trim();
+    let value_start = value_start + source[value_start..].find(value).unwrap_or(0);
+    let value_end = value_start + value.len();
+
+    if let Some(read) = before.references.iter().find(|r| {
+        value_start <= r.span.start && r.span.end <= value_end && assigned.contains(&r.declaration)
+    }) {
+        return Err(RefactorError::new(
+            format!(
+                "'{}' is assigned, so the value may differ where '{}' is used",
+                &source[read.span.start..read.span.end],
+                name
+            ),
+            Some(read.span),
+        ));
+    }
+
+    let uses: Vec<Span> = before
+        .references
+        .iter()
+        .filter(|r| r.declaration == declaration && r.span != declaration)
+        .map(|r| r.span)
+        .collect();
+    if creates_array && uses.len() > 1 {
+        return Err(RefactorError::new(
+            "The value creates an array, and each use would get an array of its own",
+            Some(declaration),
+        ));
+    }
+
+    // Operators bind less tightly than whatever the use is part of
+    let (text, open) = match decl.initializer {
+        Expr::Binary(_) | Expr::Unary(_) => (format!("({})", value), 1),
+        _ => (value.to_string(), 0),
+    };
+    let removed = removal(source, stmt_span);
+    let mut edits: Vec<Edit> = uses.iter().map(|&span| Edit::new(span, &text)).collect();
+    edits.push(Edit::new(removed, ""));
+    edits.sort_by_key(|edit| edit.span.start);
+
+    let edited = apply(source, &edits);
+    let after = parse(&edited).ok_or_else(|| {
+        RefactorError::new(
+            format!("The value of '{}' can't be written where it is used", name),
+            Some(declaration),
+        )
+    })?;
+    let after = resolve(&after.statements, Vec::new());
+
+    // The value is copied to every use, and the rest stays put
+    let relocate = |offset: usize| {
+        if (value_start..value_end).contains(&offset) {
+            uses.iter()
+                .map(|span| shift(&edits, span.start) + open + offset - value_start)
+                .collect()
+        } else if (removed.start..removed.end).contains(&offset)
+            || uses.iter().any(|span| span.start == offset)
+        {
+            Vec::new()
+        } else {
+            vec![shift(&edits, offset)]
+        }
+    };
+    // The declaration and the uses go, and the value's references are
+    // copied to every use
+    let value_references = before
+        .references
+        .iter()
+        .filter(|r| value_start <= r.span.start && r.span.end <= value_end)
+        .count();
+    let expected =
+        before.references.len() - 1 - uses.len() + value_references * uses.len() - value_references;
+    if after.errors != before.errors || after.references.len() != expected {
+        return Err(RefactorError::new(
+            format!("Inlining '{}' would change what some names refer to", name),
+            Some(declaration),
+        ));
+    }
+    if let Some(span) = first_changed(&before.references, &after.references, relocate) {
+        return Err(RefactorError::new(
+            format!("Inlining '{}' would change what this name refers to", name),
+            Some(span),
+        ));
+    }
+
+    Ok(edits)
+}
+
+// The statement at `span`, with its line when nothing else is on it
+fn removal(source: &str, span: Span) -> Span {
+    let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    let rest = &source[span.end..];
+    let line_end = rest.find('\n').map_or(source.len(), |i| span.end + i + 1);
+
+    if source[line_start..span.start].trim().is_empty()
+        && source[span.end..line_end].trim().is_empty()
+    {
+        Span::new(span.file, line_start, line_end)
+    } else {
+        let spaces = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+        Span::new(span.file, span.start, span.end + spaces)
+    }
+}
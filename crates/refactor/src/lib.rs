@@ -0,0 +1,243 @@
+mod edit;
+mod extract;
+mod inline;
+mod rename;
+mod syntax;
+
+pub use edit::{Edit, apply};
+pub use extract::{extract_variable, unused_name};
+pub use inline::inline_variable;
+pub use rename::rename;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apl_loader::ModuleLoader;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // Offset of the `n`th occurrence of `needle`, counting from zero
+    fn offset_of(source: &str, needle: &str, n: usize) -> usize {
+        source.match_indices(needle).nth(n).unwrap().0
+    }
+
+    fn rename_in(source: &str, offset: usize, new_name: &str) -> Result<String, String> {
+        let mut loader = ModuleLoader::new();
+        let file = loader
+            .load_source("main.apl", source.to_string())
+            .unwrap()
+            .file;
+
+        rename(&loader, file, offset, new_name)
+            .map(|edits| apply(source, &edits))
+            .map_err(|error| error.message)
+    }
+
+    fn extract(source: &str, selected: &str, name: &str) -> Result<String, String> {
+        let start = offset_of(source, selected, 0);
+        extract_variable(source, start..start + selected.len(), name)
+            .map(|edits| apply(source, &edits))
+            .map_err(|error| error.message)
+    }
+
+    fn inline(source: &str, offset: usize) -> Result<String, String> {
+        inline_variable(source, offset)
+            .map(|edits| apply(source, &edits))
+            .map_err(|error| error.message)
+    }
+
+    #[test]
+    fn rename_declarations_and_uses() {
+        let source = "fn add(a, b) { return a + b; }\nlet x = add(1, 2);\nprint(add(x, x));";
+
+        assert_eq!(
+            rename_in(source, offset_of(source, "add", 2), "sum").unwrap(),
+            "fn sum(a, b) { return a + b; }\nlet x = sum(1, 2);\nprint(sum(x, x));"
+        );
+        assert_eq!(
+            rename_in(source, offset_of(source, "a +", 0), "left").unwrap(),
+            "fn add(left, b) { return left + b; }\nlet x = add(1, 2);\nprint(add(x, x));"
+        );
+        // Shadowed names with the same spelling are left alone
+        let shadowed = "let x = 1;\nif x { let x = 2; print(x); }\nprint(x);";
+        assert_eq!(
+            rename_in(shadowed, 4, "y").unwrap(),
+            "let y = 1;\nif y { let x = 2; print(x); }\nprint(y);"
+        );
+
+        assert!(rename_in(source, offset_of(source, "print", 0), "show").is_err());
+        assert!(rename_in(source, offset_of(source, "x", 0), "while").is_err());
+        assert!(rename_in(source, offset_of(source, "x", 0), "1x").is_err());
+    }
+
+    #[test]
+    fn rename_refuses_capture() {
+        // `b` would refer to the renamed parameter instead of the outer `b`
+        let captured = "let b = 1;\nfn f(a) { return a + b; }";
+        assert!(rename_in(captured, offset_of(captured, "a", 0), "b").is_err());
+
+        // The outer `y` would be shadowed at its use
+        let shadowing = "let y = 1;\nfor x in [1] { print(x, y); }";
+        assert!(rename_in(shadowing, offset_of(shadowing, "x", 0), "y").is_err());
+
+        // Builtins would be hidden where they're used
+        let builtin = "let total = 1;\nprint(total);";
+        assert!(rename_in(builtin, 4, "print").is_err());
+        assert!(rename_in(builtin, 4, "len").is_ok());
+
+        // A name that was undefined would be found
+        let undefined = "let a = 1;\nprint(z);";
+        assert!(rename_in(undefined, 4, "z").is_err());
+    }
+
+    #[test]
+    fn rename_across_modules() {
+        let dir = std::env::temp_dir().join(format!("apl_refactor_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let files = [
+            (
+                "lib.apl",
+                "pub fn add(a, b) { return a + b; }\nprint(add(1, 2));",
+            ),
+            (
+                "main.apl",
+                "import \"lib.apl\" as m;\nuse m.{add};\nprint(m.add(1, 2), add(3, 4));",
+            ),
+        ];
+        for (name, source) in files {
+            fs::write(dir.join(name), source).unwrap();
+        }
+
+        let mut loader = ModuleLoader::new();
+        let main = loader.load(&dir.join("main.apl")).unwrap().file;
+        let main_source = loader.source_map().source(main).to_string();
+
+        let edits = rename(&loader, main, offset_of(&main_source, "add(3", 0), "sum").unwrap();
+        let renamed: Vec<String> = loader
+            .modules()
+            .map(|module| {
+                let edits: Vec<Edit> = edits
+                    .iter()
+                    .filter(|edit| edit.span.file == module.file)
+                    .cloned()
+                    .collect();
+                apply(loader.source_map().source(module.file), &edits)
+            })
+            .collect();
+        assert_eq!(
+            renamed,
+            vec![
+                "pub fn sum(a, b) { return a + b; }\nprint(sum(1, 2));",
+                "import \"lib.apl\" as m;\nuse m.{sum};\nprint(m.sum(1, 2), sum(3, 4));",
+            ]
+        );
+
+        // Renaming the alias renames its uses in `use` too
+        let edits = rename(&loader, main, offset_of(&main_source, "m;", 0), "lib").unwrap();
+        let main_edits: Vec<Edit> = edits.into_iter().filter(|e| e.span.file == main).collect();
+        assert_eq!(
+            apply(&main_source, &main_edits),
+            "import \"lib.apl\" as lib;\nuse lib.{add};\nprint(lib.add(1, 2), add(3, 4));"
+        );
+
+        let _ = fs::remove_dir_all(PathBuf::from(&dir));
+    }
+
+    #[test]
+    fn rename_refuses_clash_with_export() {
+        let dir = std::env::temp_dir().join(format!("apl_refactor_clash_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        // `lib.apl` already has a public `sub`
+        let files = [
+            (
+                "lib.apl",
+                "pub fn add(a, b) { return a + b; }\npub fn sub(a, b) { return a - b; }",
+            ),
+            (
+                "main.apl",
+                "import \"lib.apl\" as m;\nuse m.{add};\nprint(add(3, 4));",
+            ),
+        ];
+        for (name, source) in files {
+            fs::write(dir.join(name), source).unwrap();
+        }
+
+        let mut loader = ModuleLoader::new();
+        let main = loader.load(&dir.join("main.apl")).unwrap().file;
+        let offset = offset_of(files[1].1, "add(3", 0);
+        assert!(rename(&loader, main, offset, "sub").is_err());
+        // The old name is the renamed identifier, not the line it's on
+        assert_eq!(rename(&loader, main, offset, "add").unwrap(), Vec::new());
+
+        let _ = fs::remove_dir_all(PathBuf::from(&dir));
+    }
+
+    #[test]
+    fn extract_expressions() {
+        assert_eq!(
+            extract("fn f(a) {\n    return a * 2 + 1;\n}", "a * 2", "twice").unwrap(),
+            "fn f(a) {\n    let twice = a * 2;\n    return twice + 1;\n}"
+        );
+        assert_eq!(
+            extract("print(len([1, 2]));", "len([1, 2])", "n").unwrap(),
+            "let n = len([1, 2]);\nprint(n);"
+        );
+        assert_eq!(
+            extract("if 1 + 2 > 0 { print(1); }", "1 + 2", "sum").unwrap(),
+            "let sum = 1 + 2;\nif sum > 0 { print(1); }"
+        );
+
+        // `2 + 3` isn't an expression of `1 * 2 + 3`
+        assert!(extract("print(1 * 2 + 3);", "2 + 3", "n").is_err());
+        assert!(extract("print(1 * 2 + 3);", "* 2", "n").is_err());
+        // The condition is evaluated on every iteration
+        assert!(extract("let i = 0;\nwhile i < 3 { i = i + 1; }", "i < 3", "done").is_err());
+        assert!(extract("if 1 { } else if 2 > 1 { }", "2 > 1", "c").is_err());
+        // `g()` would run before `f()`, or before `x` is read
+        assert!(extract("print(f(), g());\nfn f() {}\nfn g() {}", "g()", "n").is_err());
+        assert!(extract("let x = 1;\nprint(x, g());\nfn g() {}", "g()", "n").is_err());
+        assert!(extract("let x = 1;\nprint(x = 2, x + 1);", "x + 1", "n").is_err());
+        // Names already in the file could be captured
+        assert!(extract("let n = 1;\nprint(n + 1);", "n + 1", "n").is_err());
+    }
+
+    #[test]
+    fn inline_variables() {
+        let source = "let a = 2;\nlet b = a + 1;\nprint(b * 3, -b);";
+        assert_eq!(
+            inline(source, offset_of(source, "b", 1)).unwrap(),
+            "let a = 2;\nprint((a + 1) * 3, -(a + 1));"
+        );
+        assert_eq!(
+            inline(source, 4).unwrap(),
+            "let b = 2 + 1;\nprint(b * 3, -b);"
+        );
+
+        assert!(inline("let a = 1;\na = 2;\nprint(a);", 4).is_err());
+        assert!(inline("let a = f();\nprint(a, a);\nfn f() {}", 4).is_err());
+        assert!(inline("let a = [1];\nprint(a, a);", 4).is_err());
+        assert!(inline("let a = [1];\nprint(a);", 4).is_ok());
+        // `x` is assigned, so `a` could differ from `x + 1` where it's used
+        assert!(
+            inline(
+                "let x = 1;\nlet a = x + 1;\nx = 5;\nprint(a);",
+                offset_of("let x = 1;\nlet a", "a", 0)
+            )
+            .is_err()
+        );
+        // The `x` in the value would be shadowed at the use
+        let shadowed = "let x = 1;\nlet a = x;\nfor x in [2] { print(a); }";
+        assert!(inline(shadowed, offset_of(shadowed, "a", 0)).is_err());
+        assert!(
+            inline(
+                "fn f(p) { return p; }",
+                offset_of("fn f(p) { return p; }", "p;", 0)
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use apl_error::RefactorError;
+use apl_interpreter::Interpreter;
+use apl_loader::{Module, ModuleLoader};
+use apl_parser::ast::{Expr, Ident, Stmt, Visibility};
+use apl_resolver::{Reference, Resolver};
+use apl_source::{FileId, Span, Symbol};
+
+use super::edit::{Edit, apply, shift};
+use super::syntax::{Resolution, check_name, first_changed, parse, resolve, walk_statements};
+
+/// Renames the variable, parameter or function at `offset` in `file` and
+/// every use of it in the loaded modules. A public name is also renamed
+/// where other modules use it, as `m.name` or through `use`.
+///
+/// The rename is refused when any name would then refer to a different
+/// declaration, e.g. when the new name is already used in the same scope.
+pub fn rename(
+    loader: &ModuleLoader,
+    file: FileId,
+    offset: usize,
+    new_name: &str,
+) -> Result<Vec<Edit>, RefactorError> {
+    check_name(new_name)?;
+
+    let globals = Interpreter::global_names();
+    let mut resolutions = HashMap::new();
+    // Every name in every module and the declaration it refers to
+    let mut occurrences: Vec<(Span, Span)> = Vec::new();
+    // Names brought in by `use`, which stand for a declaration in another module
+    let mut used: HashMap<Span, Span> = HashMap::new();
+
+    for module in loader.modules() {
+        let mut resolver = Resolver::for_module(loader, module, &globals);
+        let errors = resolver.resolve(&module.statements).len();
+        let references = resolver.references().to_vec();
+
+        occurrences.extend(references.iter().map(|r| (r.span, r.declaration)));
+        occurrences.extend(module_occurrences(loader, module, &references, &mut used));
+        resolutions.insert(module.file, Resolution { references, errors });
+    }
+
+    let declaration = |span: Span| used.get(&span).copied().unwrap_or(span);
+    let target = occurrences
+        .iter()
+        .find(|(span, _)| span.file == file && span.start <= offset && offset <= span.end)
+        .map(|&(_, span)| declaration(span))
+        .ok_or_else(|| {
+            RefactorError::new(
+                "There is no variable, parameter or function to rename here",
+                None,
+            )
+        })?;
+
+    let old_name = &loader.source_map().source(target.file)[target.start..target.end];
+    if old_name == new_name {
+        return Ok(Vec::new());
+    }
+
+    for module in loader.modules().filter(|module| module.file == target.file) {
+        if export(module, old_name) == Some(target) && module.exports().contains(&Symbol::intern(new_name)) {
+            return Err(RefactorError::new(
+                format!("The module already exports '{}'", new_name),
+                Some(target),
+            ));
+        }
+    }
+
+    let mut edits: Vec<Edit> = occurrences
+        .iter()
+        .filter(|&&(_, span)| declaration(span) == target)
+        .map(|&(span, _)| Edit::new(span, new_name))
+        .collect();
+    edits.sort_by_key(|edit| (edit.span.file, edit.span.start));
+    edits.dedup();
+
+    check_resolution(loader, &edits, &resolutions, new_name)?;
+    Ok(edits)
+}
+
+// Names the resolver doesn't follow into other modules: members accessed
+// through an import alias, and the names and module of a `use`
+fn module_occurrences(
+    loader: &ModuleLoader,
+    module: &Module,
+    references: &[Reference],
+    used: &mut HashMap<Span, Span>,
+) -> Vec<(Span, Span)> {
+    let mut occurrences = Vec::new();
+
+    // Imported modules by the declaration of their alias
+    let mut imports: HashMap<Span, &Module> = HashMap::new();
+    for stmt in &module.statements {
+        match stmt {
+            Stmt::Import(import) => {
+                if let Some(imported) = imported(loader, module, &import.alias) {
+                    imports.insert(import.alias.span, imported);
+                }
+            }
+            Stmt::Use(use_decl) => {
+                if let Some(alias) = import_alias(module, &use_decl.module) {
+                    occurrences.push((use_decl.module.span, alias));
+                }
+                let Some(imported) = imported(loader, module, &use_decl.module) else {
+                    continue;
+                };
+                for name in &use_decl.names {
+                    if let Some(declaration) = export(imported, name.as_str()) {
+                        used.insert(name.span, declaration);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    walk_statements(&module.statements, &mut |expr| {
+        let (object, member) = match expr {
+            Expr::PropertyAccess { object, propert } => (object, propert),
+            Expr::MethodAccess { object, method } => match &**method {
+                Expr::Call { callee, .. } => match &**callee {
+                    Expr::Variable(variable) => (object, &variable.name),
+                    _ => return,
+                },
+                _ => return,
+            },
+            _ => return,
+        };
+
+        if let Expr::Variable(alias) = &**object
+            && let Some(reference) = references.iter().find(|r| r.span == alias.name.span)
+            && let Some(imported) = imports.get(&reference.declaration)
+            && let Some(declaration) = export(imported, member.as_str())
+        {
+            occurrences.push((member.span, declaration));
+        }
+    });
+
+    occurrences
+}
+
+fn imported<'a>(loader: &'a ModuleLoader, module: &Module, alias: &Ident) -> Option<&'a Module> {
+    loader.module(module.imports.get(&alias.name)?)
+}
+
+fn import_alias(module: &Module, alias: &Ident) -> Option<Span> {
+    module.statements.iter().find_map(|stmt| match stmt {
+        Stmt::Import(import) if import.alias == *alias => Some(import.alias.span),
+        _ => None,
+    })
+}
+
+// Where `module` declares its public `name`
+fn export(module: &Module, name: &str) -> Option<Span> {
+    module.statements.iter().find_map(|stmt| match stmt {
+        Stmt::FunctionDecl(function)
+            if function.visibility == Visibility::Public && function.name.as_str() == name =>
+        {
+            Some(function.name.span)
+        }
+        Stmt::VariableDecl(decl) if decl.visibility == Visibility::Public => decl
+            .pattern
+            .bindings()
+            .into_iter()
+            .find(|binding| binding.as_str() == name)
+            .map(|binding| binding.span),
+        _ => None,
+    })
+}
+
+// Resolves every module again as it would be after the rename, checking
+// that each name still refers to the same declaration
+fn check_resolution(
+    loader: &ModuleLoader,
+    edits: &[Edit],
+    resolutions: &HashMap<FileId, Resolution>,
+    new_name: &str,
+) -> Result<(), RefactorError> {
+    let mut renamed: HashMap<PathBuf, Module> = HashMap::new();
+    for module in loader.modules() {
+        let source = apply(
+            loader.source_map().source(module.file),
+            &file_edits(edits, module.file),
+        );
+        let parsed = parse(&source).ok_or_else(|| {
+            RefactorError::new(format!("'{}' can't be used as a name here", new_name), None)
+        })?;
+
+        renamed.insert(
+            module.path.clone(),
+            Module {
+                path: module.path.clone(),
+                file: module.file,
+                statements: parsed.statements,
+                imports: module.imports.clone(),
+            },
+        );
+    }
+
+    for module in loader.modules() {
+        // Imports keep their order, so the nth one still names the same module
+        let aliases = |statements: &[Stmt]| -> Vec<Symbol> {
+            statements
+                .iter()
+                .filter_map(|stmt| match stmt {
+                    Stmt::Import(import) => Some(import.alias.name),
+                    _ => None,
+                })
+                .collect()
+        };
+        let after = &renamed[&module.path];
+        let modules = aliases(&module.statements)
+            .into_iter()
+            .zip(aliases(&after.statements))
+            .filter_map(|(old, new)| {
+                let imported = &renamed[module.imports.get(&old)?];
+                Some((new, imported.exports()))
+            })
+            .collect();
+
+        let before = &resolutions[&module.file];
+        let after = resolve(&after.statements, modules);
+        // Builtins aren't declared anywhere, so names that come to refer to
+        // a declaration instead only show up as extra references
+        if after.errors != before.errors || after.references.len() != before.references.len() {
+            return Err(RefactorError::new(
+                format!(
+                    "Renaming to '{}' would change what some names refer to",
+                    new_name
+                ),
+                None,
+            ));
+        }
+
+        let edits = file_edits(edits, module.file);
+        if let Some(span) = first_changed(&before.references, &after.references, |offset| {
+            vec![shift(&edits, offset)]
+        }) {
+            return Err(RefactorError::new(
+                format!(
+                    "Renaming to '{}' would change what this name refers to",
+                    new_name
+                ),
+                Some(span),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn file_edits(edits: &[Edit], file: FileId) -> Vec<Edit> {
+    edits
+        .iter()
+        .filter(|edit| edit.span.file == file)
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use apl_error::RefactorError;
+use apl_interpreter::Interpreter;
+use apl_parser::ast::{Expr, Part, Stmt};
+use apl_parser::core::Parser;
+use apl_resolver::{Reference, Resolver};
+use apl_scanner::{KEYWORDS, Scanner, Token, TokenType};
+use apl_source::{Span, Symbol};
+
+// A source that scanned and parsed without errors
+pub(crate) struct Parsed<'a> {
+    pub tokens: Vec<Token<'a>>,
+    pub statements: Vec<Stmt>,
+    // One span per statement, in the order `with_spans` visits them
+    pub spans: Vec<Span>,
+}
+
+pub(crate) fn parse(source: &str) -> Option<Parsed<'_>> {
+    if source.is_empty() {
+        return Some(Parsed {
+            tokens: Vec::new(),
+            statements: Vec::new(),
+            spans: Vec::new(),
+        });
+    }
+
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+    if !scanner.errors.is_empty() {
+        return None;
+    }
+
+    let mut parser = Parser::new(tokens.clone());
+    let statements = parser.try_parse().ok()?;
+    let spans = parser.statement_spans().to_vec();
+
+    Some(Parsed {
+        tokens,
+        statements,
+        spans,
+    })
+}
+
+pub(crate) fn has_errors() -> RefactorError {
+    RefactorError::new("The file has errors, fix them before refactoring", None)
+}
+
+pub(crate) struct Resolution {
+    pub references: Vec<Reference>,
+    pub errors: usize,
+}
+
+/// Resolves `statements` with the builtins and the given modules, as alias
+/// and exports, in scope.
+pub(crate) fn resolve(statements: &[Stmt], modules: Vec<(Symbol, Vec<Symbol>)>) -> Resolution {
+    let mut resolver = Resolver::new(&Interpreter::global_names());
+    for (alias, exports) in modules {
+        resolver.define_module(alias, exports);
+    }
+
+    let errors = resolver.resolve(statements).len();
+    Resolution {
+        references: resolver.references().to_vec(),
+        errors,
+    }
+}
+
+/// The first reference in `before` that doesn't resolve to the same
+/// declaration after an edit. `relocate` maps an offset before the edit to
+/// where that text is afterwards: nowhere when it was removed, and more than
+/// once when it was copied.
+pub(crate) fn first_changed(
+    before: &[Reference],
+    after: &[Reference],
+    relocate: impl Fn(usize) -> Vec<usize>,
+) -> Option<Span> {
+    let after: HashMap<usize, usize> = after
+        .iter()
+        .map(|reference| (reference.span.start, reference.declaration.start))
+        .collect();
+
+    before
+        .iter()
+        .find(|reference| {
+            let declarations = relocate(reference.declaration.start);
+            relocate(reference.span.start).iter().any(|start| {
+                !after
+                    .get(start)
+                    .is_some_and(|declaration| declarations.contains(declaration))
+            })
+        })
+        .map(|reference| reference.span)
+}
+
+pub(crate) fn check_name(name: &str) -> Result<(), RefactorError> {
+    if KEYWORDS.iter().any(|(keyword, _)| *keyword == name) {
+        return Err(RefactorError::new(
+            format!("'{}' is a keyword and can't be used as a name", name),
+            None,
+        ));
+    }
+
+    let is_identifier = !name.is_empty() && {
+        let mut scanner = Scanner::new(name);
+        let tokens = scanner.scan_tokens();
+        scanner.errors.is_empty()
+            && matches!(
+                tokens.first().map(|token| &token.token_type),
+                Some(TokenType::Identifier(identifier)) if identifier == name
+            )
+            && tokens.len() == 2
+    };
+    if is_identifier {
+        Ok(())
+    } else {
+        Err(RefactorError::new(
+            format!("'{}' is not a valid name", name),
+            None,
+        ))
+    }
+}
+
+/// Every statement paired with its span, parents before their children.
+pub(crate) fn with_spans<'a>(statements: &'a [Stmt], spans: &[Span]) -> Vec<(&'a Stmt, Span)> {
+    let mut flattened = Vec::new();
+    flatten(statements, &mut flattened);
+    flattened.into_iter().zip(spans.iter().copied()).collect()
+}
+
+// Statements in the order the parser records their spans
+fn flatten<'a>(statements: &'a [Stmt], flattened: &mut Vec<&'a Stmt>) {
+    for stmt in statements {
+        flattened.push(stmt);
+        match stmt {
+            Stmt::FunctionDecl(function) => flatten(&function.statements, flattened),
+            Stmt::For(for_loop) => flatten(&for_loop.body, flattened),
+            Stmt::If(if_stmt) => {
+                flatten(&if_stmt.then_branch, flattened);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    flatten(else_branch, flattened);
+                }
+            }
+            Stmt::While(while_loop) => flatten(&while_loop.body, flattened),
+            Stmt::Loop(body) => flatten(body, flattened),
+            _ => {}
+        }
+    }
+}
+
+/// The expression a statement evaluates itself, not counting its blocks.
+pub(crate) fn own_expression(stmt: &mut Stmt) -> Option<&mut Expr> {
+    match stmt {
+        Stmt::VariableDecl(decl) => Some(&mut decl.initializer),
+        Stmt::Expression(expr) | Stmt::Return(Some(expr)) => Some(expr),
+        Stmt::For(for_loop) => Some(&mut for_loop.iterable),
+        Stmt::If(if_stmt) => Some(&mut if_stmt.condition),
+        Stmt::While(while_loop) => Some(&mut while_loop.condition),
+        _ => None,
+    }
+}
+
+/// Visits every expression in `statements`, outer expressions first.
+pub(crate) fn walk_statements<'a>(statements: &'a [Stmt], visit: &mut impl FnMut(&'a Expr)) {
+    for stmt in statements {
+        match stmt {
+            Stmt::VariableDecl(decl) => walk_expression(&decl.initializer, visit),
+            Stmt::Expression(expr) | Stmt::Return(Some(expr)) => walk_expression(expr, visit),
+            Stmt::FunctionDecl(function) => walk_statements(&function.statements, visit),
+            Stmt::For(for_loop) => {
+                walk_expression(&for_loop.iterable, visit);
+                walk_statements(&for_loop.body, visit);
+            }
+            Stmt::If(if_stmt) => {
+                walk_expression(&if_stmt.condition, visit);
+                walk_statements(&if_stmt.then_branch, visit);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    walk_statements(else_branch, visit);
+                }
+            }
+            Stmt::While(while_loop) => {
+                walk_expression(&while_loop.condition, visit);
+                walk_statements(&while_loop.body, visit);
+            }
+            Stmt::Loop(body) => walk_statements(body, visit),
+            Stmt::Return(None) | Stmt::Break | Stmt::Continue | Stmt::Import(_) | Stmt::Use(_) => {}
+        }
+    }
+}
+
+pub(crate) fn walk_expression<'a>(expr: &'a Expr, visit: &mut impl FnMut(&'a Expr)) {
+    visit(expr);
+    match expr {
+        Expr::Literal(_) | Expr::Variable(_) | Expr::Identifier(_) => {}
+        Expr::Binary(binary) => {
+            walk_expression(&binary.left, visit);
+            walk_expression(&binary.right, visit);
+        }
+        Expr::Unary(unary) => walk_expression(&unary.right, visit),
+        Expr::Grouping(inner) => walk_expression(inner, visit),
+        Expr::Assignment { value, .. } => walk_expression(value, visit),
+        Expr::Call { callee, arguments } => {
+            walk_expression(callee, visit);
+            for argument in arguments {
+                walk_expression(argument, visit);
+            }
+        }
+        Expr::MethodAccess { object, method } => {
+            walk_expression(object, visit);
+            walk_expression(method, visit);
+        }
+        Expr::PropertyAccess { object, .. } => walk_expression(object, visit),
+        Expr::Array(elements) | Expr::Tuple(elements) => {
+            for element in elements {
+                walk_expression(element, visit);
+            }
+        }
+        Expr::Index { object, index } => {
+            walk_expression(object, visit);
+            walk_expression(index, visit);
+        }
+        Expr::Interpolated { parts, .. } => {
+            for part in parts {
+                if let Part::Expr(expr) = part {
+                    walk_expression(expr, visit);
+                }
+            }
+        }
+    }
+}
+
+/// Replaces the variables called `name` in `expr` with `replacement`,
+/// returning how many were replaced.
+pub(crate) fn substitute(expr: &mut Expr, name: &str, replacement: &Expr) -> usize {
+    let children: Vec<&mut Expr> = match expr {
+        Expr::Variable(variable) if variable.name.as_str() == name => {
+            *expr = replacement.clone();
+            return 1;
+        }
+        Expr::Literal(_) | Expr::Variable(_) | Expr::Identifier(_) => Vec::new(),
+        Expr::Binary(binary) => vec![&mut binary.left, &mut binary.right],
+        Expr::Unary(unary) => vec![&mut unary.right],
+        Expr::Grouping(inner) => vec![inner],
+        Expr::Assignment { value, .. } => vec![value],
+        Expr::Call { callee, arguments } => {
+            std::iter::once(&mut **callee).chain(arguments).collect()
+        }
+        Expr::MethodAccess { object, method } => vec![object, method],
+        Expr::PropertyAccess { object, .. } => vec![object],
+        Expr::Array(elements) | Expr::Tuple(elements) => elements.iter_mut().collect(),
+        Expr::Index { object, index } => vec![object, index],
+        Expr::Interpolated { parts, .. } => parts
+            .iter_mut()
+            .filter_map(|part| match part {
+                Part::Expr(expr) => Some(expr),
+                Part::Text(_) => None,
+            })
+            .collect(),
+    };
+
+    children
+        .into_iter()
+        .map(|child| substitute(child, name, replacement))
+        .sum()
+}
@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+
+use apl_error::ResolveError;
+use apl_error::resolveerror::ResolveErrorKind;
+use apl_loader::{Module, ModuleLoader};
+use apl_parser::ast::{Expr, Function, Ident, Part, Pattern, Stmt};
+use apl_source::{Span, Symbol};
+
+#[derive(Debug, Clone)]
+enum Binding {
+    Value,
+    // An imported module and the names it exports
+    Module(Vec<Symbol>),
+}
+
+#[derive(Debug, Clone)]
+struct Declaration {
+    binding: Binding,
+    // Globals and modules defined through `define_module` have no span
+    span: Option<Span>,
+}
+
+/// A name in the source and the declaration it resolved to. Declarations are
+/// recorded as references to themselves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reference {
+    pub span: Span,
+    pub declaration: Span,
+}
+
+/// Checks that every name used in a module refers to a declaration in scope.
+///
+/// The resolver keeps its outermost scope between calls to [`Resolver::resolve`],
+/// so statements can be resolved a few at a time.
+pub struct Resolver {
+    scopes: Vec<HashMap<Symbol, Declaration>>,
+    errors: Vec<ResolveError>,
+    references: Vec<Reference>,
+}
+
+impl Resolver {
+    /// Creates a resolver where `globals` (the builtins) are always in scope.
+    pub fn new(globals: &[&str]) -> Self {
+        let globals = globals
+            .iter()
+            .map(|name| {
+                let declaration = Declaration {
+                    binding: Binding::Value,
+                    span: None,
+                };
+                (Symbol::intern(name), declaration)
+            })
+            .collect();
+
+        Self {
+            scopes: vec![globals, HashMap::new()],
+            errors: Vec::new(),
+            references: Vec::new(),
+        }
+    }
+
+    /// Resolves a loaded module, checking member accesses on its imports
+    /// against what the imported modules export.
+    pub fn resolve_module(
+        loader: &ModuleLoader,
+        module: &Module,
+        globals: &[&str],
+    ) -> Vec<ResolveError> {
+        Self::for_module(loader, module, globals).resolve(&module.statements)
+    }
+
+    /// A resolver for a loaded module, with its imports already defined.
+    pub fn for_module(loader: &ModuleLoader, module: &Module, globals: &[&str]) -> Self {
+        let mut resolver = Self::new(globals);
+        for (alias, path) in &module.imports {
+            if let Some(imported) = loader.module(path) {
+                resolver.define_module(*alias, imported.exports());
+            }
+        }
+        resolver
+    }
+
+    /// Declares an imported module under `alias`.
+    pub fn define_module(&mut self, alias: impl Into<Symbol>, exports: Vec<Symbol>) {
+        self.declare(&Ident::new(alias, Span::default()), Binding::Module(exports));
+    }
+
+    pub fn resolve(&mut self, statements: &[Stmt]) -> Vec<ResolveError> {
+        self.statements(statements);
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Every name resolved so far to a declaration in the source, in the
+    /// order they were visited.
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    // Functions are hoisted, and their bodies are resolved once the rest of
+    // the block is, so they can refer to anything declared in it.
+    fn statements(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            if let Stmt::FunctionDecl(function) = stmt {
+                self.declare(&function.name, Binding::Value);
+            }
+        }
+
+        let mut functions = Vec::new();
+        for stmt in statements {
+            match stmt {
+                Stmt::FunctionDecl(function) => functions.push(function),
+                _ => self.statement(stmt),
+            }
+        }
+
+        for function in functions {
+            self.function(function);
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        self.scopes.push(HashMap::new());
+        self.statements(statements);
+        self.scopes.pop();
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.scopes.push(HashMap::new());
+        for param in &function.params {
+            self.declare(param, Binding::Value);
+        }
+        self.statements(&function.statements);
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VariableDecl(decl) => {
+                self.expression(&decl.initializer);
+                self.declare_pattern(&decl.pattern);
+            }
+            Stmt::Expression(expr) => self.expression(expr),
+            // Hoisted and resolved at the end of the block, see `statements`
+            Stmt::FunctionDecl(_) => {}
+            Stmt::For(for_loop) => {
+                self.expression(&for_loop.iterable);
+                self.scopes.push(HashMap::new());
+                self.declare_pattern(&for_loop.pattern);
+                self.block(&for_loop.body);
+                self.scopes.pop();
+            }
+            Stmt::If(if_stmt) => {
+                self.expression(&if_stmt.condition);
+                self.block(&if_stmt.then_branch);
+                if let Some(else_branch) = &if_stmt.else_branch {
+                    self.block(else_branch);
+                }
+            }
+            Stmt::While(while_loop) => {
+                self.expression(&while_loop.condition);
+                self.block(&while_loop.body);
+            }
+            Stmt::Loop(body) => self.block(body),
+            Stmt::Break | Stmt::Continue => {}
+            Stmt::Return(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            // Modules the loader didn't register are left unchecked
+            Stmt::Import(import) => match self.lookup(import.alias.name) {
+                Some(Binding::Module(exports)) => {
+                    let exports = exports.clone();
+                    self.declare(&import.alias, Binding::Module(exports));
+                }
+                _ => self.declare(&import.alias, Binding::Value),
+            },
+            Stmt::Use(use_decl) => {
+                for name in &use_decl.names {
+                    self.declare(name, Binding::Value);
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(_) | Expr::Identifier(_) => {}
+            Expr::Variable(variable) => self.reference(&variable.name),
+            Expr::Binary(binary) => {
+                self.expression(&binary.left);
+                self.expression(&binary.right);
+            }
+            Expr::Unary(unary) => self.expression(&unary.right),
+            Expr::Grouping(inner) => self.expression(inner),
+            Expr::Assignment { name, value } => {
+                self.expression(value);
+                self.reference(name);
+            }
+            Expr::Call { callee, arguments } => {
+                self.expression(callee);
+                self.expressions(arguments);
+            }
+            Expr::MethodAccess { object, method } => match (self.module(object), &**method) {
+                (Some(exports), Expr::Call { callee, arguments }) => {
+                    if let Expr::Variable(member) = &**callee {
+                        self.member(&exports, object, &member.name);
+                    }
+                    self.expressions(arguments);
+                }
+                // `a.f(b)` calls `f(a, b)`
+                _ => {
+                    self.expression(object);
+                    self.expression(method);
+                }
+            },
+            Expr::PropertyAccess { object, propert } => match self.module(object) {
+                Some(exports) => self.member(&exports, object, propert),
+                None => self.expression(object),
+            },
+            Expr::Array(elements) | Expr::Tuple(elements) => self.expressions(elements),
+            Expr::Index { object, index } => {
+                self.expression(object);
+                self.expression(index);
+            }
+            Expr::Interpolated { parts, .. } => {
+                for part in parts {
+                    if let Part::Expr(expr) = part {
+                        self.expression(expr);
+                    }
+                }
+            }
+        }
+    }
+
+    fn expressions(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            self.expression(expr);
+        }
+    }
+
+    // The exports of `expr` when it names an imported module
+    fn module(&self, expr: &Expr) -> Option<Vec<Symbol>> {
+        match expr {
+            Expr::Variable(variable) => match self.lookup(variable.name.name) {
+                Some(Binding::Module(exports)) => Some(exports.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn member(&mut self, exports: &[Symbol], object: &Expr, member: &Ident) {
+        if let Expr::Variable(alias) = object {
+            self.reference(&alias.name);
+        }
+        if !exports.contains(&member.name) {
+            let module = match object {
+                Expr::Variable(variable) => variable.name.as_str(),
+                _ => "module",
+            };
+            self.errors.push(ResolveError::new(
+                ResolveErrorKind::UndefinedMember,
+                format!("Module '{}' has no public member '{}'", module, member),
+                member.span,
+            ));
+        }
+    }
+
+    fn reference(&mut self, name: &Ident) {
+        let declaration = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.name));
+
+        match declaration {
+            Some(declaration) => {
+                if let Some(span) = declaration.span {
+                    self.references.push(Reference {
+                        span: name.span,
+                        declaration: span,
+                    });
+                }
+            }
+            None => self.errors.push(ResolveError::new(
+                ResolveErrorKind::UndefinedVariable,
+                format!("Cannot find '{}' in this scope", name),
+                name.span,
+            )),
+        }
+    }
+
+    fn declare_pattern(&mut self, pattern: &Pattern) {
+        for name in pattern.bindings() {
+            self.declare(name, Binding::Value);
+        }
+    }
+
+    // Names built without a span, like module aliases given to
+    // `define_module`, aren't recorded as references
+    fn declare(&mut self, name: &Ident, binding: Binding) {
+        let span = (name.span != Span::default()).then_some(name.span);
+        if let Some(span) = span {
+            self.references.push(Reference {
+                span,
+                declaration: span,
+            });
+        }
+
+        self.scopes
+            .last_mut()
+            .expect("the global scope is never popped")
+            .insert(name.name, Declaration { binding, span });
+    }
+
+    fn lookup(&self, name: Symbol) -> Option<&Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name))
+            .map(|declaration| &declaration.binding)
+    }
+}
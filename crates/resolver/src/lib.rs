@@ -0,0 +1,162 @@
+mod core;
+mod queries;
+
+pub use core::{Reference, Resolver};
+pub use queries::{Globals, Modules, Resolution, Resolve};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use apl_error::ResolveError;
+    use apl_error::resolveerror::ResolveErrorKind;
+    use apl_parser::core::Parser;
+    use apl_scanner::Scanner;
+
+    fn resolve(source: &str) -> Vec<ResolveError> {
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .try_parse()
+            .unwrap();
+
+        let mut resolver = Resolver::new(&["print"]);
+        resolver.define_module("m", vec!["add".into(), "pi".into()]);
+        resolver.resolve(&statements)
+    }
+
+    fn undefined(source: &str) -> Vec<(ResolveErrorKind, usize, usize)> {
+        resolve(source)
+            .iter()
+            .map(|error| (error.kind, error.span.start, error.span.end))
+            .collect()
+    }
+
+    #[test]
+    fn resolve_scopes() {
+        assert!(resolve("let a = 1; print(a);").is_empty());
+        assert!(resolve("let [x, ..rest] = [1, 2]; print(x, rest);").is_empty());
+        assert!(resolve("for (k, v) in [] { print(k, v); }").is_empty());
+
+        // Blocks end a binding's scope
+        assert_eq!(
+            undefined("if a { let b = 1; } print(b);"),
+            vec![
+                (ResolveErrorKind::UndefinedVariable, 3, 4),
+                (ResolveErrorKind::UndefinedVariable, 26, 27),
+            ]
+        );
+        // Variables can't be used in their own initializer
+        assert_eq!(
+            undefined("let c = c;"),
+            vec![(ResolveErrorKind::UndefinedVariable, 8, 9)]
+        );
+    }
+
+    #[test]
+    fn resolve_functions() {
+        // Functions are hoisted and their bodies see the whole enclosing block
+        assert!(
+            resolve("even(2); fn even(n) { return odd(n); } fn odd(n) { return even(n); }")
+                .is_empty()
+        );
+        assert!(resolve("fn f() { return x; } let x = 1;").is_empty());
+
+        assert_eq!(
+            undefined("fn f(a) { return a + b; }"),
+            vec![(ResolveErrorKind::UndefinedVariable, 21, 22)]
+        );
+        // Methods are called as functions, `a.f()` is `f(a)`
+        assert_eq!(
+            undefined("let a = 1; a.f();"),
+            vec![(ResolveErrorKind::UndefinedVariable, 13, 14)]
+        );
+    }
+
+    #[test]
+    fn resolve_module_members() {
+        assert!(resolve("print(m.add(m.pi));").is_empty());
+
+        assert_eq!(
+            undefined("m.sub(1); print(m.tau);"),
+            vec![
+                (ResolveErrorKind::UndefinedMember, 2, 5),
+                (ResolveErrorKind::UndefinedMember, 18, 21),
+            ]
+        );
+        // A local shadowing the module isn't checked
+        assert!(resolve("fn f(m) { return m.anything; }").is_empty());
+    }
+
+    #[test]
+    fn resolve_references() {
+        let source = "fn f(a) { return a + g; } let g = 1; f(g);";
+        let statements = Parser::new(Scanner::new(source).scan_tokens())
+            .try_parse()
+            .unwrap();
+
+        let mut resolver = Resolver::new(&["print"]);
+        assert!(resolver.resolve(&statements).is_empty());
+
+        let text = |span: apl_source::Span| &source[span.start..span.end];
+        let references: Vec<(&str, usize, usize)> = resolver
+            .references()
+            .iter()
+            .map(|r| (text(r.span), r.span.start, r.declaration.start))
+            .collect();
+        assert_eq!(
+            references,
+            vec![
+                ("f", 3, 3),
+                ("g", 30, 30),
+                ("f", 37, 3),
+                ("g", 39, 30),
+                ("a", 5, 5),
+                ("a", 17, 5),
+                ("g", 21, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_query() {
+        use apl_database::Database;
+
+        let mut db = Database::new();
+        db.set::<Globals>((), vec!["print".to_string()]);
+        let main = db.set_source(
+            "main.apl",
+            "import \"lib.apl\" as l;\nprint(l.add);".to_string(),
+        );
+        db.set_source("lib.apl", "pub fn add() {}".to_string());
+
+        let resolution = db.get::<Resolve>(&main);
+        assert!(resolution.errors.is_empty());
+        assert_eq!(resolution.references.len(), 2);
+
+        // The export going away is an error in the importing file
+        db.set_source("lib.apl", "pub fn sub() {}".to_string());
+        let kinds: Vec<ResolveErrorKind> = db
+            .get::<Resolve>(&main)
+            .errors
+            .iter()
+            .map(|error| error.kind)
+            .collect();
+        assert_eq!(kinds, vec![ResolveErrorKind::UndefinedMember]);
+    }
+
+    #[test]
+    fn modules_in_scope_everywhere() {
+        use apl_database::Database;
+        use apl_source::Symbol;
+
+        let mut db = Database::new();
+        db.set::<Modules>((), vec![(Symbol::intern("m"), vec![Symbol::intern("f")])]);
+        let file = db.set_source("<repl:2>", "m.f; m.g;".to_string());
+
+        let kinds: Vec<ResolveErrorKind> = db
+            .get::<Resolve>(&file)
+            .errors
+            .iter()
+            .map(|error| error.kind)
+            .collect();
+        assert_eq!(kinds, vec![ResolveErrorKind::UndefinedMember]);
+    }
+}
@@ -0,0 +1,64 @@
+use apl_database::{Database, Input, Query};
+use apl_error::ResolveError;
+use apl_loader::{Exports, Imports};
+use apl_source::{FileId, Symbol};
+
+use super::{Reference, Resolver};
+
+/// The names always in scope, i.e. the builtins, and in the REPL what
+/// earlier inputs declared.
+pub struct Globals;
+
+impl Input for Globals {
+    type Key = ();
+    type Value = Vec<String>;
+}
+
+/// Modules in scope in every file and the names they export, such as the
+/// ones earlier REPL inputs imported.
+pub struct Modules;
+
+impl Input for Modules {
+    type Key = ();
+    type Value = Vec<(Symbol, Vec<Symbol>)>;
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Resolution {
+    pub references: Vec<Reference>,
+    pub errors: Vec<ResolveError>,
+}
+
+/// Resolves every name in a file. Imports are defined from the files the
+/// loader registered for them, so member accesses are checked against what
+/// those export.
+pub struct Resolve;
+
+impl Query for Resolve {
+    type Key = FileId;
+    type Value = Resolution;
+
+    fn execute(db: &Database, file: &FileId) -> Resolution {
+        let globals = db.input::<Globals>(&()).unwrap_or_default();
+        let globals: Vec<&str> = globals.iter().map(String::as_str).collect();
+        let mut resolver = Resolver::new(&globals);
+
+        for (alias, exports) in db.input::<Modules>(&()).unwrap_or_default().iter() {
+            resolver.define_module(*alias, exports.clone());
+        }
+
+        for (alias, path) in db.get::<Imports>(file).iter() {
+            // Registered under the same name the loader gives them
+            let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if let Some(imported) = db.file(&path.display().to_string()) {
+                resolver.define_module(*alias, db.get::<Exports>(&imported).to_vec());
+            }
+        }
+
+        let errors = resolver.resolve(db.syntax(*file).statements());
+        Resolution {
+            references: resolver.references().to_vec(),
+            errors,
+        }
+    }
+}
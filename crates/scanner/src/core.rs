@@ -1,10 +1,34 @@
 use super::{Position, Token, TokenType};
 use apl_error::{LexError, lexerror::LexErrorType};
-use std::{iter::Peekable, str::Chars};
+use apl_source::{FileId, Span, Symbol};
+use std::{iter::Peekable, ops::Range, str::Chars};
+
+/// Every keyword and the token it scans to.
+pub const KEYWORDS: &[(&str, TokenType)] = &[
+    ("let", TokenType::Let),
+    ("if", TokenType::If),
+    ("else", TokenType::Else),
+    ("for", TokenType::For),
+    ("while", TokenType::While),
+    ("loop", TokenType::Loop),
+    ("in", TokenType::In),
+    ("fn", TokenType::Function),
+    ("pub", TokenType::Pub),
+    ("import", TokenType::Import),
+    ("as", TokenType::As),
+    ("use", TokenType::Use),
+    ("return", TokenType::Return),
+    ("break", TokenType::Break),
+    ("continue", TokenType::Continue),
+];
 
 pub(crate) enum ScannerMode {
     Normal,
     StringLiteral,
+    // `r"..."` closed by a quote and this many `#`
+    RawString(usize),
+    // `"""..."""`
+    MultilineString,
     CharLiteral,
     BlockComment,
     LineComment,
@@ -14,22 +38,27 @@ pub struct Scanner<'a> {
     // Character source
     pub chars: Peekable<Chars<'a>>,
 
-    // Lexeme construction
-    pub current_lexeme: String,
-
     // Position tracking
-    position: Position,
+    pub(crate) position: Position,
     pub current_char: Option<char>,
     pub start_line: usize,
     pub start_column: usize,
+    start_offset: usize,
 
     // Error handling
     pub errors: Vec<LexError>,
+    // Spans of the comments skipped over, for tools that keep them
+    pub comments: Vec<Span>,
 
     // State flag
     mode: ScannerMode,
+    // The brace depth inside each `${...}` being scanned, innermost last
+    pub(crate) interpolations: Vec<usize>,
+    // Set once `Eof` was returned by the iterator
+    finished: bool,
     // Original source for error context
     pub source: &'a str,
+    pub file: FileId,
 }
 
 #[macro_export]
@@ -60,49 +89,110 @@ macro_rules! transition_mode {
 }
 
 impl<'a> Scanner<'a> {
+    /// Creates a scanner for a source that isn't registered in a `SourceMap`,
+    /// spans will point into the default file.
     pub fn new(source: &'a str) -> Self {
+        Self::with_file(source, FileId::default())
+    }
+
+    pub fn with_file(source: &'a str, file: FileId) -> Self {
         assert!(!source.is_empty(), "Scanner source cannot be empty");
 
         Self {
             chars: source.chars().peekable(),
-            current_lexeme: String::new(),
             position: Position::new(),
             current_char: None,
             start_line: 1,
             start_column: 1,
+            start_offset: 0,
+            errors: Vec::new(),
+            comments: Vec::new(),
+            mode: ScannerMode::Normal,
+            interpolations: Vec::new(),
+            finished: false,
+            source,
+            file,
+        }
+    }
+
+    /// Continues scanning `source` from a token boundary at `offset`, where
+    /// the scanner is at `line` and `column`.
+    pub fn resume(
+        source: &'a str,
+        file: FileId,
+        offset: usize,
+        line: usize,
+        column: usize,
+    ) -> Self {
+        Self {
+            chars: source[offset..].chars().peekable(),
+            position: Position {
+                line,
+                column,
+                offset,
+            },
+            current_char: None,
+            start_line: line,
+            start_column: column,
+            start_offset: offset,
             errors: Vec::new(),
+            comments: Vec::new(),
             mode: ScannerMode::Normal,
+            interpolations: Vec::new(),
+            finished: false,
             source,
+            file,
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
-        let mut tokens: Vec<Token> = Vec::new();
+    /// Scans the whole source at once, see the `Iterator` implementation to
+    /// scan a token at a time.
+    pub fn scan_tokens(&mut self) -> Vec<Token<'a>> {
+        self.collect()
+    }
 
+    // Scans up to and including the next token, `Eof` once the source is
+    // exhausted
+    pub(crate) fn next_token(&mut self) -> Token<'a> {
         while !self.is_at_end() {
             let token = match self.mode {
                 ScannerMode::Normal => self.scan_normal_mode(),
                 ScannerMode::StringLiteral => self.scan_string_literal(),
+                ScannerMode::RawString(hashes) => self.scan_raw_string(hashes),
+                ScannerMode::MultilineString => self.scan_multiline_string(),
                 ScannerMode::CharLiteral => self.scan_char_literal(),
                 ScannerMode::BlockComment => self.scan_block_comment(),
                 ScannerMode::LineComment => self.scan_line_comment(),
             };
 
-            match token {
-                Some(token) => {
-                    tokens.push(token);
-                }
-                None => {}
+            if let Some(token) = token {
+                return token;
             }
         }
 
-        tokens.push(Token {
+        // A comment at the very end switches modes with nothing left to scan
+        match self.mode {
+            ScannerMode::BlockComment => self.scan_block_comment(),
+            ScannerMode::LineComment => self.scan_line_comment(),
+            _ => None,
+        };
+
+        // The source ended inside an interpolation
+        if !self.interpolations.is_empty() {
+            self.interpolations.clear();
+            self.start_token();
+            self.record_error(LexErrorType::UnterminatedString);
+        }
+
+        Token {
+            span: Span::new(self.file, self.position.offset, self.position.offset),
             token_type: TokenType::Eof,
-            lexeme: "".to_string(),
+            lexeme: "",
             line: self.position.line,
             column: self.position.column,
-        });
-        tokens
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
+        }
     }
 
     pub(crate) fn set_scanner_mode(&mut self, mode: ScannerMode) {
@@ -112,7 +202,7 @@ impl<'a> Scanner<'a> {
     pub(crate) fn advance(&mut self) -> Option<char> {
         let c = self.chars.next()?;
         self.current_char = Some(c);
-        self.current_lexeme.push(c);
+        self.position.offset += c.len_utf8();
 
         if c == '\n' {
             self.position.increment_line();
@@ -125,47 +215,81 @@ impl<'a> Scanner<'a> {
     }
 
     pub(crate) fn is_at_end(&mut self) -> bool {
-        match self.peek() {
-            Some(_) => false,
-            None => true,
-        }
+        self.peek().is_none()
     }
 
     pub(crate) fn peek(&mut self) -> Option<char> {
         self.chars.peek().copied()
     }
 
+    // The source after the current position, for looking further ahead than
+    // `peek`
+    pub(crate) fn rest(&self) -> &'a str {
+        &self.source[self.position.offset..]
+    }
+
     pub(crate) fn start_token(&mut self) {
         self.start_line = self.position.line;
         self.start_column = self.position.column;
-        self.current_lexeme.clear();
+        self.start_offset = self.position.offset;
     }
 
-    pub(crate) fn end_token(&mut self, token_type: TokenType) -> Token {
+    // The source from the start of the current token to the current position
+    pub(crate) fn lexeme(&self) -> &'a str {
+        &self.source[self.start_offset..self.position.offset]
+    }
+
+    // Where `range` of the source is in the current token's lexeme
+    pub(crate) fn lexeme_range(&self, range: Range<usize>) -> Range<usize> {
+        range.start - self.start_offset..range.end - self.start_offset
+    }
+
+    pub(crate) fn end_token(&mut self, token_type: TokenType) -> Token<'a> {
         Token {
+            span: self.current_span(),
             token_type,
-            lexeme: self.current_lexeme.clone(),
+            lexeme: self.lexeme(),
             line: self.position.line,
             column: self.position.column,
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
         }
     }
 
     pub(crate) fn record_error(&mut self, error_type: LexErrorType) {
+        self.record_error_from(error_type, self.start_offset);
+    }
+
+    // Records an error spanning from `start` to the current position, for
+    // errors in part of a token
+    pub(crate) fn record_error_from(&mut self, error_type: LexErrorType, start: usize) {
         let line = self.position.line;
         let column = self.position.column;
         self.errors.push(LexError {
             error_type,
-            line: line,
-            column: column,
-            snippet: self
-                .source
-                .lines()
-                .nth(line - 1)
-                .unwrap_or_default()
-                .to_string(),
+            line,
+            column,
+            span: Span::new(self.file, start, self.position.offset),
+        });
+    }
+
+    // Records an error on the first `len` bytes of the current token, for
+    // errors best pointed at where the token starts
+    pub(crate) fn record_error_at_start(&mut self, error_type: LexErrorType, len: usize) {
+        let end = self.start_offset + len;
+        self.errors.push(LexError {
+            error_type,
+            line: self.start_line,
+            column: self.start_column + self.source[self.start_offset..end].chars().count(),
+            span: Span::new(self.file, self.start_offset, end),
         });
     }
 
+    // Span from the start of the current token to the current position
+    pub(crate) fn current_span(&self) -> Span {
+        Span::new(self.file, self.start_offset, self.position.offset)
+    }
+
     pub(crate) fn skip_whitespace(&mut self) {
         while let Some(c) = self.peek() {
             if c.is_whitespace() {
@@ -180,17 +304,6 @@ impl<'a> Scanner<'a> {
         self.scan_type(|c| c.is_alphanumeric() || c == '_');
     }
 
-    pub(crate) fn scan_number(&mut self) {
-        self.scan_type(|c| c.is_digit(10) || c == '.');
-
-        let lexeme = &self.current_lexeme;
-        if lexeme.matches('.').count() > 1 {
-            self.record_error(LexErrorType::TooManyDecimalPoints);
-        } else if lexeme.ends_with('.') {
-            self.record_error(LexErrorType::TrailingDecimalPoint);
-        }
-    }
-
     pub(crate) fn scan_type<F>(&mut self, f: F)
     where
         F: Fn(char) -> bool,
@@ -205,14 +318,27 @@ impl<'a> Scanner<'a> {
     }
 
     pub(crate) fn identify_keyword(&self) -> TokenType {
-        match self.current_lexeme.as_str() {
-            "let" => TokenType::Let,
-            "if" => TokenType::If,
-            "for" => TokenType::For,
-            "while" => TokenType::While,
-            "loop" => TokenType::Loop,
-            "fn" => TokenType::Function,
-            _ => TokenType::Identifier(self.current_lexeme.clone()),
+        let lexeme = self.lexeme();
+        KEYWORDS
+            .iter()
+            .find(|(keyword, _)| *keyword == lexeme)
+            .map(|(_, token_type)| token_type.clone())
+            .unwrap_or_else(|| TokenType::Identifier(Symbol::intern(lexeme)))
+    }
+}
+
+/// Scans lazily, a token per call, ending with `Eof`. Errors and comments
+/// are recorded on the scanner as scanning reaches them.
+impl<'a> Iterator for Scanner<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        if self.finished {
+            return None;
         }
+
+        let token = self.next_token();
+        self.finished = token.token_type == TokenType::Eof;
+        Some(token)
     }
 }
@@ -0,0 +1,143 @@
+use apl_source::Span;
+
+use super::{Scanner, TokenType};
+
+/// What a piece of source is, for syntax highlighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Keyword,
+    Operator,
+    Number,
+    String,
+    Comment,
+    Function,
+    Variable,
+}
+
+impl Category {
+    pub const ALL: [Category; 7] = [
+        Category::Keyword,
+        Category::Operator,
+        Category::Number,
+        Category::String,
+        Category::Comment,
+        Category::Function,
+        Category::Variable,
+    ];
+
+    /// The name of the category, matching the LSP's standard token types.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Category::Keyword => "keyword",
+            Category::Operator => "operator",
+            Category::Number => "number",
+            Category::String => "string",
+            Category::Comment => "comment",
+            Category::Function => "function",
+            Category::Variable => "variable",
+        }
+    }
+}
+
+impl TokenType {
+    /// The category of a token on its own, identifiers are variables until
+    /// their context says otherwise (see [`highlight`]).
+    pub fn category(&self) -> Option<Category> {
+        let category = match self {
+            TokenType::Let
+            | TokenType::If
+            | TokenType::Else
+            | TokenType::For
+            | TokenType::While
+            | TokenType::Loop
+            | TokenType::In
+            | TokenType::Function
+            | TokenType::Pub
+            | TokenType::Import
+            | TokenType::As
+            | TokenType::Use
+            | TokenType::Return
+            | TokenType::Break
+            | TokenType::Continue => Category::Keyword,
+
+            TokenType::Identifier(_) => Category::Variable,
+            TokenType::Number(_) => Category::Number,
+            TokenType::String(_)
+            | TokenType::StringStart(_)
+            | TokenType::StringMiddle(_)
+            | TokenType::StringEnd(_)
+            | TokenType::Char(_) => Category::String,
+
+            // Punctuation counts as an operator
+            TokenType::Equals
+            | TokenType::EqualsEquals
+            | TokenType::BangEquals
+            | TokenType::Bang
+            | TokenType::Plus
+            | TokenType::Minus
+            | TokenType::Multiply
+            | TokenType::Divide
+            | TokenType::Modulo
+            | TokenType::LessThan
+            | TokenType::LessThanOrEqual
+            | TokenType::GreaterThan
+            | TokenType::GreaterThanOrEqual
+            | TokenType::Range
+            | TokenType::CurlyOpen
+            | TokenType::CurlyClose
+            | TokenType::BracketOpen
+            | TokenType::BracketClose
+            | TokenType::ParenthesesOpen
+            | TokenType::ParenthesesClose
+            | TokenType::SemiColon
+            | TokenType::Dot
+            | TokenType::Colon
+            | TokenType::Comma => Category::Operator,
+
+            TokenType::Eof => return None,
+        };
+        Some(category)
+    }
+}
+
+/// Categorizes the tokens and comments of `source` in source order.
+/// Identifiers that are declared or called as functions are functions.
+pub fn highlight(source: &str) -> Vec<(Span, Category)> {
+    if source.is_empty() {
+        return Vec::new();
+    }
+
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+
+    let mut highlights: Vec<(Span, Category)> = tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, token)| {
+            let category = match token.token_type.category()? {
+                Category::Variable => {
+                    let declared = i > 0 && tokens[i - 1].token_type == TokenType::Function;
+                    let called = tokens
+                        .get(i + 1)
+                        .is_some_and(|next| next.token_type == TokenType::ParenthesesOpen);
+                    if declared || called {
+                        Category::Function
+                    } else {
+                        Category::Variable
+                    }
+                }
+                category => category,
+            };
+            Some((token.span, category))
+        })
+        .collect();
+
+    highlights.extend(
+        scanner
+            .comments
+            .iter()
+            .map(|&span| (span, Category::Comment)),
+    );
+    highlights.sort_by_key(|(span, _)| span.start);
+    highlights
+}
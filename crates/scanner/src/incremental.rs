@@ -0,0 +1,200 @@
+use std::ops::Range;
+
+use apl_error::LexError;
+use apl_source::{FileId, Span};
+
+use super::{ScannedToken, Scanner, Token, TokenType};
+
+/// The tokens, errors and comments of a whole source, kept so an edit only
+/// needs the tokens around it scanned again. The tokens are kept without
+/// their lexemes, as the source they were scanned from changes with every
+/// edit, and read back from the current source.
+#[derive(Debug, Clone)]
+pub struct Scan {
+    pub tokens: Vec<ScannedToken>,
+    pub errors: Vec<LexError>,
+    pub comments: Vec<Span>,
+    pub file: FileId,
+}
+
+/// Which tokens an edit replaced: `removed` tokens from `start` made way for
+/// `inserted` new ones. The tokens after them are unchanged apart from their
+/// position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenChange {
+    pub start: usize,
+    pub removed: usize,
+    pub inserted: usize,
+}
+
+impl Scan {
+    pub fn new(source: &str, file: FileId) -> Self {
+        let mut scanner = Scanner::resume(source, file, 0, 1, 1);
+        let tokens = scanner.by_ref().map(ScannedToken::from).collect();
+
+        Self {
+            tokens,
+            errors: scanner.errors,
+            comments: scanner.comments,
+            file,
+        }
+    }
+
+    /// Updates the scan after `range` of the old source was replaced with
+    /// `inserted` bytes, `source` being the text after the edit.
+    ///
+    /// Scanning restarts after the last token ending before the edit, and
+    /// stops at the first token after it that the old scan also had at the
+    /// same place, so the cost depends on the size of the edit rather than the
+    /// size of the source.
+    pub fn edit(&mut self, source: &str, range: Range<usize>, inserted: usize) -> TokenChange {
+        let delta = inserted as isize - range.len() as isize;
+        let edit_end = range.start + inserted;
+
+        // A token ending right where the edit starts may continue into it,
+        // and the scanner can't resume inside an interpolated string
+        let outside = outside_strings(&self.tokens);
+        let mut start = self
+            .tokens
+            .partition_point(|token| token.span.end < range.start);
+        while !outside[start] {
+            start -= 1;
+        }
+        let mut scanner = match start.checked_sub(1).map(|i| &self.tokens[i]) {
+            Some(token) => {
+                Scanner::resume(source, self.file, token.span.end, token.line, token.column)
+            }
+            None => Scanner::resume(source, self.file, 0, 1, 1),
+        };
+        let restart = start.checked_sub(1).map_or(0, |i| self.tokens[i].span.end);
+
+        let mut scanned = Vec::new();
+        let resync = loop {
+            let resumable = scanner.interpolations.is_empty();
+            let token = scanner.next_token();
+            if token.token_type == TokenType::Eof {
+                scanned.push(token.into());
+                break None;
+            }
+            if token.span.start >= edit_end
+                && resumable
+                && let Some(old) = self.unchanged(&token, start, range.end, delta)
+                && outside[old]
+            {
+                break Some((old, token));
+            }
+            scanned.push(token.into());
+        };
+
+        let end = resync.as_ref().map_or(self.tokens.len(), |(old, _)| *old);
+        // Where the old and new scans agree again
+        let (old_resume, new_resume) = resync
+            .as_ref()
+            .map_or((usize::MAX, usize::MAX), |(old, token)| {
+                (self.tokens[*old].span.start, token.span.start)
+            });
+
+        let change = TokenChange {
+            start,
+            removed: end - start,
+            inserted: scanned.len(),
+        };
+
+        // Errors and comments from the tokens scanned again are replaced by
+        // the new ones
+        let rescanned = |offset: usize| restart <= offset && offset < old_resume;
+        self.errors.retain(|error| !rescanned(error.span.start));
+        self.comments.retain(|comment| !rescanned(comment.start));
+
+        // Everything after where the scans agree moved by the size of the
+        // edit, and the rest of that line moved along it too
+        if let Some((old, token)) = resync {
+            let old = &self.tokens[old];
+            let (line, lines, columns) = (
+                old.line,
+                token.line as isize - old.line as isize,
+                token.column as isize - old.column as isize,
+            );
+            let moved = |at_line: &mut usize, column: &mut usize| {
+                if *at_line == line {
+                    *column = column.wrapping_add_signed(columns);
+                }
+                *at_line = at_line.wrapping_add_signed(lines);
+            };
+
+            for token in &mut self.tokens[end..] {
+                moved(&mut token.line, &mut token.column);
+                token.span = token.span.shifted(delta);
+            }
+            for error in self
+                .errors
+                .iter_mut()
+                .filter(|e| e.span.start >= old_resume)
+            {
+                moved(&mut error.line, &mut error.column);
+                error.span = error.span.shifted(delta);
+            }
+            for comment in self.comments.iter_mut().filter(|c| c.start >= old_resume) {
+                *comment = comment.shifted(delta);
+            }
+        }
+
+        let at = self.errors.partition_point(|e| e.span.start < restart);
+        let errors = scanner.errors.into_iter();
+        self.errors
+            .splice(at..at, errors.filter(|e| e.span.start < new_resume));
+        let at = self.comments.partition_point(|c| c.start < restart);
+        let comments = scanner.comments.into_iter();
+        self.comments
+            .splice(at..at, comments.filter(|c| c.start < new_resume));
+        self.tokens.splice(start..end, scanned);
+
+        change
+    }
+
+    // The old token that `token`, scanned after the edit, is an unchanged
+    // copy of. The source after the edit is the same as before it, so a
+    // token of the same type and length there has the same lexeme. Tokens
+    // spanning lines aren't used, so the columns of everything after them
+    // can be moved by the same amount.
+    fn unchanged(
+        &self,
+        token: &Token,
+        start: usize,
+        edit_end: usize,
+        delta: isize,
+    ) -> Option<usize> {
+        if token.lexeme.contains('\n') {
+            return None;
+        }
+
+        let old_start = token.span.start.wrapping_add_signed(-delta);
+        let index = self.tokens[start..]
+            .binary_search_by_key(&old_start, |old| old.span.start)
+            .ok()?
+            + start;
+        let old = &self.tokens[index];
+
+        (old_start >= edit_end
+            && old.token_type == token.token_type
+            && old.span.len() == token.span.len())
+        .then_some(index)
+    }
+}
+
+// Whether each token, and the end after the last one, is outside of any
+// interpolated string, where scanning can resume without knowing about them
+fn outside_strings(tokens: &[ScannedToken]) -> Vec<bool> {
+    let mut depth = 0usize;
+    let mut outside = Vec::with_capacity(tokens.len() + 1);
+    for token in tokens {
+        outside.push(depth == 0);
+        match token.token_type {
+            TokenType::StringStart(_) => depth += 1,
+            TokenType::StringEnd(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    outside.push(depth == 0);
+    outside
+}
@@ -0,0 +1,40 @@
+use apl_error::lexerror::LexErrorType;
+
+use crate::{Scanner, TokenType};
+
+/// Whether `source` stops inside an unclosed bracket, string literal or block
+/// comment, in which case an interactive prompt should read another line
+/// before parsing.
+pub fn is_incomplete(source: &str) -> bool {
+    if source.trim().is_empty() {
+        return false;
+    }
+
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens();
+
+    if scanner.errors.iter().any(|e| {
+        matches!(
+            e.error_type,
+            LexErrorType::UnterminatedString | LexErrorType::UnterminatedBlockComment
+        )
+    }) {
+        return true;
+    }
+
+    let mut depth = 0;
+    for token in &tokens {
+        match token.token_type {
+            TokenType::CurlyOpen | TokenType::BracketOpen | TokenType::ParenthesesOpen => {
+                depth += 1
+            }
+            TokenType::CurlyClose | TokenType::BracketClose | TokenType::ParenthesesClose => {
+                depth -= 1
+            }
+            _ => {}
+        }
+    }
+
+    // Too many closing brackets is an error the parser will report
+    depth > 0
+}
@@ -1,25 +1,47 @@
 mod core;
+mod highlight;
+mod incremental;
+mod input;
 mod modes;
+mod number;
 mod position;
 mod tokens;
+mod trivia;
 
-pub use core::Scanner;
+pub use core::{KEYWORDS, Scanner};
+pub use highlight::{Category, highlight};
+pub use incremental::{Scan, TokenChange};
+pub use input::is_incomplete;
 use core::ScannerMode;
 use position::Position;
-pub use tokens::{Token, TokenType};
+pub use tokens::{Number, ScannedToken, Text, Token, TokenType, Trivia, TriviaKind};
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use apl_error::lexerror::*;
+    use num_bigint::BigUint;
 
     // Helper to get errors from scanning
-    fn scan_with_errors(source: &str) -> (Vec<Token>, Vec<LexError>) {
+    fn scan_with_errors(source: &str) -> (Vec<Token<'_>>, Vec<LexError>) {
         let mut scanner = Scanner::new(source);
         let tokens = scanner.scan_tokens();
         (tokens, scanner.errors)
     }
 
+    // A token's type with the text of a string in it, to compare with the
+    // types expected
+    fn with_text(token: &Token) -> TokenType {
+        let text = token.text().map(Text::from);
+        match (&token.token_type, text) {
+            (TokenType::String(_), Some(text)) => TokenType::String(text),
+            (TokenType::StringStart(_), Some(text)) => TokenType::StringStart(text),
+            (TokenType::StringMiddle(_), Some(text)) => TokenType::StringMiddle(text),
+            (TokenType::StringEnd(_), Some(text)) => TokenType::StringEnd(text),
+            (other, _) => other.clone(),
+        }
+    }
+
     // Helper to assert specific errors
     fn assert_has_error(
         errors: &[LexError],
@@ -52,9 +74,9 @@ mod tests {
             tokens,
             vec![
                 TokenType::Let,
-                TokenType::Identifier("a".to_string()),
+                TokenType::Identifier("a".into()),
                 TokenType::Equals,
-                TokenType::Number("10".to_string()),
+                TokenType::Number(Number::Integer(10)),
                 TokenType::SemiColon,
                 TokenType::Eof,
             ]
@@ -74,9 +96,9 @@ mod tests {
             tokens,
             vec![
                 TokenType::Let,
-                TokenType::Identifier("a".to_string()),
+                TokenType::Identifier("a".into()),
                 TokenType::Equals,
-                TokenType::Number("10.0".to_string()),
+                TokenType::Number(Number::Float(10.0)),
                 TokenType::SemiColon,
                 TokenType::Eof,
             ]
@@ -87,24 +109,43 @@ mod tests {
     fn scan_variable_declaration_string_literal() {
         let mut scanner = Scanner::new(r#"let a = "test";"#);
 
-        let tokens: Vec<TokenType> = scanner
-            .scan_tokens()
-            .iter()
-            .map(|token| token.token_type.clone())
-            .collect();
+        let tokens: Vec<TokenType> = scanner.scan_tokens().iter().map(with_text).collect();
         assert_eq!(
             tokens,
             vec![
                 TokenType::Let,
-                TokenType::Identifier("a".to_string()),
+                TokenType::Identifier("a".into()),
                 TokenType::Equals,
-                TokenType::String("test".to_string()),
+                TokenType::String("test".into()),
                 TokenType::SemiColon,
                 TokenType::Eof,
             ]
         )
     }
 
+    #[test]
+    fn borrowed_lexemes() {
+        let source = r#"name "plain" "esc\"aped" 12"#;
+        let tokens = Scanner::new(source).scan_tokens();
+
+        assert_eq!(tokens[1].lexeme, "\"plain\"");
+        assert!(std::ptr::eq(tokens[1].lexeme, &source[5..12]));
+        // Names are interned instead
+        assert_eq!(
+            tokens[0].token_type,
+            TokenType::Identifier(apl_source::Symbol::intern("name"))
+        );
+        assert_eq!(tokens[1].token_type, TokenType::String(Text::Lexeme(1..6)));
+        assert_eq!(tokens[1].text(), Some("plain"));
+        // Only a value that differs from its lexeme is copied
+        assert_eq!(tokens[2].token_type, TokenType::String("esc\"aped".into()));
+        assert_eq!(tokens[2].text(), Some("esc\"aped"));
+
+        // Kept without the lexeme, which is sliced from the source again
+        let kept = ScannedToken::from(tokens[1].clone());
+        assert_eq!(kept.token(source), tokens[1]);
+    }
+
     #[test]
     fn unexpected_character_error() {
         let source = "let a = @;";
@@ -115,7 +156,7 @@ mod tests {
             tokens.iter().map(|t| &t.token_type).collect::<Vec<_>>(),
             vec![
                 &TokenType::Let,
-                &TokenType::Identifier("a".to_string()),
+                &TokenType::Identifier("a".into()),
                 &TokenType::Equals,
                 &TokenType::SemiColon,
                 &TokenType::Eof,
@@ -133,6 +174,24 @@ mod tests {
         assert_has_error(&errors1, LexErrorType::TooManyDecimalPoints, 1, 10);
     }
 
+    #[test]
+    fn number_before_range() {
+        let (tokens, errors) = scan_with_errors("for i in 1..3 {}");
+        assert!(errors.is_empty(), "{:?}", errors);
+        let types: Vec<_> = tokens.iter().map(|token| &token.token_type).collect();
+        assert_eq!(
+            types[3..6],
+            [
+                &TokenType::Number(Number::Integer(1)),
+                &TokenType::Range,
+                &TokenType::Number(Number::Integer(3)),
+            ]
+        );
+
+        let (_, errors) = scan_with_errors("1.5..2.5");
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
     #[test]
     fn number_format_errors_trailing_decimal_points() {
         // Test trailing decimal
@@ -140,6 +199,58 @@ mod tests {
         assert_has_error(&errors2, LexErrorType::TrailingDecimalPoint, 1, 4);
     }
 
+    #[test]
+    fn number_literals() {
+        let value = |source: &str| {
+            let (tokens, errors) = scan_with_errors(source);
+            assert!(errors.is_empty(), "{:?}", errors);
+            assert_eq!(tokens.len(), 2, "{:?}", tokens);
+            match &tokens[0].token_type {
+                TokenType::Number(value) => value.clone(),
+                other => panic!("expected a number, got {:?}", other),
+            }
+        };
+
+        assert_eq!(value("0xFF"), Number::Integer(255));
+        assert_eq!(value("0o17"), Number::Integer(15));
+        assert_eq!(value("0b1010"), Number::Integer(10));
+        assert_eq!(value("1_000_000"), Number::Integer(1_000_000));
+        assert_eq!(value("1.5e-3"), Number::Float(1.5e-3));
+        assert_eq!(value("2E+2"), Number::Float(200.0));
+        assert_eq!(value("0x_dead_beef"), Number::Integer(0xdead_beef));
+        assert_eq!(value("18446744073709551615"), Number::Integer(u64::MAX));
+        assert_eq!(
+            value("1_000.10d"),
+            Number::Decimal {
+                mantissa: BigUint::from(100_010u32),
+                scale: 2
+            }
+        );
+        assert_eq!(
+            value("0x1_0000_0000_0000_0000"),
+            Number::BigInteger(BigUint::from(u64::MAX) + 1u32)
+        );
+    }
+
+    #[test]
+    fn number_format_errors() {
+        let (_, errors) = scan_with_errors("0b1012;");
+        assert_has_error(&errors, LexErrorType::InvalidDigit('2', 2), 1, 7);
+        let (_, errors) = scan_with_errors("0o8");
+        assert_has_error(&errors, LexErrorType::InvalidDigit('8', 8), 1, 4);
+        let (_, errors) = scan_with_errors("0xFG");
+        assert_has_error(&errors, LexErrorType::InvalidDigit('G', 16), 1, 5);
+        let (_, errors) = scan_with_errors("0x;");
+        assert_has_error(&errors, LexErrorType::MalformedNumber, 1, 3);
+
+        let (_, errors) = scan_with_errors("1.5e;");
+        assert_has_error(&errors, LexErrorType::EmptyExponent, 1, 5);
+        let (_, errors) = scan_with_errors("1e3d");
+        assert_has_error(&errors, LexErrorType::MalformedNumber, 1, 5);
+        let (_, errors) = scan_with_errors("1e-_");
+        assert_has_error(&errors, LexErrorType::EmptyExponent, 1, 5);
+    }
+
     #[test]
     fn string_literal_errors() {
         // Unterminated string
@@ -147,14 +258,188 @@ mod tests {
 
         assert_eq!(
             // Takes second last token as last token is Eof
-            tokens.iter().nth(tokens.len() - 2).unwrap().token_type,
-            TokenType::String("unterminated".to_string())
+            with_text(&tokens[tokens.len() - 2]),
+            TokenType::String("unterminated".into())
         );
         assert_has_error(&errors, LexErrorType::UnterminatedString, 1, 22);
 
         // Invalid escape sequence
-        let (_, errors) = scan_with_errors(r#""invalid \x escape""#);
-        assert_has_error(&errors, LexErrorType::InvalidEscape('x'), 1, 12);
+        let (_, errors) = scan_with_errors(r#""invalid \q escape""#);
+        assert_has_error(&errors, LexErrorType::InvalidEscape('q'), 1, 12);
+    }
+
+    #[test]
+    fn raw_strings() {
+        let source = r##"r"C:\files\" r#"say "hi" \n"# r"" r"##;
+        let (tokens, errors) = scan_with_errors(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let types: Vec<_> = tokens.iter().map(with_text).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::String(r"C:\files\".into()),
+                TokenType::String(r#"say "hi" \n"#.into()),
+                TokenType::String("".into()),
+                TokenType::Identifier(apl_source::Symbol::intern("r")),
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(tokens[1].lexeme, r###"r#"say "hi" \n"#"###);
+
+        let (tokens, errors) = scan_with_errors(r##"r#"open" "##);
+        assert_has_error(&errors, LexErrorType::UnterminatedString, 1, 10);
+        assert_eq!(tokens[0].text(), Some(r#"open" "#));
+    }
+
+    #[test]
+    fn multiline_strings() {
+        let value = |source: &str| {
+            let (tokens, errors) = scan_with_errors(source);
+            assert!(errors.is_empty(), "{:?}", errors);
+            match tokens[0].text() {
+                Some(value) => value.to_string(),
+                None => panic!("expected a string, got {:?}", tokens[0].token_type),
+            }
+        };
+
+        // The closing line's indentation is stripped from every line
+        let source = "\"\"\"\n    {\n      \"a\": 1\n    }\n    \"\"\"";
+        assert_eq!(value(source), "{\n  \"a\": 1\n}");
+
+        // Blank lines don't count towards the common indentation
+        let source = "\"\"\"\n    one\n\n      two\n  \"\"\"";
+        assert_eq!(value(source), "  one\n\n    two");
+
+        // Closing quotes after text keep the last line
+        let source = "\"\"\"\n  one\n  two\"\"\"";
+        assert_eq!(value(source), "one\ntwo");
+
+        // Escapes are processed after stripping, `\"` doesn't close
+        let source = "\"\"\"\n  \\t\\\"\"\"\n  \"\"\"";
+        assert_eq!(value(source), "\t\"\"\"");
+
+        // A `\` at the end of a line continues it on the next
+        let source = "\"\"\"\n  one \\\n    two \\\r\n three\n  \"\"\"";
+        assert_eq!(value(source), "one two three");
+
+        assert_eq!(value("\"\"\"one line\"\"\""), "one line");
+        assert_eq!(value("\"\"\"\r\n  a\r\n  \"\"\""), "a");
+
+        let (tokens, errors) = scan_with_errors("let s = \"\"\"\n  a \"\";\nprint(s);");
+        assert_has_error(&errors, LexErrorType::UnterminatedString, 3, 11);
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[4].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn interpolation() {
+        let source = r#""Hello ${name}, you have ${n + 1} items" "${ {} }${"${a}"}" "\${}""#;
+        let (tokens, errors) = scan_with_errors(source);
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let ident = |name: &str| TokenType::Identifier(apl_source::Symbol::intern(name));
+        let types: Vec<_> = tokens.iter().map(with_text).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::StringStart("Hello ".into()),
+                ident("name"),
+                TokenType::StringMiddle(", you have ".into()),
+                ident("n"),
+                TokenType::Plus,
+                TokenType::Number(Number::Integer(1)),
+                TokenType::StringEnd(" items".into()),
+                // Braces inside an interpolation are tokens of their own
+                TokenType::StringStart("".into()),
+                TokenType::CurlyOpen,
+                TokenType::CurlyClose,
+                TokenType::StringMiddle("".into()),
+                TokenType::StringStart("".into()),
+                ident("a"),
+                TokenType::StringEnd("".into()),
+                TokenType::StringEnd("".into()),
+                TokenType::String("${}".into()),
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(tokens[0].lexeme, "\"Hello ${");
+        assert_eq!(tokens[2].lexeme, "}, you have ${");
+        assert_eq!(tokens[6].lexeme, "} items\"");
+
+        let (tokens, errors) = scan_with_errors(r#"print("a ${b"#);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].error_type, LexErrorType::UnterminatedString);
+        assert_eq!(tokens.last().unwrap().token_type, TokenType::Eof);
+        assert!(is_incomplete(r#"print("a ${b"#));
+    }
+
+    #[test]
+    fn tokens_display_their_text() {
+        let shown: Vec<String> = Scanner::new(r#""a\tb" "x ${y} z" 1"#)
+            .map(|token| token.to_string())
+            .collect();
+        assert_eq!(
+            shown,
+            [
+                r#"String("a\tb")"#,
+                r#"StringStart("x ")"#,
+                "Identifier(\"y\")",
+                r#"StringEnd(" z")"#,
+                "Number(Integer(1))",
+                "Eof",
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        let (tokens, errors) =
+            scan_with_errors(r#""\r\0\x41\u{1F600}\u{e9}\'\"" '\u{263A}' '\x7e' '\"'"#);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let values: Vec<_> = tokens.iter().map(with_text).collect();
+        assert_eq!(
+            values,
+            vec![
+                TokenType::String("\r\0A\u{1F600}\u{e9}'\"".into()),
+                TokenType::Char('\u{263A}'),
+                TokenType::Char('~'),
+                TokenType::Char('"'),
+                TokenType::Eof,
+            ]
+        );
+
+        // Errors span just the escape
+        fn error(source: &str) -> (LexErrorType, &str) {
+            let (_, errors) = scan_with_errors(source);
+            assert_eq!(errors.len(), 1, "{:?}", errors);
+            let error = &errors[0];
+            (
+                error.error_type.clone(),
+                &source[error.span.start..error.span.end],
+            )
+        }
+        assert_eq!(
+            error(r#""a\x4g""#),
+            (LexErrorType::InvalidHexEscape, r"\x4")
+        );
+        assert_eq!(
+            error(r#""a\u41 b""#),
+            (LexErrorType::InvalidUnicodeEscape, r"\u")
+        );
+        assert_eq!(
+            error(r#""\u{1234567}""#),
+            (LexErrorType::InvalidUnicodeEscape, r"\u{1234567")
+        );
+        assert_eq!(
+            error(r#"'\u{110000}'"#),
+            (LexErrorType::CodePointOutOfRange(0x110000), r"\u{110000}")
+        );
+        assert_eq!(
+            error(r#""ok \u{D83D}""#),
+            (LexErrorType::SurrogateCodePoint(0xD83D), r"\u{D83D}")
+        );
+        assert_eq!(error(r#""\q""#), (LexErrorType::InvalidEscape('q'), r"\q"));
     }
 
     #[test]
@@ -166,7 +451,7 @@ mod tests {
             vec![
                 &TokenType::If,
                 &TokenType::Bang,
-                &TokenType::Identifier("true".to_string()),
+                &TokenType::Identifier("true".into()),
                 &TokenType::Eof,
             ]
         );
@@ -189,34 +474,44 @@ let y = "unclosde"#;
     }
 
     #[test]
-    fn char_literals() {
-        let mut scanner = Scanner::new("'a'");
-        assert_eq!(
-            scanner.scan_char_literal().unwrap().token_type,
-            TokenType::Char('a')
-        );
+    fn token_spans() {
+        let source = "let ø = \"hi\";\n  x";
+        let tokens = Scanner::new(source).scan_tokens();
+
+        let lexemes: Vec<&str> = tokens
+            .iter()
+            .map(|t| &source[t.span.start..t.span.end])
+            .collect();
+        assert_eq!(lexemes, vec!["let", "ø", "=", "\"hi\"", ";", "x", ""]);
+
+        let (_, errors) = scan_with_errors("a @");
+        assert_eq!((errors[0].span.start, errors[0].span.end), (2, 3));
+    }
 
-        let mut scanner = Scanner::new("'\\n'");
+    #[test]
+    fn char_literals() {
+        let (tokens, _) = scan_with_errors("'a' '\\n' 'b'");
         assert_eq!(
-            scanner.scan_char_literal().unwrap().token_type,
-            TokenType::Char('\n')
+            tokens.iter().map(|t| &t.token_type).collect::<Vec<_>>(),
+            vec![
+                &TokenType::Char('a'),
+                &TokenType::Char('\n'),
+                &TokenType::Char('b'),
+                &TokenType::Eof
+            ]
         );
 
         // Error cases
-        let mut scanner = Scanner::new("''");
-        scanner.scan_char_literal();
+        let (_, errors) = scan_with_errors("''");
         assert!(
-            scanner
-                .errors
+            errors
                 .iter()
                 .any(|e| matches!(e.error_type, LexErrorType::EmptyCharLiteral))
         );
 
-        let mut scanner = Scanner::new("'ab'");
-        scanner.scan_char_literal();
+        let (_, errors) = scan_with_errors("'ab'");
         assert!(
-            scanner
-                .errors
+            errors
                 .iter()
                 .any(|e| matches!(e.error_type, LexErrorType::TooManyChars))
         );
@@ -235,20 +530,21 @@ let y = "unclosde"#;
             tokens,
             vec![
                 TokenType::Let,
-                TokenType::Identifier("a".to_string()),
+                TokenType::Identifier("a".into()),
                 TokenType::Equals,
                 TokenType::BracketOpen,
-                TokenType::Number("1".to_string()),
+                TokenType::Number(Number::Integer(1)),
                 TokenType::Comma,
-                TokenType::Number("2".to_string()),
+                TokenType::Number(Number::Integer(2)),
                 TokenType::Comma,
-                TokenType::Number("3".to_string()),
+                TokenType::Number(Number::Integer(3)),
                 TokenType::BracketClose,
                 TokenType::Eof,
             ]
         )
     }
 
+    #[test]
     fn object_function_call() {
         let mut scanner = Scanner::new("obj.func1()");
         let tokens: Vec<TokenType> = scanner
@@ -260,12 +556,222 @@ let y = "unclosde"#;
         assert_eq!(
             tokens,
             vec![
-                TokenType::Identifier("obj".to_string()),
+                TokenType::Identifier("obj".into()),
                 TokenType::Dot,
-                TokenType::Identifier("func1".to_string()),
+                TokenType::Identifier("func1".into()),
                 TokenType::ParenthesesOpen,
                 TokenType::ParenthesesClose,
+                TokenType::Eof,
             ]
         )
     }
+
+    #[test]
+    fn incomplete_input() {
+        assert!(is_incomplete("fn f() {"));
+        assert!(is_incomplete("print((1,\n 2)"));
+        assert!(is_incomplete("let s = \"abc"));
+        assert!(is_incomplete("if a { [1, 2"));
+
+        assert!(!is_incomplete(""));
+        assert!(!is_incomplete("fn f() {}"));
+        assert!(!is_incomplete("let s = \"{\";"));
+        assert!(!is_incomplete("print(1));"));
+        assert!(is_incomplete("let s = \"\"\"\n  abc"));
+        assert!(is_incomplete("let s = r#\"abc\""));
+    }
+
+    #[test]
+    fn comments() {
+        let source = "a // one\n/* two\n */ b //";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+
+        assert_eq!(
+            tokens.iter().map(|t| &t.token_type).collect::<Vec<_>>(),
+            vec![
+                &TokenType::Identifier("a".into()),
+                &TokenType::Identifier("b".into()),
+                &TokenType::Eof
+            ]
+        );
+
+        let comments: Vec<&str> = scanner
+            .comments
+            .iter()
+            .map(|span| &source[span.start..span.end])
+            .collect();
+        assert_eq!(comments, vec!["// one", "/* two\n */", "//"]);
+    }
+
+    #[test]
+    fn nested_block_comments() {
+        let source = "a /* one /* two */ three */ b /*/ four **/ c";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens();
+        assert!(scanner.errors.is_empty(), "{:?}", scanner.errors);
+        assert_eq!(
+            tokens.iter().map(|t| &t.token_type).collect::<Vec<_>>(),
+            vec![
+                &TokenType::Identifier("a".into()),
+                &TokenType::Identifier("b".into()),
+                &TokenType::Identifier("c".into()),
+                &TokenType::Eof
+            ]
+        );
+        let comments: Vec<&str> = scanner
+            .comments
+            .iter()
+            .map(|span| &source[span.start..span.end])
+            .collect();
+        assert_eq!(comments, vec!["/* one /* two */ three */", "/*/ four **/"]);
+
+        // Unterminated comments point at their opening `/*`
+        for source in ["let a;\n  /* one /* two */ b", "a /*"] {
+            let (tokens, errors) = scan_with_errors(source);
+            assert_eq!(errors.len(), 1, "{:?}", errors);
+            let start = source.find("/*").unwrap();
+            assert_eq!(errors[0].error_type, LexErrorType::UnterminatedBlockComment);
+            assert_eq!(
+                (errors[0].span.start, errors[0].span.end),
+                (start, start + 2)
+            );
+            assert_eq!(tokens.last().unwrap().token_type, TokenType::Eof);
+        }
+        assert_has_error(
+            &scan_with_errors("let a;\n  /* one /* two */ b").1,
+            LexErrorType::UnterminatedBlockComment,
+            2,
+            6,
+        );
+        assert!(is_incomplete("/* one /* two */"));
+    }
+
+    #[test]
+    fn lossless_trivia() {
+        let sources = [
+            "let a = 1; // one\n\n/* two\n */ fn f() {\n    a @ b\n}\n",
+            "\t x /* trailing */\r\n// at the end",
+            "let s = \"unterminated\n",
+        ];
+        for source in sources {
+            let tokens = Scanner::new(source).scan_tokens_with_trivia();
+            let text: String = tokens.iter().map(Token::full_text).collect();
+            assert_eq!(text, source);
+        }
+
+        let tokens = Scanner::new("a = 1; // one\n  b").scan_tokens_with_trivia();
+        let kinds = |trivia: &[Trivia]| trivia.iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds(&tokens[3].trailing_trivia),
+            vec![TriviaKind::Whitespace, TriviaKind::LineComment]
+        );
+        assert_eq!(tokens[4].leading_trivia[0].text, "\n  ");
+
+        // Plain scanning leaves the trivia empty
+        let tokens = Scanner::new("a // one").scan_tokens();
+        assert!(tokens[0].trailing_trivia.is_empty());
+    }
+
+    #[test]
+    fn highlight_categories() {
+        let source = "fn f(a) { return a + 1; } // done\nlet s = f('c');";
+        let highlights: Vec<(&str, Category)> = highlight(source)
+            .into_iter()
+            .map(|(span, category)| (&source[span.start..span.end], category))
+            .collect();
+
+        use Category::*;
+        assert_eq!(
+            highlights,
+            vec![
+                ("fn", Keyword),
+                ("f", Function),
+                ("(", Operator),
+                ("a", Variable),
+                (")", Operator),
+                ("{", Operator),
+                ("return", Keyword),
+                ("a", Variable),
+                ("+", Operator),
+                ("1", Number),
+                (";", Operator),
+                ("}", Operator),
+                ("// done", Comment),
+                ("let", Keyword),
+                ("s", Variable),
+                ("=", Operator),
+                ("f", Function),
+                ("(", Operator),
+                ("'c'", String),
+                (")", Operator),
+                (";", Operator),
+            ]
+        );
+        assert!(highlight("").is_empty());
+    }
+
+    #[test]
+    fn rescan_after_edit() {
+        let mut source = "let a = 1;\n/* note */ let b = a;\nlet c = \"s\";".to_string();
+        let mut scan = Scan::new(&source, Default::default());
+
+        // Only the edited token is scanned again
+        let start = source.find("a;").unwrap();
+        source.replace_range(start..start + 1, "abc");
+        let change = scan.edit(&source, start..start + 1, 3);
+        assert_eq!(
+            change,
+            TokenChange {
+                start: 8,
+                removed: 1,
+                inserted: 1
+            }
+        );
+
+        let fresh = Scan::new(&source, Default::default());
+        assert_eq!(scan.tokens, fresh.tokens);
+        assert_eq!(scan.comments, fresh.comments);
+
+        // An edit opening a string changes everything after it
+        source.insert(0, '"');
+        let change = scan.edit(&source, 0..0, 1);
+        assert_eq!(change.start, 0);
+        let fresh = Scan::new(&source, Default::default());
+        assert_eq!(scan.tokens, fresh.tokens);
+        assert_eq!(format!("{:?}", scan.errors), format!("{:?}", fresh.errors));
+    }
+
+    #[test]
+    fn rescan_inside_interpolation() {
+        let mut source = r#"let s = "a ${f("b ${c}")} d"; let t = 1;"#.to_string();
+        let mut scan = Scan::new(&source, Default::default());
+
+        // Scanning restarts before the outermost string
+        let start = source.find('c').unwrap();
+        source.replace_range(start..start + 1, "} e ${c");
+        let change = scan.edit(&source, start..start + 1, 7);
+        assert_eq!(change.start, 3);
+
+        let fresh = Scan::new(&source, Default::default());
+        assert_eq!(scan.tokens, fresh.tokens);
+        assert!(scan.errors.is_empty(), "{:?}", scan.errors);
+    }
+
+    #[test]
+    fn lazy_scanning() {
+        let mut scanner = Scanner::new("let a = 1; \"open");
+
+        // Errors are only found once scanning reaches them
+        let first: Vec<TokenType> = scanner.by_ref().take(2).map(|t| t.token_type).collect();
+        assert_eq!(
+            first,
+            vec![TokenType::Let, TokenType::Identifier("a".into())]
+        );
+        assert!(scanner.errors.is_empty());
+
+        assert_eq!(scanner.by_ref().last().unwrap().token_type, TokenType::Eof);
+        assert_eq!(scanner.errors.len(), 1);
+        assert!(scanner.next().is_none());
+    }
 }
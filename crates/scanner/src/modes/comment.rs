@@ -1,30 +1,47 @@
+use apl_error::lexerror::LexErrorType;
+
 use crate::tokens::Token;
 
-use super::super::Scanner;
+use super::super::{Scanner, ScannerMode};
 
 impl<'a> Scanner<'a> {
-    pub(crate) fn scan_block_comment(&mut self) -> Option<Token> {
+    // Block comments nest, `/* a /* b */ c */` is a single comment
+    pub(crate) fn scan_block_comment(&mut self) -> Option<Token<'a>> {
+        let mut depth = 1;
         while let Some(c) = self.advance() {
-            match c {
-                '*' => {
-                    if let Some('/') = self.peek() {
-                        self.advance();
+            match (c, self.peek()) {
+                ('/', Some('*')) => {
+                    self.advance();
+                    depth += 1;
+                }
+                ('*', Some('/')) => {
+                    self.advance();
+                    depth -= 1;
+                    if depth == 0 {
                         break;
                     }
                 }
                 _ => {}
             }
         }
-        None
+
+        if depth > 0 {
+            self.record_error_at_start(LexErrorType::UnterminatedBlockComment, "/*".len());
+        }
+        self.end_comment()
     }
 
-    pub(crate) fn scan_line_comment(&mut self) -> Option<Token> {
-        while let Some(c) = self.advance() {
-            match c {
-                '\n' => break,
-                _ => {}
-            }
+    pub(crate) fn scan_line_comment(&mut self) -> Option<Token<'a>> {
+        // The newline isn't part of the comment
+        while self.peek().is_some_and(|c| c != '\n') {
+            self.advance();
         }
+        self.end_comment()
+    }
+
+    fn end_comment(&mut self) -> Option<Token<'a>> {
+        self.comments.push(self.current_span());
+        self.set_scanner_mode(ScannerMode::Normal);
         None
     }
 }
@@ -5,7 +5,7 @@ use crate::{match_operator, simple_token, tokens::{Token, TokenType}, transition
 use super::super::{Scanner, ScannerMode};
 
 impl<'a> Scanner<'a> {
-    pub(crate) fn scan_normal_mode(&mut self) -> Option<Token> {
+    pub(crate) fn scan_normal_mode(&mut self) -> Option<Token<'a>> {
         self.skip_whitespace();
         if self.is_at_end() {
             return None;
@@ -15,20 +15,35 @@ impl<'a> Scanner<'a> {
         let c = self.advance().unwrap();
 
         match c {
+            // Switches mode to RawString after `r"` or `r#"`
+            'r' if self.raw_string_hashes().is_some() => {
+                let hashes = self.raw_string_hashes().unwrap();
+                for _ in 0..=hashes {
+                    self.advance();
+                }
+                self.set_scanner_mode(ScannerMode::RawString(hashes));
+                None
+            },
+
             // Scans keywords and identifiers
-            c if c.is_alphabetic() => {
+            c if c.is_alphabetic() || c == '_' => {
                 self.scan_identifier();
                 let token_type = self.identify_keyword();
                 Some(self.end_token(token_type))
             },
 
             // Scans numbers floating point and integers
-            c if c.is_digit(10) => {
-                self.scan_number();
-                Some(self.end_token(TokenType::Number(self.current_lexeme.clone())))
+            c if c.is_ascii_digit() => {
+                let value = self.scan_number();
+                Some(self.end_token(TokenType::Number(value)))
             },
 
-            // Switches mode to StringLiteral or CharLiteral
+            // Switches mode to MultilineString, StringLiteral or CharLiteral
+            '"' if self.rest().starts_with("\"\"") => {
+                self.advance();
+                self.advance();
+                transition_mode!(self, MultilineString)
+            },
             '"' => transition_mode!(self, StringLiteral),
             '\'' => transition_mode!(self, CharLiteral),
 
@@ -40,8 +55,24 @@ impl<'a> Scanner<'a> {
             ';' => simple_token!(self, SemiColon),
             '.' => match_operator!(self, '.', '.', Range, Dot),
             ',' => simple_token!(self, Comma),
-            '{' => simple_token!(self, CurlyOpen),
-            '}' => simple_token!(self, CurlyClose),
+            ':' => simple_token!(self, Colon),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                simple_token!(self, CurlyOpen)
+            },
+            // Closes an interpolation, going back to the rest of its string
+            '}' if self.interpolations.last() == Some(&0) => {
+                self.interpolations.pop();
+                transition_mode!(self, StringLiteral)
+            },
+            '}' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth -= 1;
+                }
+                simple_token!(self, CurlyClose)
+            },
             '[' => simple_token!(self, BracketOpen),
             ']' => simple_token!(self, BracketClose),
             '(' => simple_token!(self, ParenthesesOpen),
@@ -75,4 +106,12 @@ impl<'a> Scanner<'a> {
             }
         }
     }
+
+    // The number of `#` between an `r` and the quote of a raw string, `None`
+    // if the `r` doesn't start one
+    fn raw_string_hashes(&self) -> Option<usize> {
+        let rest = self.rest();
+        let hashes = rest.bytes().take_while(|&b| b == b'#').count();
+        (rest.as_bytes().get(hashes) == Some(&b'"')).then_some(hashes)
+    }
 }
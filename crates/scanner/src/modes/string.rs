@@ -1,13 +1,20 @@
 use apl_error::lexerror::LexErrorType;
 
-use crate::tokens::{Token, TokenType};
+use crate::tokens::{Text, Token, TokenType};
 
 use super::super::{Scanner, ScannerMode};
 
 impl<'a> Scanner<'a> {
-    pub(crate) fn scan_string_literal(&mut self) -> Option<Token> {
-        let mut value = String::new();
+    // Scans the text of a string up to its closing quote or the `${` of an
+    // interpolation, the text after an interpolation starting at its `}`
+    pub(crate) fn scan_string_literal(&mut self) -> Option<Token<'a>> {
+        let source = self.source;
+        let start = self.position.offset;
+        let continued = source.as_bytes()[start - 1] == b'}';
+        // Only allocated once an escape makes the value differ from the source
+        let mut value: Option<String> = None;
         let mut terminated = false;
+        let mut interpolation = false;
 
         while let Some(c) = self.advance() {
             match c {
@@ -15,64 +22,176 @@ impl<'a> Scanner<'a> {
                     terminated = true;
                     break;
                 }
+                '$' if self.peek() == Some('{') => {
+                    self.advance();
+                    interpolation = true;
+                    break;
+                }
                 '\\' => {
-                    if let Some(escaped) = self.advance() {
-                        value.push(match escaped {
-                            'n' => '\n',
-                            't' => '\t',
-                            '"' => '"',
-                            '\\' => '\\',
-                            _ => {
-                                self.record_error(LexErrorType::InvalidEscape(escaped));
-                                escaped
-                            }
-                        })
+                    let backslash = self.position.offset - 1;
+                    let value = value.get_or_insert_with(|| source[start..backslash].to_string());
+                    if let Some(escaped) = self.scan_escape() {
+                        value.push(escaped);
                     } else {
                         self.record_error(LexErrorType::UnterminatedString);
                         break;
                     }
                 }
-                _ => value.push(c),
+                _ => {
+                    if let Some(value) = &mut value {
+                        value.push(c);
+                    }
+                }
+            }
+        }
+
+        if interpolation {
+            self.interpolations.push(0);
+        } else if !terminated {
+            self.record_error(LexErrorType::UnterminatedString);
+        }
+
+        self.set_scanner_mode(ScannerMode::Normal);
+
+        let closing = if interpolation {
+            "${".len()
+        } else {
+            usize::from(terminated)
+        };
+        let end = self.position.offset - closing;
+        let value = match value {
+            Some(value) => Text::Owned(value.into()),
+            None => Text::Lexeme(self.lexeme_range(start..end)),
+        };
+        let token_type = match (continued, interpolation) {
+            (false, false) => TokenType::String(value),
+            (false, true) => TokenType::StringStart(value),
+            (true, true) => TokenType::StringMiddle(value),
+            (true, false) => TokenType::StringEnd(value),
+        };
+        Some(self.end_token(token_type))
+    }
+
+    // A raw string's value is its source, escapes are left as they are
+    pub(crate) fn scan_raw_string(&mut self, hashes: usize) -> Option<Token<'a>> {
+        let start = self.position.offset;
+        let mut end = None;
+
+        while let Some(c) = self.advance() {
+            if c == '"' && self.rest().bytes().take_while(|&b| b == b'#').count() >= hashes {
+                end = Some(self.position.offset - 1);
+                for _ in 0..hashes {
+                    self.advance();
+                }
+                break;
+            }
+        }
+
+        if end.is_none() {
+            self.record_error(LexErrorType::UnterminatedString);
+        }
+
+        self.set_scanner_mode(ScannerMode::Normal);
+
+        let end = end.unwrap_or(self.position.offset);
+        let text = Text::Lexeme(self.lexeme_range(start..end));
+        Some(self.end_token(TokenType::String(text)))
+    }
+
+    // A multi-line string drops the line break after its opening quotes and
+    // the line of its closing quotes when they are on their own, and strips
+    // the indentation its lines have in common, closing line included.
+    // Escapes are processed after the indentation is stripped, and a `\` at
+    // the end of a line joins it to the next without its indentation.
+    pub(crate) fn scan_multiline_string(&mut self) -> Option<Token<'a>> {
+        let body = self.rest();
+        let start = self.position.offset;
+        let closing = closing_quotes(body);
+        let body = &body[..closing.unwrap_or(body.len())];
+        let indent = common_indent(body);
+
+        // Where the value ends, before the closing line if it's blank
+        let mut content_end = start + body.len();
+        if let Some(newline) = body.rfind('\n')
+            && is_blank(&body[newline + 1..])
+        {
+            content_end = start + body[..newline].trim_end_matches('\r').len();
+        }
+
+        if body.starts_with("\r\n") {
+            self.advance();
+        }
+        if body.starts_with('\n') || body.starts_with("\r\n") {
+            self.advance();
+        }
+
+        let mut value = String::new();
+        let mut line_start = self.position.offset > start;
+        while self.position.offset < content_end {
+            if line_start {
+                line_start = false;
+                let mut skipped = 0;
+                while skipped < indent && matches!(self.peek(), Some(' ' | '\t')) {
+                    self.advance();
+                    skipped += 1;
+                }
+                continue;
+            }
+
+            match self.advance() {
+                Some('\\') if self.rest().trim_start_matches('\r').starts_with('\n') => {
+                    self.scan_type(|c| c == '\r');
+                    self.advance();
+                    self.scan_type(|c| matches!(c, ' ' | '\t'));
+                }
+                Some('\\') => match self.scan_escape() {
+                    Some(escaped) => value.push(escaped),
+                    None => break,
+                },
+                Some('\n') => {
+                    value.push('\n');
+                    line_start = true;
+                }
+                Some(c) => value.push(c),
+                None => break,
             }
         }
 
-        if !terminated {
+        // Skip the closing line and the quotes
+        while self.position.offset < start + body.len() {
+            self.advance();
+        }
+        if closing.is_some() {
+            for _ in 0..3 {
+                self.advance();
+            }
+        } else {
             self.record_error(LexErrorType::UnterminatedString);
         }
 
         self.set_scanner_mode(ScannerMode::Normal);
 
-        Some(self.end_token(TokenType::String(value)))
+        Some(self.end_token(TokenType::String(Text::Owned(value.into()))))
     }
 
-    pub(crate) fn scan_char_literal(&mut self) -> Option<Token> {
-        self.advance(); // Skip opening quote
+    pub(crate) fn scan_char_literal(&mut self) -> Option<Token<'a>> {
         let value = match self.advance() {
             Some('\'') => {
                 // Empty char literal ''
                 self.record_error(LexErrorType::EmptyCharLiteral);
                 '\0' // Default value for error case
             }
-            Some('\\') => {
-                // Handle escape sequences
-                match self.advance() {
-                    Some('n') => '\n',
-                    Some('t') => '\t',
-                    Some('\\') => '\\',
-                    Some('\'') => '\'',
-                    Some(escaped) => {
-                        self.record_error(LexErrorType::InvalidEscape(escaped));
-                        escaped // Still use the character despite the error
-                    }
-                    None => {
-                        self.record_error(LexErrorType::UnterminatedChar);
-                        '\0'
-                    }
+            Some('\\') => match self.scan_escape() {
+                Some(escaped) => escaped,
+                None => {
+                    self.record_error(LexErrorType::UnterminatedChar);
+                    '\0'
                 }
-            }
+            },
             Some(c) => c,
             None => {
                 self.record_error(LexErrorType::UnterminatedChar);
+                self.set_scanner_mode(ScannerMode::Normal);
                 return None;
             }
         };
@@ -94,6 +213,128 @@ impl<'a> Scanner<'a> {
             }
         }
 
+        self.set_scanner_mode(ScannerMode::Normal);
+
         Some(self.end_token(TokenType::Char(value)))
     }
+
+    /// Scans an escape sequence after its `\\`, returning the character it
+    /// stands for, or `None` at the end of the input. Invalid escapes are
+    /// recorded with the span of the escape and stand for the escaped
+    /// character, or U+FFFD when there isn't one.
+    pub(crate) fn scan_escape(&mut self) -> Option<char> {
+        let start = self.position.offset - 1;
+        let escaped = match self.advance()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            '$' => '$',
+            'x' => self.scan_hex_escape(start),
+            'u' => self.scan_unicode_escape(start),
+            other => {
+                self.record_error_from(LexErrorType::InvalidEscape(other), start);
+                other
+            }
+        };
+        Some(escaped)
+    }
+
+    // `\xNN`, exactly two hex digits
+    fn scan_hex_escape(&mut self, start: usize) -> char {
+        let mut value = 0;
+        for _ in 0..2 {
+            let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) else {
+                self.record_error_from(LexErrorType::InvalidHexEscape, start);
+                return char::REPLACEMENT_CHARACTER;
+            };
+            self.advance();
+            value = value * 16 + digit;
+        }
+        char::from_u32(value).expect("two hex digits are a valid char")
+    }
+
+    // `\u{N}`, one to six hex digits naming a code point that isn't a
+    // surrogate
+    fn scan_unicode_escape(&mut self, start: usize) -> char {
+        let invalid = |scanner: &mut Self, error_type| {
+            scanner.record_error_from(error_type, start);
+            char::REPLACEMENT_CHARACTER
+        };
+
+        if self.peek() != Some('{') {
+            return invalid(self, LexErrorType::InvalidUnicodeEscape);
+        }
+        self.advance();
+
+        let mut value: u32 = 0;
+        let mut digits = 0;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+            self.advance();
+            value = value.saturating_mul(16).saturating_add(digit);
+            digits += 1;
+        }
+        if digits == 0 || digits > 6 || self.peek() != Some('}') {
+            return invalid(self, LexErrorType::InvalidUnicodeEscape);
+        }
+        self.advance();
+
+        match char::from_u32(value) {
+            Some(c) => c,
+            None if (0xD800..=0xDFFF).contains(&value) => {
+                invalid(self, LexErrorType::SurrogateCodePoint(value))
+            }
+            None => invalid(self, LexErrorType::CodePointOutOfRange(value)),
+        }
+    }
+}
+
+// The offset of the `"""` closing a multi-line string's `body`, skipping
+// escaped quotes
+fn closing_quotes(body: &str) -> Option<usize> {
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' if body[i..].starts_with("\"\"\"") => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+// The indentation shared by the lines of a multi-line string's `body` after
+// the opening line, ignoring blank lines other than the closing one and lines
+// continuing the one before
+fn common_indent(body: &str) -> usize {
+    let mut lines = body.split('\n').peekable();
+    let mut continued = is_continued(lines.next().unwrap_or_default());
+    let mut indent = None;
+    while let Some(line) = lines.next() {
+        let line = line.trim_end_matches('\r');
+        let is_closing = lines.peek().is_none();
+        let skip = continued || (is_blank(line) && !is_closing);
+        continued = is_continued(line);
+        if skip {
+            continue;
+        }
+        let width = line.chars().take_while(|c| matches!(c, ' ' | '\t')).count();
+        indent = Some(indent.map_or(width, |indent: usize| indent.min(width)));
+    }
+    indent.unwrap_or(0)
+}
+
+// Whether a line ends with a `\` that isn't itself escaped
+fn is_continued(line: &str) -> bool {
+    let line = line.trim_end_matches('\r');
+    (line.len() - line.trim_end_matches('\\').len()) % 2 == 1
+}
+
+fn is_blank(line: &str) -> bool {
+    line.chars().all(|c| matches!(c, ' ' | '\t' | '\r'))
 }
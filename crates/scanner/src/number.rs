@@ -0,0 +1,122 @@
+use apl_error::lexerror::LexErrorType;
+use num_bigint::BigUint;
+
+use super::{Number, Scanner};
+
+impl Scanner<'_> {
+    /// Scans the rest of a number literal after its first digit: `0x`, `0o`
+    /// and `0b` integers, decimal integers and floats with an optional
+    /// exponent, or exact decimals like `1.10d`. Digits may be separated by
+    /// `_`. Invalid literals are
+    /// recorded as errors and have the value 0.
+    pub(crate) fn scan_number(&mut self) -> Number {
+        let radix = match (self.lexeme(), self.peek()) {
+            ("0", Some('x' | 'X')) => 16,
+            ("0", Some('o' | 'O')) => 8,
+            ("0", Some('b' | 'B')) => 2,
+            _ => return self.scan_decimal(),
+        };
+        self.advance();
+
+        // Letters are part of the literal, so `0xFG` is an invalid digit
+        // rather than a number followed by a name
+        self.scan_type(|c| c.is_alphanumeric() || c == '_');
+        let digits = self.lexeme()[2..].replace('_', "");
+        if let Some(digit) = digits.chars().find(|c| !c.is_digit(radix)) {
+            self.record_error(LexErrorType::InvalidDigit(digit, radix));
+            Number::Integer(0)
+        } else if digits.is_empty() {
+            self.record_error(LexErrorType::MalformedNumber);
+            Number::Integer(0)
+        } else {
+            self.integer(&digits, radix)
+        }
+    }
+
+    fn scan_decimal(&mut self) -> Number {
+        self.scan_type(|c| c.is_ascii_digit() || c == '_');
+
+        // A `.` only belongs to the literal when a digit follows it, so `1..3`
+        // stays a range; a lone trailing `.` is still taken to report it
+        while self.peek() == Some('.') {
+            match self.rest()[1..].chars().next() {
+                Some(c) if c.is_ascii_digit() => {
+                    self.advance();
+                    self.scan_type(|c| c.is_ascii_digit() || c == '_');
+                }
+                Some(c) if c == '.' || c.is_alphabetic() || c == '_' => break,
+                _ => {
+                    self.advance();
+                    break;
+                }
+            }
+        }
+
+        let mantissa = self.lexeme();
+        let is_float = mantissa.contains('.');
+        if mantissa.matches('.').count() > 1 {
+            self.record_error(LexErrorType::TooManyDecimalPoints);
+        } else if mantissa.ends_with('.') {
+            self.record_error(LexErrorType::TrailingDecimalPoint);
+        }
+
+        let has_exponent = matches!(self.peek(), Some('e' | 'E'));
+        if has_exponent {
+            self.advance();
+            if let Some('+' | '-') = self.peek() {
+                self.advance();
+            }
+
+            let start = self.lexeme().len();
+            self.scan_type(|c| c.is_ascii_digit() || c == '_');
+            if !self.lexeme()[start..].contains(|c: char| c.is_ascii_digit()) {
+                self.record_error(LexErrorType::EmptyExponent);
+            }
+        }
+
+        if self.peek() == Some('d') {
+            self.advance();
+            return self.decimal(has_exponent);
+        }
+
+        let text = self.lexeme().replace('_', "");
+        if is_float || has_exponent {
+            // Errors for what doesn't parse were recorded above
+            Number::Float(text.parse().unwrap_or_default())
+        } else {
+            self.integer(&text, 10)
+        }
+    }
+
+    // A literal with the `d` suffix, which can't have an exponent
+    fn decimal(&mut self, has_exponent: bool) -> Number {
+        let text = self.lexeme().replace('_', "");
+        let text = &text[..text.len() - 1];
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if has_exponent {
+            self.record_error(LexErrorType::MalformedNumber);
+        }
+
+        // Errors for what doesn't parse were recorded above
+        match BigUint::parse_bytes(format!("{}{}", whole, fraction).as_bytes(), 10) {
+            Some(mantissa) if !has_exponent => Number::Decimal {
+                mantissa,
+                scale: fraction.len() as u32,
+            },
+            _ => Number::Decimal {
+                mantissa: BigUint::ZERO,
+                scale: 0,
+            },
+        }
+    }
+
+    // `digits` are valid in `radix`
+    fn integer(&mut self, digits: &str, radix: u32) -> Number {
+        match u64::from_str_radix(digits, radix) {
+            Ok(value) => Number::Integer(value),
+            Err(_) => Number::BigInteger(
+                BigUint::parse_bytes(digits.as_bytes(), radix).expect("digits are valid"),
+            ),
+        }
+    }
+}
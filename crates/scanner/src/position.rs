@@ -1,11 +1,17 @@
 pub(crate) struct Position {
     pub line: usize,
     pub column: usize,
+    // Byte offset into the source
+    pub offset: usize,
 }
 
 impl Position {
     pub fn new() -> Self {
-        Self { line: 1, column: 1 }
+        Self {
+            line: 1,
+            column: 1,
+            offset: 0,
+        }
     }
 
     pub fn increment_line(&mut self) {
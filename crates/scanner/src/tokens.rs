@@ -1,9 +1,24 @@
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+use apl_source::{Span, Symbol};
+use num_bigint::BigUint;
+
+/// The kind of a token. Names are interned, numbers hold their value, and
+/// strings point at their text in the lexeme unless an escape made their
+/// value differ from it. An interpolated string is split into the text
+/// around its `${...}` expressions, `"a ${b} c ${d} e"` scanning to
+/// `StringStart("a ")`, `b`, `StringMiddle(" c ")`, `d`, `StringEnd(" e")`.
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
     Let,
-    Identifier(String),
-    Number(String),
-    String(String),
+    Identifier(Symbol),
+    Number(Number),
+    String(Text),
+    StringStart(Text),
+    StringMiddle(Text),
+    StringEnd(Text),
     Char(char),
     Equals,
     EqualsEquals,
@@ -19,9 +34,11 @@ pub enum TokenType {
     GreaterThan,
     GreaterThanOrEqual,
     If,
+    Else,
     For,
     While,
     Loop,
+    In,
     Range,
     CurlyOpen,
     CurlyClose,
@@ -31,15 +48,153 @@ pub enum TokenType {
     ParenthesesClose,
     SemiColon,
     Dot,
+    Colon,
     Comma,
     Function,
+    Pub,
+    Import,
+    As,
+    Use,
+    Return,
+    Break,
+    Continue,
     Eof,
 }
 
+/// The value of a number literal, whatever base or notation it was written
+/// in. Integers that don't fit in 64 bits are `BigInteger`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Number {
+    Integer(u64),
+    BigInteger(BigUint),
+    Float(f64),
+    // `mantissa / 10^scale`, written with a `d` suffix
+    Decimal { mantissa: BigUint, scale: u32 },
+}
+
+/// The text of a string token. Text that is the same as in the source is
+/// kept as its range in the token's lexeme, only text that escapes made
+/// differ from the source is stored.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Text {
+    Lexeme(Range<usize>),
+    Owned(Rc<str>),
+}
+
+impl From<&str> for Text {
+    fn from(text: &str) -> Self {
+        Text::Owned(text.into())
+    }
+}
+
+/// A token, with its lexeme borrowed from the source it was scanned from.
 #[derive(Debug, PartialEq, Clone)]
-pub struct Token {
+pub struct Token<'a> {
+    pub span: Span,
     pub token_type: TokenType,
-    pub lexeme: String,
+    pub lexeme: &'a str,
     pub line: usize,
     pub column: usize,
-}
\ No newline at end of file
+    // Only filled in by `Scanner::scan_tokens_with_trivia`
+    pub leading_trivia: Vec<Trivia<'a>>,
+    pub trailing_trivia: Vec<Trivia<'a>>,
+}
+
+impl Token<'_> {
+    /// The token's lexeme surrounded by its trivia, concatenating this for
+    /// every token of a lossless scan reproduces the source.
+    pub fn full_text(&self) -> String {
+        let mut text = String::new();
+        for trivia in &self.leading_trivia {
+            text.push_str(trivia.text);
+        }
+        text.push_str(self.lexeme);
+        for trivia in &self.trailing_trivia {
+            text.push_str(trivia.text);
+        }
+        text
+    }
+
+    /// The text of a string token, or of a part of an interpolated one.
+    pub fn text(&self) -> Option<&str> {
+        match &self.token_type {
+            TokenType::String(text)
+            | TokenType::StringStart(text)
+            | TokenType::StringMiddle(text)
+            | TokenType::StringEnd(text) => Some(match text {
+                Text::Lexeme(range) => &self.lexeme[range.clone()],
+                Text::Owned(text) => text,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A token kept without its lexeme and trivia, so keeping it doesn't borrow
+/// or copy the source. [`ScannedToken::token`] slices the lexeme from the
+/// source again.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScannedToken {
+    pub span: Span,
+    pub token_type: TokenType,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ScannedToken {
+    /// The token, with its lexeme borrowed from `source`, which must be the
+    /// source it was scanned from.
+    pub fn token<'a>(&self, source: &'a str) -> Token<'a> {
+        Token {
+            span: self.span,
+            token_type: self.token_type.clone(),
+            lexeme: &source[self.span.start..self.span.end],
+            line: self.line,
+            column: self.column,
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
+        }
+    }
+}
+
+/// The token's type as `Debug` shows it, except that a string token shows
+/// its text rather than where the text is kept.
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.token_type {
+            TokenType::String(_) => "String",
+            TokenType::StringStart(_) => "StringStart",
+            TokenType::StringMiddle(_) => "StringMiddle",
+            TokenType::StringEnd(_) => "StringEnd",
+            _ => return write!(f, "{:?}", self.token_type),
+        };
+        write!(f, "{}({:?})", name, self.text().unwrap_or_default())
+    }
+}
+
+impl From<Token<'_>> for ScannedToken {
+    fn from(token: Token) -> Self {
+        Self {
+            span: token.span,
+            token_type: token.token_type,
+            line: token.line,
+            column: token.column,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+    // Characters that didn't make a token, reported as lex errors
+    Skipped,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia<'a> {
+    pub kind: TriviaKind,
+    pub span: Span,
+    pub text: &'a str,
+}
@@ -0,0 +1,91 @@
+use apl_source::Span;
+use std::iter::Peekable;
+
+use super::{Scanner, Token, Trivia, TriviaKind};
+
+impl<'a> Scanner<'a> {
+    /// Scans like `scan_tokens`, but keeps everything in between tokens as
+    /// trivia. Trivia on the same line as a token trails it, the rest leads the
+    /// next token, so the source ends up in exactly one token's `full_text`.
+    pub fn scan_tokens_with_trivia(&mut self) -> Vec<Token<'a>> {
+        let mut tokens = self.scan_tokens();
+        let mut comments = self.comments.iter().copied().peekable();
+
+        let mut gap_start = 0;
+        for i in 0..tokens.len() {
+            let mut leading = self.trivia_between(gap_start, tokens[i].span.start, &mut comments);
+
+            if i > 0 {
+                let line_end = leading
+                    .iter()
+                    .position(|trivia| trivia.text.contains('\n'))
+                    .unwrap_or(leading.len());
+                let rest = leading.split_off(line_end);
+                tokens[i - 1].trailing_trivia = leading;
+                leading = rest;
+            }
+
+            tokens[i].leading_trivia = leading;
+            gap_start = tokens[i].span.end;
+        }
+
+        tokens
+    }
+
+    // Splits the source between two tokens into comments, whitespace and
+    // skipped characters. Whitespace is split before its first newline so the
+    // part on a token's own line can trail it.
+    fn trivia_between<I>(
+        &self,
+        start: usize,
+        end: usize,
+        comments: &mut Peekable<I>,
+    ) -> Vec<Trivia<'a>>
+    where
+        I: Iterator<Item = Span>,
+    {
+        let mut trivia = Vec::new();
+        let mut offset = start;
+
+        while offset < end {
+            let (kind, piece_end) = match comments.peek() {
+                Some(comment) if comment.start == offset => {
+                    let kind = if self.source[offset..].starts_with("//") {
+                        TriviaKind::LineComment
+                    } else {
+                        TriviaKind::BlockComment
+                    };
+                    (kind, comments.next().unwrap().end)
+                }
+                next => {
+                    let limit = next.map_or(end, |comment| comment.start.min(end));
+                    let rest = &self.source[offset..limit];
+
+                    let (kind, len) = if rest.starts_with('\n') {
+                        (
+                            TriviaKind::Whitespace,
+                            rest.find(|c: char| !c.is_whitespace()),
+                        )
+                    } else if rest.starts_with(char::is_whitespace) {
+                        (
+                            TriviaKind::Whitespace,
+                            rest.find(|c: char| c == '\n' || !c.is_whitespace()),
+                        )
+                    } else {
+                        (TriviaKind::Skipped, rest.find(char::is_whitespace))
+                    };
+                    (kind, offset + len.unwrap_or(rest.len()))
+                }
+            };
+
+            trivia.push(Trivia {
+                kind,
+                span: Span::new(self.file, offset, piece_end),
+                text: &self.source[offset..piece_end],
+            });
+            offset = piece_end;
+        }
+
+        trivia
+    }
+}
@@ -0,0 +1,144 @@
+use std::cell::OnceCell;
+use std::ops::Range;
+
+use super::{FileId, Location, Span};
+
+pub struct SourceFile {
+    name: String,
+    source: String,
+    // Byte offset of the start of every line, computed on first lookup
+    line_starts: OnceCell<Vec<usize>>,
+}
+
+impl SourceFile {
+    fn new(name: String, source: String) -> Self {
+        Self {
+            name,
+            source,
+            line_starts: OnceCell::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn line_starts(&self) -> &[usize] {
+        self.line_starts.get_or_init(|| {
+            std::iter::once(0)
+                .chain(self.source.match_indices('\n').map(|(i, _)| i + 1))
+                .collect()
+        })
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts().len()
+    }
+
+    /// Maps a byte offset to its line and column, offsets past the end map to
+    /// the end of the file.
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = offset.min(self.source.len());
+        let line_starts = self.line_starts();
+        let line = line_starts.partition_point(|&start| start <= offset) - 1;
+
+        let column = self.source[line_starts[line]..]
+            .char_indices()
+            .take_while(|(i, _)| line_starts[line] + i < offset)
+            .count()
+            + 1;
+
+        Location {
+            line: line + 1,
+            column,
+        }
+    }
+
+    /// Maps a line and column back to a byte offset, the end of the line for
+    /// columns past it. `None` for lines that don't exist.
+    pub fn offset(&self, location: Location) -> Option<usize> {
+        let line = self.line_text(location.line)?;
+        let start = self.line_starts()[location.line - 1];
+        let column = line
+            .char_indices()
+            .nth(location.column.saturating_sub(1))
+            .map_or(line.len(), |(i, _)| i);
+
+        Some(start + column)
+    }
+
+    /// The text of a 1-based line without its line terminator.
+    pub fn line_text(&self, line: usize) -> Option<&str> {
+        let line_starts = self.line_starts();
+        let start = *line_starts.get(line.checked_sub(1)?)?;
+        let end = line_starts.get(line).copied().unwrap_or(self.source.len());
+
+        Some(self.source[start..end].trim_end_matches(['\n', '\r']))
+    }
+}
+
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self { files: Vec::new() }
+    }
+
+    pub fn add_file(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+        self.files.push(SourceFile::new(name.into(), source.into()));
+        id
+    }
+
+    /// Replaces `range` of a file's text with `text`, e.g. after an edit in
+    /// an editor.
+    pub fn edit(&mut self, id: FileId, range: Range<usize>, text: &str) {
+        let file = &mut self.files[id.0 as usize];
+        file.source.replace_range(range, text);
+        file.line_starts = OnceCell::new();
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    pub fn source(&self, id: FileId) -> &str {
+        self.file(id).source()
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (FileId(i as u32), file))
+    }
+
+    pub fn location(&self, span: Span) -> Location {
+        self.file(span.file).location(span.start)
+    }
+
+    /// The source line containing the start of `span`.
+    pub fn snippet(&self, span: Span) -> &str {
+        let file = self.file(span.file);
+        file.line_text(file.location(span.start).line)
+            .unwrap_or_default()
+    }
+
+    /// Formats the start of `span` as `file.apl:3:7`.
+    pub fn format_location(&self, span: Span) -> String {
+        let location = self.location(span);
+        format!(
+            "{}:{}:{}",
+            self.file(span.file).name(),
+            location.line,
+            location.column
+        )
+    }
+}
@@ -0,0 +1,104 @@
+mod core;
+mod span;
+mod symbol;
+
+pub use core::{SourceFile, SourceMap};
+pub use span::{FileId, Location, Span};
+pub use symbol::Symbol;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locations() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("main.apl", "let a = 1;\nlet b = a;\n");
+
+        assert_eq!(
+            map.location(Span::new(file, 0, 3)),
+            Location { line: 1, column: 1 }
+        );
+        assert_eq!(
+            map.location(Span::new(file, 15, 16)),
+            Location { line: 2, column: 5 }
+        );
+        // The offset of a newline belongs to the line it terminates
+        assert_eq!(
+            map.location(Span::new(file, 10, 11)),
+            Location {
+                line: 1,
+                column: 11
+            }
+        );
+        assert_eq!(map.file(file).line_count(), 3);
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("utf8.apl", "let s = \"æøå\"; x");
+
+        // `x` starts at byte 19 but is the 17th character
+        assert_eq!(
+            map.location(Span::new(file, 19, 20)),
+            Location {
+                line: 1,
+                column: 17
+            }
+        );
+        assert_eq!(
+            map.file(file).offset(Location {
+                line: 1,
+                column: 17
+            }),
+            Some(19)
+        );
+        assert_eq!(map.file(file).offset(Location { line: 2, column: 1 }), None);
+    }
+
+    #[test]
+    fn multiple_files() {
+        let mut map = SourceMap::new();
+        let main = map.add_file("main.apl", "import \"lib.apl\" as l;");
+        let lib = map.add_file("lib.apl", "fn f() {}\r\npub let x = @;\n");
+
+        assert_ne!(main, lib);
+        assert_eq!(map.format_location(Span::new(lib, 23, 24)), "lib.apl:2:13");
+        assert_eq!(map.snippet(Span::new(lib, 23, 24)), "pub let x = @;");
+        assert_eq!(map.format_location(Span::new(main, 7, 16)), "main.apl:1:8");
+        assert_eq!(
+            map.snippet(Span::new(main, 7, 16)),
+            "import \"lib.apl\" as l;"
+        );
+
+        // Edits replace the text and the line table
+        map.edit(lib, 0..11, "");
+        assert_eq!(map.source(lib), "pub let x = @;\n");
+        assert_eq!(map.format_location(Span::new(lib, 12, 13)), "lib.apl:1:13");
+    }
+
+    #[test]
+    fn symbols() {
+        let a = Symbol::intern("alpha");
+        assert_eq!(a, Symbol::from("alpha".to_string()));
+        assert_ne!(a, Symbol::intern("beta"));
+        assert_eq!(a.as_str(), "alpha");
+        assert_eq!(a, "alpha");
+        assert_eq!(Symbol::default().as_str(), "");
+        assert_eq!(format!("{} {:?}", a, a), "alpha \"alpha\"");
+    }
+
+    #[test]
+    fn symbols_across_segments_and_threads() {
+        let names: Vec<String> = (0..1000).map(|i| format!("name{}", i)).collect();
+        let symbols: Vec<Symbol> = names.iter().map(Symbol::from).collect();
+        assert_eq!(Symbol::intern(""), Symbol::default());
+
+        let read = std::thread::spawn(move || {
+            let names: Vec<&str> = symbols.iter().map(|s| s.as_str()).collect();
+            names
+        });
+        assert_eq!(read.join().unwrap(), names);
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, Mutex, OnceLock};
+
+/// An interned name. Symbols for the same name are equal, so comparing and
+/// hashing them doesn't look at the name at all.
+///
+/// Names are interned for the whole process and never freed, which keeps
+/// `as_str` free of lifetimes and of locks. The cost is that every distinct
+/// name stays allocated until the process exits, including the partial names
+/// a long-running language server sees while one is typed. That is bounded by
+/// the number of distinct names rather than by how often they are scanned, so
+/// it stays small next to the documents themselves. The default symbol is the
+/// empty name.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Symbol(u32);
+
+// Names by symbol, in segments of 1, 2, 4, ... slots so a segment never moves
+// once allocated and reading one only takes atomic loads. Only interning
+// locks, to look up the name and fill the next slot
+const SEGMENTS: usize = u32::BITS as usize;
+
+type Segment = Box<[OnceLock<&'static str>]>;
+
+static NAMES: [OnceLock<Segment>; SEGMENTS] = [const { OnceLock::new() }; SEGMENTS];
+
+static SYMBOLS: LazyLock<Mutex<HashMap<&'static str, Symbol>>> =
+    LazyLock::new(|| Mutex::new(HashMap::from([("", Symbol(0))])));
+
+// The segment and the slot in it of the name of symbol `index`
+fn slot(index: u32) -> (usize, usize) {
+    let position = index as u64 + 1;
+    let segment = position.ilog2();
+    (segment as usize, (position - (1 << segment)) as usize)
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Self {
+        let mut symbols = SYMBOLS.lock().unwrap();
+        if let Some(&symbol) = symbols.get(name) {
+            return symbol;
+        }
+
+        let name: &'static str = Box::leak(name.into());
+        let symbol = Symbol(symbols.len() as u32);
+        let (segment, slot) = slot(symbol.0);
+        let segment = NAMES[segment]
+            .get_or_init(|| (0..1usize << segment).map(|_| OnceLock::new()).collect());
+        segment[slot].set(name).expect("each symbol is named once");
+        symbols.insert(name, symbol);
+        symbol
+    }
+
+    pub fn as_str(self) -> &'static str {
+        // The empty name is the default symbol, which has no slot
+        if self.0 == 0 {
+            return "";
+        }
+        let (segment, slot) = slot(self.0);
+        NAMES[segment]
+            .get()
+            .and_then(|names| names[slot].get())
+            .expect("symbols are named when interned")
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(name: &String) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Self::intern(&name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
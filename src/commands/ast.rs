@@ -0,0 +1,43 @@
+use std::process::ExitCode;
+
+use apl_error::Diagnostic;
+use apl_parser::core::Parser;
+use apl_scanner::Scanner;
+use apl_source::SourceMap;
+
+use super::{Input, report};
+
+pub fn ast(input: &Input) -> ExitCode {
+    match input.read() {
+        Ok(source) => print_ast(input.name(), source),
+        Err(e) => report(&e.diagnostics(), &SourceMap::new()),
+    }
+}
+
+/// Pretty-prints the syntax tree of `source`. Imports are not followed.
+pub(super) fn print_ast(name: String, source: String) -> ExitCode {
+    if source.is_empty() {
+        println!("[]");
+        return ExitCode::SUCCESS;
+    }
+
+    let mut source_map = SourceMap::new();
+    let file = source_map.add_file(name, source);
+    let mut scanner = Scanner::with_file(source_map.source(file), file);
+    let result = Parser::new(&mut scanner).try_parse();
+    // Errors past where parsing stopped are still reported first
+    scanner.by_ref().for_each(drop);
+
+    if !scanner.errors.is_empty() {
+        let diagnostics: Vec<Diagnostic> = scanner.errors.iter().map(Diagnostic::from).collect();
+        return report(&diagnostics, &source_map);
+    }
+
+    match result {
+        Ok(statements) => {
+            println!("{:#?}", statements);
+            ExitCode::SUCCESS
+        }
+        Err(error) => report(&[Diagnostic::from(&error)], &source_map),
+    }
+}
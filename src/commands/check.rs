@@ -0,0 +1,39 @@
+use std::process::ExitCode;
+
+use apl_error::Diagnostic;
+use apl_interpreter::Interpreter;
+use apl_loader::ModuleLoader;
+use apl_resolver::{Globals, Resolve};
+
+use super::{Input, load, report};
+
+/// Scans, parses and resolves the input and its imports without running it.
+pub fn check(input: &Input) -> ExitCode {
+    let mut loader = ModuleLoader::new();
+    match analyze(&mut loader, input) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(diagnostics) => report(&diagnostics, loader.source_map()),
+    }
+}
+
+/// Loads and resolves the input, collecting every error found.
+pub(super) fn analyze(loader: &mut ModuleLoader, input: &Input) -> Result<(), Vec<Diagnostic>> {
+    load(loader, input).map_err(|e| e.diagnostics())?;
+
+    let globals = Interpreter::global_names();
+    let globals = globals.iter().map(|name| name.to_string()).collect();
+    loader.database_mut().set::<Globals>((), globals);
+
+    let database = loader.database();
+    let diagnostics: Vec<Diagnostic> = loader
+        .modules()
+        .flat_map(|module| database.get::<Resolve>(&module.file).errors.clone())
+        .map(|error| Diagnostic::from(&error))
+        .collect();
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(diagnostics)
+    }
+}
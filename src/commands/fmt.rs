@@ -0,0 +1,74 @@
+use std::process::ExitCode;
+
+use apl_formatter::format_source;
+use apl_source::SourceMap;
+
+use super::{Input, report};
+
+/// Formats each file in place, or stdin to stdout. With `check` nothing is
+/// written, and the command fails if any input isn't already formatted.
+pub fn fmt(paths: &[&str], check: bool) -> ExitCode {
+    let inputs: Vec<Input> = if paths.is_empty() {
+        vec![Input::Stdin]
+    } else {
+        paths.iter().map(|path| Input::new(Some(path))).collect()
+    };
+
+    let mut success = true;
+    for input in &inputs {
+        success &= format_input(input, check);
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn format_input(input: &Input, check: bool) -> bool {
+    let mut source_map = SourceMap::new();
+    let source = match input.read() {
+        Ok(source) => source,
+        Err(e) => {
+            report(&e.diagnostics(), &source_map);
+            return false;
+        }
+    };
+
+    let file = source_map.add_file(input.name(), source);
+    let source = source_map.source(file);
+    let formatted = match format_source(source, file) {
+        Ok(formatted) => formatted,
+        Err(diagnostics) => {
+            report(&diagnostics, &source_map);
+            return false;
+        }
+    };
+
+    let unchanged = formatted == source;
+    match input {
+        _ if check => {
+            if !unchanged {
+                println!("{} is not formatted", input.name());
+            }
+            unchanged
+        }
+        Input::Stdin => {
+            print!("{}", formatted);
+            true
+        }
+        Input::File(path) => {
+            if unchanged {
+                return true;
+            }
+            match std::fs::write(path, formatted) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("error: could not write {}: {}", path.display(), e);
+                    false
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::process::ExitCode;
+
+use apl_scanner::highlight;
+use apl_source::SourceMap;
+
+use super::{Input, report};
+
+/// Prints the input as HTML, each token wrapped in a `<span>` classed by its
+/// category so a stylesheet can color it.
+pub fn highlight_html(input: &Input) -> ExitCode {
+    match input.read() {
+        Ok(source) => {
+            println!("{}", html(&source));
+            ExitCode::SUCCESS
+        }
+        Err(e) => report(&e.diagnostics(), &SourceMap::new()),
+    }
+}
+
+fn html(source: &str) -> String {
+    let mut out = String::from("<pre class=\"apl\"><code>");
+    let mut last_end = 0;
+
+    for (span, category) in highlight(source) {
+        out.push_str(&escape(&source[last_end..span.start]));
+        out.push_str(&format!(
+            "<span class=\"{}\">{}</span>",
+            category.as_str(),
+            escape(&source[span.start..span.end])
+        ));
+        last_end = span.end;
+    }
+    out.push_str(&escape(&source[last_end..]));
+
+    out.push_str("</code></pre>");
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
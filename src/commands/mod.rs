@@ -0,0 +1,108 @@
+mod ast;
+mod check;
+mod fmt;
+mod highlight;
+mod rename;
+mod repl;
+mod run;
+mod tokens;
+
+pub use ast::ast;
+pub use check::check;
+pub use fmt::fmt;
+pub use highlight::highlight_html;
+pub use rename::rename;
+pub use repl::repl;
+pub use run::run;
+pub use tokens::tokens;
+
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use apl_error::{Diagnostic, ModuleError, Renderer, codes};
+use apl_loader::ModuleLoader;
+use apl_source::SourceMap;
+
+/// Where a command reads its program from.
+pub enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    /// A missing path or `-` reads stdin.
+    pub fn new(path: Option<&str>) -> Self {
+        match path {
+            None | Some("-") => Input::Stdin,
+            Some(path) => Input::File(PathBuf::from(path)),
+        }
+    }
+
+    pub(super) fn name(&self) -> String {
+        match self {
+            Input::Stdin => "<stdin>".to_string(),
+            Input::File(path) => path.display().to_string(),
+        }
+    }
+
+    fn read(&self) -> Result<String, ModuleError> {
+        let result = match self {
+            Input::Stdin => {
+                let mut source = String::new();
+                std::io::stdin().read_to_string(&mut source).map(|_| source)
+            }
+            Input::File(path) => std::fs::read_to_string(path),
+        };
+
+        result.map_err(|e| ModuleError::Io {
+            path: PathBuf::from(self.name()),
+            message: e.to_string(),
+        })
+    }
+}
+
+/// Loads the input and every module it imports.
+fn load(loader: &mut ModuleLoader, input: &Input) -> Result<(), ModuleError> {
+    match input {
+        Input::File(path) => loader.load(path)?,
+        Input::Stdin => loader.load_reader(&input.name(), std::io::stdin().lock())?,
+    };
+    Ok(())
+}
+
+/// Prints diagnostics to stderr and returns the failure exit code.
+fn report(diagnostics: &[Diagnostic], source_map: &SourceMap) -> ExitCode {
+    // Colors only when writing to a terminal, see https://no-color.org
+    let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    let renderer = Renderer::new(color);
+
+    eprint!("{}", renderer.render_all(diagnostics, source_map));
+    if let Some(code) = diagnostics.iter().find_map(|d| d.code.as_ref()) {
+        eprintln!(
+            "\nFor more information about this error, try `apl explain {}`.",
+            code
+        );
+    }
+
+    ExitCode::FAILURE
+}
+
+pub fn explain(code: &str) -> ExitCode {
+    match codes::lookup(code) {
+        Some(error_code) => {
+            print!(
+                "{} ({})\n\n{}",
+                error_code.code, error_code.name, error_code.explanation
+            );
+            ExitCode::SUCCESS
+        }
+        None => {
+            eprintln!(
+                "error: no explanation for '{}', codes look like E0001",
+                code
+            );
+            ExitCode::FAILURE
+        }
+    }
+}
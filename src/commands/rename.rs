@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::ExitCode;
+
+use apl_error::Diagnostic;
+use apl_loader::ModuleLoader;
+use apl_refactor::{Edit, apply};
+use apl_source::Location;
+
+use super::report;
+
+/// Renames what is declared or used at `position`, a `line:column` in
+/// `path`, everywhere in the file and the modules it imports. Changed files
+/// are rewritten in place.
+pub fn rename(path: &str, position: &str, new_name: &str) -> ExitCode {
+    let Some(location) = parse_location(position) else {
+        eprintln!(
+            "error: expected a position like 12:5 (line and column), found '{}'",
+            position
+        );
+        return ExitCode::FAILURE;
+    };
+
+    let mut loader = ModuleLoader::new();
+    let file = match loader.load(Path::new(path)) {
+        Ok(module) => module.file,
+        Err(e) => return report(&e.diagnostics(), loader.source_map()),
+    };
+    let Some(offset) = loader.source_map().file(file).offset(location) else {
+        eprintln!("error: {} has no line {}", path, location.line);
+        return ExitCode::FAILURE;
+    };
+
+    let edits = match apl_refactor::rename(&loader, file, offset, new_name) {
+        Ok(edits) => edits,
+        Err(e) => return report(&[Diagnostic::from(&e)], loader.source_map()),
+    };
+
+    let mut files: BTreeMap<_, Vec<Edit>> = BTreeMap::new();
+    for edit in edits {
+        files.entry(edit.span.file).or_default().push(edit);
+    }
+
+    let mut success = true;
+    for (file, edits) in files {
+        let source_file = loader.source_map().file(file);
+        let renamed = apply(source_file.source(), &edits);
+        if let Err(e) = std::fs::write(source_file.name(), renamed) {
+            eprintln!("error: could not write {}: {}", source_file.name(), e);
+            success = false;
+        }
+    }
+
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn parse_location(position: &str) -> Option<Location> {
+    let (line, column) = position.split_once(':')?;
+    Some(Location {
+        line: line.parse().ok()?,
+        column: column.parse().ok()?,
+    })
+}
@@ -0,0 +1,268 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use apl_error::Diagnostic;
+use apl_interpreter::{Interpreter, Value};
+use apl_loader::{Module, ModuleLoader};
+use apl_parser::ast::Stmt;
+use apl_resolver::{Globals, Modules, Resolve};
+use apl_scanner::is_incomplete;
+use apl_source::Symbol;
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+
+use super::ast::print_ast;
+use super::report;
+use super::tokens::print_tokens;
+
+// Kept in the current directory, so each project has its own history
+const HISTORY_FILE: &str = ".apl_history";
+
+const HELP: &str = "\
+Enter statements or expressions, the values of expressions are printed.
+Input continues on the next line while a bracket or string is left open.
+
+Commands:
+    :tokens <code>    Print the tokens of some code
+    :ast <code>       Print the syntax tree of some code
+    :type <expr>      Print the type of an expression
+    :help             Show this message
+    :quit             Exit, as does Ctrl-D
+";
+
+pub fn repl() -> ExitCode {
+    Repl::new().run()
+}
+
+struct Repl {
+    // Each input is loaded as a module, so it can import files. What earlier
+    // inputs declared is in scope when the database resolves the next one
+    loader: ModuleLoader,
+    interpreter: Interpreter,
+    inputs: usize,
+}
+
+impl Repl {
+    fn new() -> Self {
+        let mut loader = ModuleLoader::new();
+        let globals = Interpreter::global_names();
+        let database = loader.database_mut();
+        database.set::<Globals>((), globals.iter().map(|name| name.to_string()).collect());
+        database.set::<Modules>((), Vec::new());
+
+        Self {
+            loader,
+            interpreter: Interpreter::new(),
+            inputs: 0,
+        }
+    }
+
+    fn run(&mut self) -> ExitCode {
+        let mut editor = match DefaultEditor::new() {
+            Ok(editor) => editor,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        // There is no history the first time
+        let _ = editor.load_history(HISTORY_FILE);
+
+        println!("apl {}, type :help for help", env!("CARGO_PKG_VERSION"));
+
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.is_empty() { ">> " } else { ".. " };
+
+            match editor.readline(prompt) {
+                Ok(line) => {
+                    buffer.push_str(&line);
+                    buffer.push('\n');
+                    if is_incomplete(&buffer) {
+                        continue;
+                    }
+
+                    let input = std::mem::take(&mut buffer);
+                    let input = input.trim();
+                    if input.is_empty() {
+                        continue;
+                    }
+
+                    let _ = editor.add_history_entry(input);
+                    if !self.input(input) {
+                        break;
+                    }
+                }
+                // Ctrl-C discards the current input
+                Err(ReadlineError::Interrupted) => buffer.clear(),
+                Err(ReadlineError::Eof) => break,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    break;
+                }
+            }
+        }
+
+        if let Err(e) = editor.save_history(HISTORY_FILE) {
+            eprintln!("warning: could not save history: {}", e);
+        }
+        ExitCode::SUCCESS
+    }
+
+    // Returns false when the user asks to quit
+    fn input(&mut self, input: &str) -> bool {
+        let Some(command) = input.strip_prefix(':') else {
+            self.eval(input, false);
+            return true;
+        };
+
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .unwrap_or((command, ""));
+        let argument = argument.trim().to_string();
+
+        match name {
+            "q" | "quit" => return false,
+            "help" => print!("{}", HELP),
+            "tokens" => {
+                print_tokens("<repl>".to_string(), argument);
+            }
+            "ast" => {
+                print_ast("<repl>".to_string(), terminated(&argument));
+            }
+            "type" => self.eval(&argument, true),
+            _ => eprintln!("error: unknown command ':{}', try :help", name),
+        }
+        true
+    }
+
+    /// Runs `input` in the session's environment, printing the value of each
+    /// expression statement, or only the type of `input` if `type_only` is set.
+    fn eval(&mut self, input: &str, type_only: bool) {
+        let Some(path) = self.load(input) else {
+            return;
+        };
+        let module = self
+            .loader
+            .module(&path)
+            .expect("the input was just loaded");
+
+        let statements = match (type_only, module.statements.as_slice()) {
+            (false, statements) => statements,
+            (true, [statement @ Stmt::Expression(_)]) => std::slice::from_ref(statement),
+            (true, _) => {
+                eprintln!("error: :type expects a single expression");
+                return;
+            }
+        };
+
+        let errors = &self.loader.database().get::<Resolve>(&module.file).errors;
+        if !errors.is_empty() {
+            let diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+            report(&diagnostics, self.loader.source_map());
+            return;
+        }
+        let (names, modules) = declarations(&self.loader, module);
+
+        // Functions are hoisted as they are in a module
+        let (functions, statements): (Vec<&Stmt>, Vec<&Stmt>) = statements
+            .iter()
+            .partition(|stmt| matches!(stmt, Stmt::FunctionDecl(_)));
+        let functions: Vec<Stmt> = functions.into_iter().cloned().collect();
+
+        let mut result = self
+            .interpreter
+            .import(&self.loader, module)
+            .and_then(|_| self.interpreter.execute(&functions));
+
+        for stmt in statements {
+            if result.is_err() {
+                break;
+            }
+
+            result = match stmt {
+                Stmt::Expression(expr) => self.interpreter.evaluate(expr).map(|value| {
+                    if type_only {
+                        println!("{}", value.type_name());
+                    } else if !matches!(value, Value::Unit) {
+                        println!("{}", value.repr());
+                    }
+                }),
+                _ => self.interpreter.execute(std::slice::from_ref(stmt)),
+            };
+        }
+
+        if let Err(error) = result {
+            report(&[Diagnostic::from(&error)], self.loader.source_map());
+        }
+        self.declare(names, modules);
+    }
+
+    /// Brings what an input declared into scope for the ones after it. A
+    /// value shadows a module imported under the same name.
+    fn declare(&mut self, names: Vec<Symbol>, modules: Vec<(Symbol, Vec<Symbol>)>) {
+        let database = self.loader.database_mut();
+        database.update::<Globals>(&(), |globals| {
+            for name in &names {
+                if !globals.iter().any(|global| global == name.as_str()) {
+                    globals.push(name.to_string());
+                }
+            }
+        });
+        database.update::<Modules>(&(), |session| {
+            session.retain(|(alias, _)| {
+                !names.contains(alias) && !modules.iter().any(|(module, _)| module == alias)
+            });
+            session.extend(modules);
+        });
+    }
+
+    /// Loads `input` as a module, reporting any errors in it or its imports.
+    fn load(&mut self, input: &str) -> Option<PathBuf> {
+        self.inputs += 1;
+        let name = format!("<repl:{}>", self.inputs);
+
+        match self.loader.load_source(&name, terminated(input)) {
+            Ok(module) => Some(module.path.clone()),
+            Err(e) => {
+                report(&e.diagnostics(), self.loader.source_map());
+                None
+            }
+        }
+    }
+}
+
+// The names declared at the top of `module`, and the modules it imports with
+// what they export
+fn declarations(
+    loader: &ModuleLoader,
+    module: &Module,
+) -> (Vec<Symbol>, Vec<(Symbol, Vec<Symbol>)>) {
+    let mut names = Vec::new();
+    for stmt in &module.statements {
+        match stmt {
+            Stmt::FunctionDecl(function) => names.push(function.name.name),
+            Stmt::VariableDecl(decl) => {
+                names.extend(decl.pattern.bindings().into_iter().map(|name| name.name))
+            }
+            Stmt::Use(use_decl) => names.extend(use_decl.names.iter().map(|name| name.name)),
+            _ => {}
+        }
+    }
+
+    let modules = module
+        .imports
+        .iter()
+        .filter_map(|(alias, path)| Some((*alias, loader.module(path)?.exports())))
+        .collect();
+    (names, modules)
+}
+
+// `input` with the final `;` it can leave out, e.g. `1 + 2`
+fn terminated(input: &str) -> String {
+    let mut source = input.to_string();
+    if !source.ends_with(';') && !source.ends_with('}') {
+        source.push(';');
+    }
+    source
+}
@@ -0,0 +1,21 @@
+use std::process::ExitCode;
+
+use apl_error::Diagnostic;
+use apl_interpreter::Interpreter;
+use apl_loader::ModuleLoader;
+
+use super::check::analyze;
+use super::{Input, report};
+
+/// Checks the input and runs it if there are no errors.
+pub fn run(input: Input) -> ExitCode {
+    let mut loader = ModuleLoader::new();
+    if let Err(diagnostics) = analyze(&mut loader, &input) {
+        return report(&diagnostics, loader.source_map());
+    }
+
+    match Interpreter::new().run(&loader) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => report(&[Diagnostic::from(&error)], loader.source_map()),
+    }
+}
@@ -0,0 +1,37 @@
+use std::process::ExitCode;
+
+use apl_error::Diagnostic;
+use apl_scanner::Scanner;
+use apl_source::SourceMap;
+
+use super::{Input, report};
+
+pub fn tokens(input: &Input) -> ExitCode {
+    match input.read() {
+        Ok(source) => print_tokens(input.name(), source),
+        Err(e) => report(&e.diagnostics(), &SourceMap::new()),
+    }
+}
+
+/// Prints every token of `source` with the line and column it starts at.
+pub(super) fn print_tokens(name: String, source: String) -> ExitCode {
+    if source.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+
+    let mut source_map = SourceMap::new();
+    let file = source_map.add_file(name, source);
+    let mut scanner = Scanner::with_file(source_map.source(file), file);
+
+    for token in scanner.by_ref() {
+        let location = source_map.location(token.span);
+        println!("{:>4}:{:<4} {}", location.line, location.column, token);
+    }
+
+    if scanner.errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        let diagnostics: Vec<Diagnostic> = scanner.errors.iter().map(Diagnostic::from).collect();
+        report(&diagnostics, &source_map)
+    }
+}
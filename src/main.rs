@@ -1,10 +1,76 @@
-use apl_parser::{ast::Stmt, core::Parser};
-use apl_scanner::{Scanner, Token};
-
-fn main() {
-    let mut scanner = Scanner::new("let a = 10;");
-    let tokens: Vec<Token> = scanner.scan_tokens();
-    let mut parser = Parser::new(tokens);
-    let result: Vec<Stmt> = parser.parse();
-    println!("{:?}", result);
-}
\ No newline at end of file
+mod commands;
+
+use std::process::ExitCode;
+
+use apl_interpreter::STACK_SIZE;
+use commands::Input;
+
+const USAGE: &str = "\
+Usage: apl <command> [file]
+
+Commands:
+    run [file]        Run a program and the modules it imports
+    check [file]      Report errors without running the program
+    tokens [file]     Print the tokens of a file
+    ast [file]        Print the syntax tree of a file
+    fmt [files]       Format files in place, `--check` only reports
+                      files that aren't formatted
+    highlight --html [file]
+                      Print a file as syntax highlighted HTML
+    rename <file> <line:column> <name>
+                      Rename the variable or function at a position,
+                      along with every use of it
+    repl              Start an interactive session
+    explain <code>    Explain an error code, e.g. `apl explain E0001`
+
+Programs are read from stdin when no file (or `-`) is given.
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Every command parses, and deeply nested code would overflow the main
+    // thread's stack, so they all get the stack the interpreter needs
+    let thread = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || command(&args))
+        .expect("failed to spawn the command thread");
+
+    thread.join().unwrap_or(ExitCode::FAILURE)
+}
+
+fn command(args: &[String]) -> ExitCode {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["explain", code] => commands::explain(code),
+        ["run", rest @ ..] if rest.len() <= 1 => commands::run(Input::new(rest.first().copied())),
+        ["check", rest @ ..] if rest.len() <= 1 => {
+            commands::check(&Input::new(rest.first().copied()))
+        }
+        ["tokens", rest @ ..] if rest.len() <= 1 => {
+            commands::tokens(&Input::new(rest.first().copied()))
+        }
+        ["ast", rest @ ..] if rest.len() <= 1 => commands::ast(&Input::new(rest.first().copied())),
+        ["fmt", rest @ ..] => {
+            let check = rest.contains(&"--check");
+            let paths: Vec<&str> = rest.iter().copied().filter(|&a| a != "--check").collect();
+            commands::fmt(&paths, check)
+        }
+        ["highlight", "--html", rest @ ..] if rest.len() <= 1 => {
+            commands::highlight_html(&Input::new(rest.first().copied()))
+        }
+        ["rename", path, position, new_name] => commands::rename(path, position, new_name),
+        ["repl"] => commands::repl(),
+        ["help" | "--help" | "-h"] => {
+            print!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        // `apl main.apl` is short for `apl run main.apl`
+        [path] if path.ends_with(".apl") => commands::run(Input::new(Some(path))),
+        _ => {
+            eprint!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}